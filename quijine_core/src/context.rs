@@ -2,10 +2,10 @@ use crate::{
     atom::Atom,
     class::ClassId,
     convert::{AsJsClassId, AsJsValue, AsMutPtr, AsPtr},
-    enums::CFunctionEnum,
+    enums::{CFunctionEnum, PromiseState},
     ffi::{self, c_size_t},
     flags::{EvalFlags, ParseJsonFlags, ReadObjFlags, WriteObjFlags},
-    function::{convert_function_arguments, convert_function_result},
+    function::{catch_panic, convert_function_arguments, convert_function_result},
     internal::{c_int_as_i32, ref_sized_to_slice},
     marker::Covariant,
     module::ModuleDef,
@@ -23,6 +23,7 @@ use std::{
     os::raw::{c_char, c_int},
     ptr::{null_mut, NonNull},
     slice,
+    sync::atomic::{AtomicU32, Ordering},
 };
 
 macro_rules! def_throw_error {
@@ -173,6 +174,12 @@ impl<'q> Context<'q> {
         unsafe { ffi::JS_ResetUncatchableError(self.0.as_ptr()) }
     }
 
+    /// Makes an error object uncatchable by `try`/`catch` like the error of an interruption.
+    #[inline]
+    pub fn set_uncatchable_error(self, val: Value<'q>, flag: bool) {
+        unsafe { ffi::JS_SetUncatchableError(self.0.as_ptr(), val.as_js_value(), flag as i32) }
+    }
+
     #[inline]
     pub fn new_error(self) -> Value<'q> {
         unsafe {
@@ -338,6 +345,24 @@ impl<'q> Context<'q> {
         unsafe { Value::from_raw(value, self) }
     }
 
+    #[inline]
+    pub fn call_constructor<F, A>(self, func_obj: F, args: &[A]) -> Value<'q>
+    where
+        F: AsJsValue<'q>,
+        A: AsJsValue<'q>,
+    {
+        let mut c_args: Vec<_> = args.as_ref().iter().map(|v| v.as_js_value()).collect();
+        let value = unsafe {
+            ffi::JS_CallConstructor(
+                self.0.as_ptr(),
+                func_obj.as_js_value(),
+                c_args.len() as i32,
+                c_args.as_mut_ptr(),
+            )
+        };
+        unsafe { Value::from_raw(value, self) }
+    }
+
     #[inline]
     pub fn eval(self, code: &str, filename: &str, eval_flags: EvalFlags) -> Value<'q> {
         let c_code = CString::new(code).expect("code");
@@ -488,7 +513,7 @@ impl<'q> Context<'q> {
     }
 
     #[inline]
-    fn new_callback(self, mut func: Box<Callback<'q, 'q>>, length: i32) -> Value<'q> {
+    fn new_callback(self, func: Box<Callback<'q, 'q>>, length: i32) -> Value<'q> {
         unsafe extern "C" fn call(
             ctx: *mut ffi::JSContext,
             js_this: ffi::JSValue,
@@ -500,17 +525,23 @@ impl<'q> Context<'q> {
             let (ctx, this, args) = convert_function_arguments(ctx, js_this, argc, argv);
             let cb = Value::from_raw(*func_data, ctx);
             log::trace!("load pointer from ArrayBuffer");
-            let func = cb.array_buffer_as_ref::<*mut Callback>(ctx).unwrap();
-            let any = (**func)(ctx, this, args.as_slice());
+            let func = cb.array_buffer_as_ref::<Box<Callback>>(ctx).unwrap();
+            let any = match catch_panic(|| func(ctx, this, args.as_slice())) {
+                Ok(any) => any,
+                Err(message) => ctx.throw_internal_error(&message),
+            };
             convert_function_result(any)
         }
         log::trace!("save pointer to ArrayBuffer");
-        let cb = self.new_array_buffer_copy_from_ref(&func.as_mut());
+        // the ArrayBuffer owns the closure, which is dropped when the function is freed
+        let cb = self.new_array_buffer_from_boxed(Box::new(func));
         if cb.is_exception() {
             return cb;
         }
         log::trace!("new c function data");
-        self.new_c_function_data(Some(call), length, 0, &[cb])
+        let f = self.new_c_function_data(Some(call), length, 0, &[cb]);
+        unsafe { self.free_value(cb) };
+        f
     }
 
     #[inline]
@@ -689,6 +720,45 @@ impl<'q> Context<'q> {
         }
     }
 
+    /// Returns the state of `promise`, or `None` if it is not a promise.
+    #[inline]
+    pub fn promise_state(self, promise: Value<'q>) -> Option<PromiseState> {
+        let data = self.promise_data(promise)?;
+        Some(match unsafe { (*data).promise_state } {
+            1 => PromiseState::Fulfilled,
+            2 => PromiseState::Rejected,
+            _ => PromiseState::Pending,
+        })
+    }
+
+    /// Returns the value or the reason `promise` was settled with, or `None` if it is not a
+    /// promise. The value is not duplicated and is `undefined` while the promise is pending.
+    #[inline]
+    pub fn promise_result(self, promise: Value<'q>) -> Option<Value<'q>> {
+        let data = self.promise_data(promise)?;
+        Some(unsafe { Value::from_raw((*data).promise_result, self) })
+    }
+
+    fn promise_data(self, promise: Value<'q>) -> Option<*mut JSPromiseData> {
+        // JS_CLASS_PROMISE is not exported and its value depends on CONFIG_BIGNUM, so find the
+        // only built-in class id whose opaque a fresh promise exposes.
+        static PROMISE_CLASS_ID: AtomicU32 = AtomicU32::new(0);
+        let mut class_id = PROMISE_CLASS_ID.load(Ordering::Relaxed);
+        if class_id == 0 {
+            let (probe, funcs) = self.new_promise_capability();
+            class_id = (1..JS_CLASS_INIT_COUNT_MAX)
+                .find(|&id| !probe.opaque(ClassId::from_raw(id)).is_null())
+                .expect("a promise has a built-in class");
+            unsafe {
+                self.free_value(probe);
+                funcs.into_iter().for_each(|f| self.free_value(f));
+            }
+            PROMISE_CLASS_ID.store(class_id, Ordering::Relaxed);
+        }
+        let data = promise.opaque(ClassId::from_raw(class_id)) as *mut JSPromiseData;
+        (!data.is_null()).then_some(data)
+    }
+
     /// return 0 if OK, < 0 if exception
     #[inline]
     pub fn enqueue_job(self, job_func: raw::JSJobFunc, args: &[Value]) -> i32 {
@@ -734,6 +804,38 @@ impl<'q> Context<'q> {
         }
     }
 
+    /// Returns the data pointers of SharedArrayBuffers in addition, so that they can be kept alive
+    /// until the object is read.
+    #[inline]
+    pub fn write_object_with_sab_tab(self, obj: Value, flags: WriteObjFlags) -> Option<(Vec<u8>, Vec<*mut u8>)> {
+        let mut size: c_size_t = 0;
+        let mut sab_tab: *mut *mut u8 = null_mut();
+        let mut sab_tab_len: c_size_t = 0;
+        unsafe {
+            let buf = ffi::JS_WriteObject2(
+                self.0.as_ptr(),
+                &mut size,
+                obj.as_js_value(),
+                flags.bits() as i32,
+                &mut sab_tab,
+                &mut sab_tab_len,
+            );
+            if buf.is_null() {
+                return None;
+            }
+            let vec = slice::from_raw_parts(buf, size as usize).to_vec();
+            ffi::js_free(self.0.as_ptr(), buf as *mut _);
+            let sabs = if sab_tab.is_null() {
+                Vec::new()
+            } else {
+                let sabs = slice::from_raw_parts(sab_tab, sab_tab_len as usize).to_vec();
+                ffi::js_free(self.0.as_ptr(), sab_tab as *mut _);
+                sabs
+            };
+            Some((vec, sabs))
+        }
+    }
+
     #[inline]
     pub fn read_object(self, buf: &[u8], flags: ReadObjFlags) -> Value<'q> {
         unsafe {
@@ -748,6 +850,18 @@ impl<'q> Context<'q> {
     }
 }
 
+/// Bounds the ids of the classes built into QuickJS (`JS_CLASS_INIT_COUNT`).
+const JS_CLASS_INIT_COUNT_MAX: u32 = 64;
+
+/// Mirrors `JSPromiseData` of quickjs.c.
+#[repr(C)]
+struct JSPromiseData {
+    promise_state: u32,
+    promise_reactions: [[*mut c_void; 2]; 2],
+    is_handled: c_int,
+    promise_result: ffi::JSValue,
+}
+
 impl fmt::Debug for Context<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         f.write_str(format!("Context({:p})", self.0).as_str())
@@ -41,3 +41,12 @@ pub enum ValueTag {
     Exception = ffi::JS_TAG_EXCEPTION,
     Float64 = ffi::JS_TAG_FLOAT64,
 }
+
+/// The state of a promise, mirroring `JSPromiseStateEnum` of quickjs.c.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum PromiseState {
+    Pending = 0,
+    Fulfilled = 1,
+    Rejected = 2,
+}
@@ -15,6 +15,12 @@ use crate::internal::i32_as_c_int;
 #[allow(non_camel_case_types)]
 pub type c_size_t = size_t;
 
+// exported by quickjs.c but not declared in quickjs.h
+
+extern "C" {
+    pub fn JS_SetUncatchableError(ctx: *mut JSContext, val: JSValue, flag: JS_BOOL);
+}
+
 // from C preprocessor macro
 
 #[cold]
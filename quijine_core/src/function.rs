@@ -1,5 +1,11 @@
 use crate::{convert::AsJsValue, ffi, raw, Context, PropFlags, Value};
-use std::{ffi::CStr, os::raw::c_int, slice};
+use std::{
+    any::Any,
+    ffi::CStr,
+    os::raw::c_int,
+    panic::{catch_unwind, AssertUnwindSafe},
+    slice,
+};
 
 /// This function is used by js_c_function macro.
 /// # Safety
@@ -25,6 +31,30 @@ pub fn convert_function_result(res: Value) -> ffi::JSValue {
     res.as_js_value()
 }
 
+/// This function is used by the macros of callbacks, since a panic must not unwind into C.
+/// A panic is returned as its message.
+pub fn catch_panic<R, F: FnOnce() -> R>(f: F) -> Result<R, String> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|payload| panic_message(payload.as_ref()))
+}
+
+/// This function is used by the macros of callbacks which cannot report an error, like finalizers.
+/// A panic is logged and ignored.
+pub fn ignore_panic<F: FnOnce()>(f: F) {
+    if let Err(message) = catch_panic(f) {
+        log::error!("panic in a callback: {}", message);
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_owned()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "panic with a non-string payload".to_owned()
+    }
+}
+
 #[derive(Clone)]
 #[repr(transparent)]
 pub struct CFunctionListEntry(ffi::JSCFunctionListEntry);
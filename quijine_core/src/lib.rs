@@ -24,10 +24,15 @@ pub use atom::{Atom, PropertyEnum};
 pub use class::{ClassDef, ClassId};
 pub use context::Context;
 pub use convert::{AsJsAtom, AsJsClassId, AsJsValue, AsMutPtr, AsPtr};
-pub use enums::ValueTag;
+pub use enums::{PromiseState, ValueTag};
 pub use flags::{EvalFlags, GpnFlags, PropFlags, ReadObjFlags, WriteObjFlags};
-pub use function::{convert_function_arguments, convert_function_result, CFunctionListEntry};
+pub use function::{
+    catch_panic, convert_function_arguments, convert_function_result, ignore_panic, CFunctionListEntry,
+};
 pub use module::ModuleDef;
 pub use runtime::Runtime;
 pub use string::CString;
 pub use value::{PropertyDescriptor, Value};
+
+/// The version of QuickJS bundled by libquickjs-sys. A test checks it against the linked library.
+pub const QUICKJS_VERSION: &str = "2021-03-27";
@@ -1,6 +1,7 @@
 #[macro_export]
 macro_rules! js_c_function {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'q>(
             ctx: *mut $crate::raw::JSContext,
             this_val: $crate::raw::JSValue,
@@ -9,7 +10,10 @@ macro_rules! js_c_function {
         ) -> $crate::raw::JSValue {
             let f: fn($crate::Context<'q>, $crate::Value<'q>, &[$crate::Value<'q>]) -> $crate::Value<'q> = $f;
             let (ctx, this, args) = $crate::convert_function_arguments::<'q>(ctx, this_val, argc, argv);
-            let ret = f(ctx, this, args.as_slice());
+            let ret = match $crate::catch_panic(|| f(ctx, this, args.as_slice())) {
+                Ok(ret) => ret,
+                Err(message) => ctx.throw_internal_error(&message),
+            };
             $crate::convert_function_result(ret)
         }
         Some(wrap)
@@ -19,11 +23,12 @@ macro_rules! js_c_function {
 #[macro_export]
 macro_rules! js_class_finalizer {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'r>(rt: *mut $crate::raw::JSRuntime, val: $crate::raw::JSValue) {
             let f: unsafe fn($crate::Runtime<'r>, $crate::Value<'r>) = $f;
             let rt = $crate::Runtime::from_raw(rt);
             let val = $crate::Value::from_raw_with_runtime(val, rt);
-            f(rt, val)
+            $crate::ignore_panic(|| f(rt, val))
         }
         Some(wrap)
     }};
@@ -32,6 +37,7 @@ macro_rules! js_class_finalizer {
 #[macro_export]
 macro_rules! js_class_gc_mark {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'r>(
             rt: *mut $crate::raw::JSRuntime,
             val: $crate::raw::JSValue,
@@ -40,7 +46,7 @@ macro_rules! js_class_gc_mark {
             let f: fn($crate::Runtime<'r>, $crate::Value<'r>, $crate::raw::JS_MarkFunc) = $f;
             let rt = $crate::Runtime::from_raw(rt);
             let val = $crate::Value::from_raw_with_runtime(val, rt);
-            f(rt, val, mark_func)
+            $crate::ignore_panic(|| f(rt, val, mark_func))
         }
         Some(wrap)
     }};
@@ -49,6 +55,7 @@ macro_rules! js_class_gc_mark {
 #[macro_export]
 macro_rules! js_class_call {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'q>(
             ctx: *mut $crate::raw::JSContext,
             func_obj: $crate::raw::JSValue,
@@ -66,7 +73,10 @@ macro_rules! js_class_call {
             ) -> $crate::Value<'q> = $f;
             let (ctx, this, args) = $crate::convert_function_arguments::<'q>(ctx, this_val, argc, argv);
             let func_obj = $crate::Value::from_raw(func_obj, ctx);
-            let ret = f(ctx, func_obj, this, args.as_slice(), flags);
+            let ret = match $crate::catch_panic(|| f(ctx, func_obj, this, args.as_slice(), flags)) {
+                Ok(ret) => ret,
+                Err(message) => ctx.throw_internal_error(&message),
+            };
             $crate::convert_function_result(ret)
         }
         Some(wrap)
@@ -77,6 +87,7 @@ macro_rules! js_class_call {
 #[doc(hidden)]
 macro_rules! js_module_init_func {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'q>(
             ctx: *mut $crate::raw::JSContext,
             m: *mut $crate::raw::JSModuleDef,
@@ -84,7 +95,13 @@ macro_rules! js_module_init_func {
             let f: fn($crate::Context<'q>, $crate::ModuleDef<'q>) -> i32 = $f;
             let ctx = $crate::Context::from_raw(ctx);
             let m = $crate::ModuleDef::from_raw(m, ctx);
-            f(ctx, m) as ::std::os::raw::c_int
+            match $crate::catch_panic(|| f(ctx, m)) {
+                Ok(ret) => ret as ::std::os::raw::c_int,
+                Err(message) => {
+                    ctx.throw_internal_error(&message);
+                    -1
+                }
+            }
         }
         Some(wrap)
     }};
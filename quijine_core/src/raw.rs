@@ -1,7 +1,7 @@
 pub use crate::ffi::{
-    JSCFunction, JSCFunctionData, JSCFunctionListEntry, JSCFunctionMagic, JSClassCall, JSClassDef,
+    c_size_t, JSCFunction, JSCFunctionData, JSCFunctionListEntry, JSCFunctionMagic, JSClassCall, JSClassDef,
     JSClassExoticMethods, JSClassFinalizer, JSClassGCMark, JSContext, JSFreeArrayBufferDataFunc, JSGCObjectHeader,
     JSHostPromiseRejectionTracker, JSInterruptHandler, JSJobFunc, JSMallocFunctions, JSMallocState, JSMemoryUsage,
     JSModuleDef, JSModuleInitFunc, JSModuleLoaderFunc, JSModuleNormalizeFunc, JSPropertyDescriptor, JSPropertyEnum,
-    JSRuntime, JSSharedArrayBufferFunctions, JSValue, JS_MarkFunc,
+    JSRuntime, JSSharedArrayBufferFunctions, JSValue, JS_MarkFunc, JS_CALL_FLAG_CONSTRUCTOR,
 };
@@ -227,3 +227,35 @@ impl<'q> AsMutPtr<ffi::JSRuntime> for Runtime<'q> {
         self.0.as_ptr()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{ffi, AsMutPtr, Runtime, QUICKJS_VERSION};
+    use std::ffi::c_void;
+
+    #[test]
+    fn quickjs_version() {
+        // the header of the memory usage dump contains `CONFIG_VERSION` of the linked QuickJS
+        let mut rt = Runtime::new();
+        let usage = rt.compute_memory_usage();
+        let mut buf = vec![0u8; 256];
+        let len = unsafe {
+            let fp = ffi::tmpfile();
+            assert!(!fp.is_null());
+            ffi::JS_DumpMemoryUsage(fp, &usage, rt.as_mut_ptr());
+            ffi::rewind(fp);
+            let len = ffi::fread(buf.as_mut_ptr() as *mut c_void, 1, buf.len() as _, fp);
+            ffi::fclose(fp);
+            Runtime::free(rt);
+            len
+        };
+        let dump = String::from_utf8_lossy(&buf[..len as usize]);
+        let header = dump.lines().next().unwrap_or_default();
+        assert!(
+            header.contains(&format!(" {} version,", QUICKJS_VERSION)),
+            "QUICKJS_VERSION is {} but the header is {:?}",
+            QUICKJS_VERSION,
+            header
+        );
+    }
+}
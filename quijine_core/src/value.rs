@@ -271,6 +271,37 @@ impl<'q> Value<'q> {
         }
     }
 
+    #[inline]
+    pub fn property_uint32(self, mut ctx: Context<'q>, idx: u32) -> Value<'q> {
+        unsafe {
+            let value = ffi::JS_GetPropertyUint32(ctx.as_mut_ptr(), self.0, idx);
+            Value::from_raw(value, ctx)
+        }
+    }
+
+    #[inline]
+    pub fn set_property_uint32<V>(self, mut ctx: Context<'q>, idx: u32, val: V) -> Result<bool, Error>
+    where
+        V: AsJsValue<'q>,
+    {
+        let ret = unsafe { ffi::JS_SetPropertyUint32(ctx.as_mut_ptr(), self.0, idx, val.as_js_value()) };
+        if ret == -1 {
+            Err(Error::HasException)
+        } else {
+            Ok(ret != 0)
+        }
+    }
+
+    #[inline]
+    pub fn delete_property(self, mut ctx: Context<'q>, prop: Atom<'q>, flags: PropFlags) -> Result<bool, Error> {
+        let ret = unsafe { ffi::JS_DeleteProperty(ctx.as_mut_ptr(), self.0, prop.as_js_atom(), flags.bits() as c_int) };
+        if ret == -1 {
+            Err(Error::HasException)
+        } else {
+            Ok(ret != 0)
+        }
+    }
+
     #[inline]
     pub fn has_property(self, mut ctx: Context<'q>, prop: Atom<'q>) -> Result<bool, Error> {
         let ret = unsafe { ffi::JS_HasProperty(ctx.as_mut_ptr(), self.0, prop.as_js_atom()) };
@@ -491,7 +522,7 @@ impl<'q> Value<'q> {
     }
 
     #[inline]
-    pub fn array_buffer(self, mut ctx: Context<'q>) -> Option<&[u8]> {
+    pub fn array_buffer(self, mut ctx: Context<'q>) -> Option<&'q [u8]> {
         let mut len = 0;
         let bs: *const u8 = unsafe { ffi::JS_GetArrayBuffer(ctx.as_mut_ptr(), &mut len, self.0) };
         if bs.is_null() {
@@ -520,7 +551,7 @@ impl<'q> Value<'q> {
     /// # Safety
     /// The content of ArrayBuffer must be created from `T`.
     #[inline]
-    pub unsafe fn array_buffer_as_ref<T>(self, ctx: Context<'q>) -> Option<&T> {
+    pub unsafe fn array_buffer_as_ref<T>(self, ctx: Context<'q>) -> Option<&'q T> {
         self.array_buffer(ctx).map(|v| ref_sized_from_bytes(v))
     }
 
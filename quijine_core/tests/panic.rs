@@ -0,0 +1,30 @@
+use quijine_core::{js_c_function, Context, EvalFlags, Runtime, Value};
+
+#[test]
+fn test() {
+    let rt = Runtime::new();
+    let ctx = Context::new(rt);
+    let global = ctx.global_object();
+    let boom = ctx.new_c_function(js_c_function!(|_ctx, _this, _args| panic!("boom")), "boom", 0);
+    global.set_property_str(ctx, "boom", boom).unwrap();
+    let message = "callback".to_owned();
+    let callback = ctx.new_function(move |_ctx, _this, _args| -> Value { panic!("{}", message) }, 0);
+    global.set_property_str(ctx, "callback", callback).unwrap();
+    let ret = ctx.eval(
+        r#"
+        const messages = [boom, callback].map((f) => {
+            try { f(); } catch (e) { return e instanceof InternalError && e.message; }
+        });
+        messages.join() === "boom,callback"
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    );
+    assert_eq!(Some(true), ret.to_bool(ctx));
+    unsafe {
+        ctx.free_value(ret);
+        ctx.free_value(global);
+        Context::free(ctx);
+        Runtime::free(rt);
+    }
+}
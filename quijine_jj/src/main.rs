@@ -1,7 +1,9 @@
 use anyhow::Result;
 use clap::Parser;
 use colored_json::ColoredFormatter;
-use quijine::{self, Context, EvalFlags, ExternalResult, FunctionBytecode, Result as QjResult, Value as QjValue};
+use quijine::{
+    self, CompiledScript, Context, EvalFlags, ExternalResult, FunctionBytecode, Result as QjResult, Value as QjValue,
+};
 use serde::Serialize;
 use serde_json::{
     ser::{CompactFormatter, PrettyFormatter},
@@ -11,6 +13,7 @@ use serde_quijine::to_qj;
 use std::{
     fs::{self, File},
     io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
     process::exit,
     sync::Arc,
 };
@@ -27,6 +30,10 @@ pub enum JjError {
 #[derive(Clone, Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct Opt {
+    /// Cache compiled scripts in the directory
+    #[clap(long, value_name = "DIR")]
+    cache_dir: Option<String>,
+
     /// Colorize JSON output
     #[clap(short = 'C', long)]
     color_output: bool,
@@ -86,7 +93,7 @@ type Handler<'q> = Box<dyn Fn(Context<'q>, QjValue<'q>, &[QjValue<'q>]) -> QjRes
 
 fn print<'q>(opt: &Opt, ctx: Context<'q>, _this: QjValue<'q>, args: &[QjValue<'q>]) -> QjResult<QjValue<'q>> {
     let ret = Ok(ctx.undefined().into());
-    let arg = args.get(0);
+    let arg = args.first();
     let arg = match arg {
         Some(v) => v,
         None => return ret,
@@ -216,8 +223,7 @@ fn process<'q, R: BufRead>(
     } else {
         let de = serde_json::Deserializer::from_reader(r);
         let stream = de.into_iter::<Value>();
-        let values: QjValue = ctx.new_array()?.into();
-        let array_push: QjValue = values.get("push")?;
+        let mut values = Vec::new();
         for (i, value) in stream.enumerate() {
             let value = match value {
                 Ok(v) => v,
@@ -228,7 +234,7 @@ fn process<'q, R: BufRead>(
             };
             let result = to_qj(ctx, value)?;
             if opt.slurp {
-                ctx.call(array_push.clone(), values.clone(), &[result])?;
+                values.push(result);
             } else {
                 args.i = i;
                 args.result = result;
@@ -237,7 +243,7 @@ fn process<'q, R: BufRead>(
         }
         if opt.slurp {
             args.i = 0;
-            args.result = values;
+            args.result = ctx.new_array_from(values)?.into();
             process_one(&args)?;
         }
     }
@@ -257,6 +263,46 @@ fn process_file<'q>(opt: Arc<Opt>, ctx: Context<'q>, bytecode: FunctionBytecode<
     process(opt, ctx, bytecode, buf, file)
 }
 
+fn cache_path(dir: &str, script: &str) -> PathBuf {
+    // FNV-1a is stable across Rust versions unlike DefaultHasher
+    let hash = script.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    Path::new(dir).join(format!("{:016x}-{}.qjbc", hash, script.len()))
+}
+
+fn load_cache<'q>(ctx: Context<'q>, path: &Path) -> Option<FunctionBytecode<'q>> {
+    let buf = fs::read(path).ok()?;
+    let loaded = CompiledScript::from_bytes(&buf)
+        .and_then(|compiled| compiled.load(ctx))
+        .and_then(|v| v.try_into());
+    match loaded {
+        Ok(bytecode) => Some(bytecode),
+        Err(e) => {
+            log::debug!("ignore the cache {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn compile<'q>(opt: &Opt, ctx: Context<'q>, script: &str) -> QjResult<FunctionBytecode<'q>> {
+    let path = opt.cache_dir.as_ref().map(|dir| cache_path(dir, script));
+    if let Some(bytecode) = path.as_ref().and_then(|path| load_cache(ctx, path)) {
+        return Ok(bytecode);
+    }
+    let compiled = ctx.compile(script, "<input>", EvalFlags::TYPE_GLOBAL)?;
+    if let Some(path) = path {
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, compiled.to_bytes()));
+        if let Err(e) = written {
+            log::warn!("can't write the cache {}: {}", path.display(), e);
+        }
+    }
+    compiled.load(ctx)?.try_into()
+}
+
 fn main() -> Result<()> {
     env_logger::init();
     #[cfg(windows)]
@@ -284,16 +330,10 @@ fn main() -> Result<()> {
     };
     quijine::context(move |ctx| {
         // check a syntax error
-        let bytecode: FunctionBytecode = ctx
-            .eval_into(
-                &script,
-                "<input>",
-                EvalFlags::TYPE_GLOBAL | EvalFlags::FLAG_COMPILE_ONLY,
-            )
-            .unwrap_or_else(|e| {
-                eprint!("{}", e);
-                exit(3)
-            });
+        let bytecode = compile(&opt, ctx, &script).unwrap_or_else(|e| {
+            eprint!("{}", e);
+            exit(3)
+        });
         // read stdin
         if files.is_empty() {
             process_stdin(opt, ctx, bytecode)?;
@@ -370,7 +370,7 @@ struct ObjectAccess<'q> {
     pos: usize,
 }
 
-fn str_deserializer(s: &str) -> de::value::StrDeserializer<Error> {
+fn str_deserializer(s: &str) -> de::value::StrDeserializer<'_, Error> {
     de::IntoDeserializer::into_deserializer(s)
 }
 
@@ -415,7 +415,7 @@ impl<'de> de::SeqAccess<'de> for SeqAccess<'_> {
         self.pos += 1;
 
         if pos < self.len {
-            let value = self.object.get(pos).unwrap();
+            let value = self.object.get_index(pos as u32)?;
             let mut deserializer = Deserializer::new(value);
             Ok(Some(seed.deserialize(&mut deserializer)?))
         } else {
@@ -99,9 +99,9 @@ impl<'q> ser::Serializer for Serializer<'q> {
         self.serialize_unit()
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(self)
     }
@@ -123,14 +123,14 @@ impl<'q> ser::Serializer for Serializer<'q> {
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -138,7 +138,7 @@ impl<'q> ser::Serializer for Serializer<'q> {
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         let ctx = self.context;
         let x = self.serialize_newtype_struct(variant, value)?;
@@ -220,11 +220,7 @@ impl<'q> ser::SerializeSeq for ArraySerializer<'q> {
     }
 
     fn end(self) -> Result<Self::Ok> {
-        let arr = self.context.new_array()?;
-        arr.set("length", self.pending.len() as i32)?;
-        for (i, elem) in self.pending.iter().enumerate() {
-            arr.set(i as i32, elem.clone())?;
-        }
+        let arr = self.context.new_array_from(self.pending)?;
         Ok(arr.into())
     }
 }
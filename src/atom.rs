@@ -121,6 +121,13 @@ impl<'q> IntoQjAtom<'q> for i32 {
     }
 }
 
+impl<'q> IntoQjAtom<'q> for u32 {
+    #[inline]
+    fn into_qj_atom(self, ctx: Context<'q>) -> Result<Atom<'q>> {
+        ctx.new_atom_with_u32(self)
+    }
+}
+
 pub struct PropertyEnum<'q> {
     property_enum: qc::PropertyEnum<'q>,
     context: qc::Context<'q>,
@@ -138,7 +145,9 @@ impl<'q> PropertyEnum<'q> {
     }
 
     pub fn atom(&self) -> Atom<'q> {
-        Atom::from_raw_parts(self.property_enum.atom(), self.context)
+        let atom = Atom::from_raw_parts(self.property_enum.atom(), self.context);
+        Atom::dup(&atom);
+        atom
     }
 
     // memory
@@ -1,5 +1,6 @@
 use crate::{
     convert::{FromQj, FromQjMulti, IntoQj},
+    panic,
     types::{ClassObject, Object},
     value::Value,
     Context, PropFlags, Result, Runtime,
@@ -19,6 +20,26 @@ pub trait ClassProperties<'q, C: Class> {
         F: Fn(&mut C, Context<'q>, ClassObject<'q, C>, A) -> Result<R> + 'static,
         A: FromQjMulti<'q>,
         R: IntoQj<'q> + 'q;
+    /// Same as `define_method` but the `length` of the method is `A::LENGTH`.
+    #[inline]
+    fn define_method_auto<F, A, R>(&mut self, name: &str, method: F) -> Result<Object<'q>>
+    where
+        F: Fn(&C, Context<'q>, ClassObject<'q, C>, A) -> Result<R> + 'static,
+        A: FromQjMulti<'q>,
+        R: IntoQj<'q> + 'q,
+    {
+        self.define_method(name, method, A::LENGTH)
+    }
+    /// Same as `define_method_mut` but the `length` of the method is `A::LENGTH`.
+    #[inline]
+    fn define_method_mut_auto<F, A, R>(&mut self, name: &str, method: F) -> Result<Object<'q>>
+    where
+        F: Fn(&mut C, Context<'q>, ClassObject<'q, C>, A) -> Result<R> + 'static,
+        A: FromQjMulti<'q>,
+        R: IntoQj<'q> + 'q,
+    {
+        self.define_method_mut(name, method, A::LENGTH)
+    }
     fn define_get_set_mut<G, R1, S, A, R2>(
         &mut self,
         name: &str,
@@ -87,7 +108,10 @@ unsafe fn finalize<C: Class + 'static>(rrt: qc::Runtime, val: qc::Value) {
         return;
     }
     // this Box was created by Value::set_opaque
-    let _b = Box::from_raw(p);
+    let b = Box::from_raw(p);
+    if let Err(message) = panic::catch(rt, move || drop(b)) {
+        log::error!("panic in the finalizer of {}: {}", C::name(), message);
+    }
 }
 
 struct Properties<'q> {
@@ -159,8 +183,8 @@ impl<'q, C: Class + 'static> ClassProperties<'q, C> for Properties<'q> {
         R2: IntoQj<'q> + 'q,
     {
         let ctx = self.context;
-        let g = make_getter(ctx, getter)?;
-        let s = make_setter(ctx, setter)?;
+        let g = make_getter(ctx, name, getter)?;
+        let s = make_setter(ctx, name, setter)?;
         trace!("registering get/set: {}::{} ({:?}, {:?})", C::name(), name, g, s);
         self.proto.define_property_get_set_from(
             name,
@@ -186,7 +210,7 @@ impl<'q, C: Class + 'static> ClassProperties<'q, C> for Properties<'q> {
         R: IntoQj<'q> + 'q,
     {
         let ctx = self.context;
-        let g = make_getter(ctx, getter)?;
+        let g = make_getter(ctx, name, getter)?;
         trace!("registering get: {}::{} ({:?})", C::name(), name, g);
         self.proto.define_property_get_set_from(
             name,
@@ -204,7 +228,7 @@ impl<'q, C: Class + 'static> ClassProperties<'q, C> for Properties<'q> {
         R: IntoQj<'q> + 'q,
     {
         let ctx = self.context;
-        let s = make_setter(ctx, setter)?;
+        let s = make_setter(ctx, name, setter)?;
         trace!("registering set: {}::{} ({:?})", C::name(), name, s);
         self.proto.define_property_get_set_from(
             name,
@@ -216,7 +240,7 @@ impl<'q, C: Class + 'static> ClassProperties<'q, C> for Properties<'q> {
     }
 }
 
-fn make_getter<'q, C, G, R>(ctx: Context<'q>, getter: G) -> Result<Object<'q>>
+fn make_getter<'q, C, G, R>(ctx: Context<'q>, name: &str, getter: G) -> Result<Object<'q>>
 where
     C: Class + 'static,
     G: Fn(&mut C, Context<'q>, ClassObject<'q, C>) -> Result<R> + 'static,
@@ -228,12 +252,12 @@ where
             let v = cloned.opaque_mut::<C>().unwrap();
             (getter)(v, ctx, unsafe { Value::copy_unchecked(this) })
         },
-        "get",
+        &format!("get {}", name),
         0,
     )
 }
 
-fn make_setter<'q, C, S, A, R>(ctx: Context<'q>, setter: S) -> Result<Object<'q>>
+fn make_setter<'q, C, S, A, R>(ctx: Context<'q>, name: &str, setter: S) -> Result<Object<'q>>
 where
     C: Class + 'static,
     S: Fn(&mut C, Context<'q>, ClassObject<'q, C>, A) -> Result<R> + 'static,
@@ -242,12 +266,12 @@ where
 {
     ctx.new_function_from(
         move |ctx, this: Value<'q>, args: Vec<Value<'q>>| {
-            let arg = args.get(0).cloned().unwrap_or_else(|| ctx.undefined().into());
+            let arg = args.first().cloned().unwrap_or_else(|| ctx.undefined().into());
             let mut cloned = this.clone();
             let v = cloned.opaque_mut::<C>().unwrap();
             (setter)(v, ctx, unsafe { Value::copy_unchecked(this) }, A::from_qj(arg)?)
         },
-        "set",
+        &format!("set {}", name),
         1,
     )
 }
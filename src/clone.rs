@@ -0,0 +1,225 @@
+use crate::{
+    context::Context,
+    error::{Error, ErrorKind},
+    flags::{GpnFlags, PropFlags, ReadObjFlags, WriteObjFlags},
+    inspect::is_identifier,
+    result::Result,
+    shared::SharedBuffer,
+    types::Tag,
+    value::Value,
+};
+use quijine_core as qc;
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::c_void,
+    mem::forget,
+};
+
+const ROOT: &str = "value";
+
+pub(crate) fn structured_clone<'q, 'p>(value: &Value<'q>, ctx: Context<'p>) -> Result<Value<'p>> {
+    let src = value.context();
+    let (src_rt, dst_rt) = (src.runtime(), ctx.runtime());
+    if src_rt.is_same_runtime(&dst_rt) {
+        let mut copier = Copier {
+            ctx,
+            object_proto: src.new_object()?.prototype()?,
+            array_proto: src.new_array()?.prototype()?,
+            copied: HashMap::new(),
+        };
+        if let Some(v) = copier.copy(value, &mut ROOT.to_owned())? {
+            return Ok(v);
+        }
+    }
+    deserialize(ctx, &serialize(value, dst_rt.is_shared_array_buffer_enabled())?)
+}
+
+/// `Serialized` is a value written by `write_object`, which keeps its `SharedArrayBuffer`s alive.
+pub(crate) struct Serialized {
+    buf: Vec<u8>,
+    buffers: Vec<SharedBuffer>,
+}
+
+/// Serializes the value. `SharedArrayBuffer`s are allowed if `sab` is `true` and the runtime enables them.
+pub(crate) fn serialize(value: &Value, sab: bool) -> Result<Serialized> {
+    let src = value.context();
+    let mut flags = WriteObjFlags::REFERENCE;
+    if sab && src.runtime().is_shared_array_buffer_enabled() {
+        flags |= WriteObjFlags::SAB;
+    }
+    match src.as_raw().write_object_with_sab_tab(*value.as_raw(), flags) {
+        Some((buf, sab_tab)) => Ok(Serialized {
+            buf,
+            buffers: sab_tab
+                .into_iter()
+                .filter_map(|data| SharedBuffer::from_data(data))
+                .collect(),
+        }),
+        None => {
+            let e = src.internal_js_error();
+            let mut visited = HashSet::new();
+            Err(locate(value, flags, &mut ROOT.to_owned(), &mut visited).unwrap_or(e))
+        }
+    }
+}
+
+pub(crate) fn deserialize<'p>(ctx: Context<'p>, serialized: &Serialized) -> Result<Value<'p>> {
+    let mut flags = ReadObjFlags::REFERENCE;
+    if !serialized.buffers.is_empty() {
+        flags |= ReadObjFlags::SAB;
+    }
+    ctx.read_object(&serialized.buf, flags)
+}
+
+/// `Copier` copies plain objects and arrays directly between contexts sharing a runtime.
+struct Copier<'q, 'p> {
+    ctx: Context<'p>,
+    object_proto: Value<'q>,
+    array_proto: Value<'q>,
+    copied: HashMap<*mut c_void, Value<'p>>,
+}
+
+impl<'q, 'p> Copier<'q, 'p> {
+    /// Returns `None` if the value needs serialization.
+    fn copy(&mut self, v: &Value<'q>, path: &mut String) -> Result<Option<Value<'p>>> {
+        match v.tag() {
+            Tag::Object => {}
+            Tag::Symbol => return Err(not_cloneable("symbol", path)),
+            // primitives are immutable and shared in a runtime
+            _ => return Ok(Some(unsafe { share(v, self.ctx) })),
+        }
+        if v.is_function() {
+            return Err(not_cloneable("function", path));
+        }
+        let ptr = v.as_raw().ptr().unwrap();
+        if let Some(copied) = self.copied.get(&ptr) {
+            return Ok(Some(copied.clone()));
+        }
+        let proto = v.prototype()?;
+        let is_array = v.is_array();
+        let plain = if is_array {
+            same_object(&proto, &self.array_proto)
+        } else {
+            proto.is_null() || same_object(&proto, &self.object_proto)
+        };
+        if !plain {
+            return Ok(None);
+        }
+        // like the serialization, an object without a prototype becomes an ordinary object
+        let obj: Value<'p> = if is_array {
+            self.ctx.new_array()?.into()
+        } else {
+            self.ctx.new_object()?.into()
+        };
+        self.copied.insert(ptr, obj.clone());
+        for prop in v.own_property_names(GpnFlags::STRING_MASK | GpnFlags::ENUM_ONLY)? {
+            let atom = prop.atom();
+            let key = atom.to_value()?;
+            let len = path.len();
+            push_key(path, &key.to_string()?, is_array);
+            let desc = match v.own_property(atom)? {
+                Some(desc) => desc,
+                None => {
+                    path.truncate(len);
+                    continue;
+                }
+            };
+            let value = match desc.value_opt() {
+                Some(value) => value,
+                None => return Err(not_cloneable("accessor property", path)),
+            };
+            let copied = match self.copy(value, path)? {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+            path.truncate(len);
+            let key = self.ctx.value_to_atom(&unsafe { share(&key, self.ctx) })?;
+            obj.define_property_value(key, copied, PropFlags::C_W_E | PropFlags::THROW)?;
+        }
+        if is_array {
+            // trailing holes are not enumerated
+            let len: Value<'q> = v.get("length")?;
+            obj.set("length", unsafe { share(&len, self.ctx) })?;
+        }
+        Ok(Some(obj))
+    }
+}
+
+/// Finds the value which can't be serialized.
+fn locate<'q>(
+    v: &Value<'q>,
+    flags: WriteObjFlags,
+    path: &mut String,
+    visited: &mut HashSet<*mut c_void>,
+) -> Option<Error> {
+    let ctx = v.context();
+    if ctx.write_object(v.clone(), flags).is_ok() {
+        return None;
+    }
+    match v.tag() {
+        Tag::Object => {}
+        Tag::Symbol => return Some(not_cloneable("symbol", path)),
+        _ => return Some(not_cloneable("value", path)),
+    }
+    if v.is_function() {
+        return Some(not_cloneable("function", path));
+    }
+    if !visited.insert(v.as_raw().ptr().unwrap()) {
+        return None;
+    }
+    let is_array = v.is_array();
+    for prop in v.own_property_names(GpnFlags::STRING_MASK | GpnFlags::ENUM_ONLY).ok()? {
+        let atom = prop.atom();
+        let key = atom.to_value().ok()?.to_string().ok()?;
+        let len = path.len();
+        push_key(path, &key, is_array);
+        let desc = v.own_property(atom).ok()??;
+        let value = match desc.value_opt() {
+            Some(value) => value,
+            None => return Some(not_cloneable("accessor property", path)),
+        };
+        if let Some(e) = locate(value, flags, path, visited) {
+            return Some(e);
+        }
+        path.truncate(len);
+    }
+    let name = v
+        .get::<_, Value>("constructor")
+        .and_then(|c| c.get::<_, String>("name"))
+        .ok()
+        .filter(|s| !s.is_empty());
+    Some(not_cloneable(name.as_deref().unwrap_or("object"), path))
+}
+
+/// # Safety
+/// The contexts must belong to the same runtime.
+unsafe fn share<'q, 'p>(v: &Value<'q>, ctx: Context<'p>) -> Value<'p> {
+    let v = v.clone();
+    let raw = qc::Value::into_raw(*v.as_raw());
+    forget(v);
+    Value::from_raw_parts(qc::Value::from_raw(raw, ctx.as_raw()), ctx.as_raw())
+}
+
+#[inline]
+fn same_object(a: &Value, b: &Value) -> bool {
+    a.as_raw().ptr() == b.as_raw().ptr()
+}
+
+fn push_key(path: &mut String, key: &str, is_array: bool) {
+    if is_array && key.parse::<u32>().is_ok() {
+        path.push_str(&format!("[{}]", key));
+    } else if is_identifier(key) {
+        path.push('.');
+        path.push_str(key);
+    } else {
+        path.push_str(&format!("[{:?}]", key));
+    }
+}
+
+#[inline]
+fn not_cloneable(what: &str, path: &str) -> Error {
+    Error::with_str(
+        ErrorKind::TypeError,
+        &format!("{} at {} could not be cloned", what, path),
+    )
+}
@@ -0,0 +1,259 @@
+use crate::{
+    context::Context,
+    inspect::{format, InspectOptions, Inspector},
+    result::Result,
+    types::{Object, Tag},
+    value::Value,
+};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Instant};
+
+/// `ConsoleLevel` is the severity of a message written by `console`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ConsoleLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// `ConsoleSink` receives formatted messages of `console` installed by `Context::install_console`.
+pub trait ConsoleSink {
+    fn write(&self, level: ConsoleLevel, message: &str);
+}
+
+/// `LogSink` forwards messages to the `log` crate with the target `console`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LogSink;
+
+impl ConsoleSink for LogSink {
+    fn write(&self, level: ConsoleLevel, message: &str) {
+        let level = match level {
+            ConsoleLevel::Debug => log::Level::Debug,
+            ConsoleLevel::Info => log::Level::Info,
+            ConsoleLevel::Warn => log::Level::Warn,
+            ConsoleLevel::Error => log::Level::Error,
+        };
+        log::log!(target: "console", level, "{}", message);
+    }
+}
+
+#[derive(Default)]
+struct State {
+    counts: HashMap<String, u64>,
+    timers: HashMap<String, Instant>,
+}
+
+type Console = Rc<(Box<dyn ConsoleSink>, RefCell<State>)>;
+
+fn label(args: &[Value]) -> Result<String> {
+    match args.first() {
+        Some(v) if !v.is_undefined() => v.to_string(),
+        _ => Ok("default".to_owned()),
+    }
+}
+
+fn define<'q, F>(ctx: Context<'q>, console: &Object<'q>, state: &Console, name: &str, f: F) -> Result<()>
+where
+    F: Fn(Context<'q>, &Console, &[Value<'q>]) -> Result<()> + 'q,
+{
+    let state = state.clone();
+    let func = ctx.new_function(
+        move |ctx, _this, args| {
+            f(ctx, &state, args)?;
+            Ok(ctx.undefined().into())
+        },
+        name,
+        0,
+    )?;
+    console.set(name, func)?;
+    Ok(())
+}
+
+fn define_writer<'q>(
+    ctx: Context<'q>,
+    console: &Object<'q>,
+    state: &Console,
+    name: &str,
+    level: ConsoleLevel,
+) -> Result<()> {
+    define(ctx, console, state, name, move |ctx, console, args| {
+        console.0.write(level, &format(ctx, args)?);
+        Ok(())
+    })
+}
+
+pub(crate) fn install<'q, S: ConsoleSink + 'static>(ctx: Context<'q>, sink: S) -> Result<Object<'q>> {
+    let state: Console = Rc::new((Box::new(sink), RefCell::new(State::default())));
+    let console = ctx.new_object()?;
+    define_writer(ctx, &console, &state, "log", ConsoleLevel::Info)?;
+    define_writer(ctx, &console, &state, "info", ConsoleLevel::Info)?;
+    define_writer(ctx, &console, &state, "debug", ConsoleLevel::Debug)?;
+    define_writer(ctx, &console, &state, "warn", ConsoleLevel::Warn)?;
+    define_writer(ctx, &console, &state, "error", ConsoleLevel::Error)?;
+    define(ctx, &console, &state, "trace", |ctx, console, args| {
+        let message = format(ctx, args)?;
+        let stack = ctx.native_stack();
+        let message = if message.is_empty() {
+            "Trace".to_owned()
+        } else {
+            format!("Trace: {}", message)
+        };
+        console
+            .0
+            .write(ConsoleLevel::Error, format!("{}\n{}", message, stack).trim_end());
+        Ok(())
+    })?;
+    define(ctx, &console, &state, "count", |_ctx, console, args| {
+        let label = label(args)?;
+        let count = {
+            let mut state = console.1.borrow_mut();
+            let count = state.counts.entry(label.clone()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        console.0.write(ConsoleLevel::Info, &format!("{}: {}", label, count));
+        Ok(())
+    })?;
+    define(ctx, &console, &state, "countReset", |_ctx, console, args| {
+        let label = label(args)?;
+        if console.1.borrow_mut().counts.remove(&label).is_none() {
+            console
+                .0
+                .write(ConsoleLevel::Warn, &format!("Count for '{}' does not exist", label));
+        }
+        Ok(())
+    })?;
+    define(ctx, &console, &state, "time", |_ctx, console, args| {
+        let label = label(args)?;
+        let mut state = console.1.borrow_mut();
+        if state.timers.contains_key(&label) {
+            drop(state);
+            console.0.write(
+                ConsoleLevel::Warn,
+                &format!("Label '{}' already exists for console.time()", label),
+            );
+            return Ok(());
+        }
+        state.timers.insert(label, Instant::now());
+        Ok(())
+    })?;
+    define(ctx, &console, &state, "timeLog", |ctx, console, args| {
+        time_log(ctx, console, args, false)
+    })?;
+    define(ctx, &console, &state, "timeEnd", |ctx, console, args| {
+        time_log(ctx, console, args, true)
+    })?;
+    define(ctx, &console, &state, "table", |ctx, console, args| {
+        let message = match args.first() {
+            Some(data) if data.tag() == Tag::Object && args.len() == 1 => table(ctx, data)?,
+            _ => format(ctx, args)?,
+        };
+        console.0.write(ConsoleLevel::Info, &message);
+        Ok(())
+    })?;
+    ctx.global_object()?.set("console", console.clone())?;
+    Ok(console)
+}
+
+fn time_log<'q>(ctx: Context<'q>, console: &Console, args: &[Value<'q>], end: bool) -> Result<()> {
+    let label = label(args)?;
+    let start = {
+        let mut state = console.1.borrow_mut();
+        if end {
+            state.timers.remove(&label)
+        } else {
+            state.timers.get(&label).cloned()
+        }
+    };
+    let start = match start {
+        Some(start) => start,
+        None => {
+            let message = format!(
+                "No such label '{}' for console.{}()",
+                label,
+                if end { "timeEnd" } else { "timeLog" }
+            );
+            console.0.write(ConsoleLevel::Warn, &message);
+            return Ok(());
+        }
+    };
+    let ms = start.elapsed().as_secs_f64() * 1000.0;
+    let mut message = format!("{}: {:.3}ms", label, ms);
+    if !end && args.len() > 1 {
+        message.push(' ');
+        message.push_str(&format(ctx, &args[1..])?);
+    }
+    console.0.write(ConsoleLevel::Info, &message);
+    Ok(())
+}
+
+/// Renders rows of an object or an array like Node's `console.table`.
+fn table<'q>(ctx: Context<'q>, data: &Value<'q>) -> Result<String> {
+    let mut inspector = Inspector::new(ctx, InspectOptions::default())?;
+    let index_header = "(index)".to_owned();
+    let values_header = "Values".to_owned();
+    let mut columns: Vec<String> = Vec::new();
+    let mut has_values = false;
+    let mut rows = Vec::new();
+    for entry in data.entries()? {
+        let (key, row) = entry?;
+        let mut cells = HashMap::new();
+        if row.tag() == Tag::Object && !row.is_function() {
+            for cell in row.entries()? {
+                let (k, v) = cell?;
+                let k = k.to_string()?;
+                if !columns.contains(&k) {
+                    columns.push(k.clone());
+                }
+                cells.insert(k, inspector.inspect(&v)?);
+            }
+        } else {
+            has_values = true;
+            cells.insert(values_header.clone(), inspector.inspect(&row)?);
+        }
+        rows.push((key.to_string()?, cells));
+    }
+    if has_values {
+        columns.push(values_header);
+    }
+    let mut header = vec![index_header];
+    header.extend(columns.iter().cloned());
+    let body: Vec<Vec<String>> = rows
+        .into_iter()
+        .map(|(key, mut cells)| {
+            let mut line = vec![key];
+            line.extend(columns.iter().map(|c| cells.remove(c).unwrap_or_default()));
+            line
+        })
+        .collect();
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            body.iter()
+                .map(|line| line[i].chars().count())
+                .chain(Some(header[i].chars().count()))
+                .max()
+                .unwrap_or(0)
+                + 2
+        })
+        .collect();
+    let rule = |l: &str, m: &str, r: &str| {
+        let cells: Vec<_> = widths.iter().map(|w| "─".repeat(*w)).collect();
+        format!("{}{}{}", l, cells.join(m), r)
+    };
+    let line = |cells: &[String]| {
+        let cells: Vec<_> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| {
+                let len = c.chars().count();
+                let left = (w - len) / 2;
+                format!("{}{}{}", " ".repeat(left), c, " ".repeat(w - len - left))
+            })
+            .collect();
+        format!("│{}│", cells.join("│"))
+    };
+    let mut out = vec![rule("┌", "┬", "┐"), line(&header), rule("├", "┼", "┤")];
+    out.extend(body.iter().map(|cells| line(cells)));
+    out.push(rule("└", "┴", "┘"));
+    Ok(out.join("\n"))
+}
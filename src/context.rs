@@ -1,17 +1,45 @@
+#[cfg(feature = "console")]
+use crate::console::{self, ConsoleSink};
+#[cfg(feature = "stream")]
+use crate::stream::{self, AsyncIterators};
+#[cfg(feature = "timers")]
+use crate::timers::{self, Clock, Timers};
+#[cfg(feature = "worker")]
+use crate::worker::{self, WorkerOptions};
 use crate::{
     atom::Atom,
     class::{register_class, Class},
     convert::{FromQj, FromQjMulti, IntoQj, IntoQjMulti},
-    error::ErrorValue,
+    error::{ErrorValue, JsStackFrame},
+    native_function::{self, CallInfo},
+    panic,
     result::Result,
-    runtime::Runtime,
-    types::{Bool, ClassObject, Float64, Int, Null, Object, String as QjString, Undefined},
+    runtime::{poisoned_error, Runtime},
+    script::CompiledScript,
+    shared::SharedBuffer,
+    types::{ArrayRef, Bool, ClassObject, Float64, Int, Null, Object, String as QjString, Tag, Undefined},
     Error, ErrorKind, EvalFlags, Exception, IntoQjAtom, ModuleDef, PropFlags, RuntimeScope, Value,
 };
 use qc::{ReadObjFlags, WriteObjFlags};
 use quijine_core::{self as qc, raw, AsJsValue};
+#[cfg(feature = "stream")]
+use std::future::Future;
+#[cfg(feature = "worker")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(any(feature = "timers", feature = "worker"))]
+use std::time::Duration;
 use std::{
-    any::TypeId, collections::HashSet, convert::TryInto, ffi::c_void, fmt, os::raw::c_int, result::Result as StdResult,
+    any::TypeId,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    convert::TryInto,
+    error::Error as StdError,
+    ffi::c_void,
+    fmt,
+    os::raw::c_int,
+    ptr::null_mut,
+    rc::Rc,
+    result::Result as StdResult,
 };
 
 macro_rules! def_throw_error {
@@ -25,8 +53,96 @@ macro_rules! def_throw_error {
     };
 }
 
+/// The id of the next context, which is never reused unlike the address of a freed context.
+#[cfg(feature = "worker")]
+static NEXT_CONTEXT_ID: AtomicU64 = AtomicU64::new(1);
+
 pub struct ContextOpaque {
+    #[cfg(feature = "worker")]
+    id: u64,
     registered_classes: HashSet<TypeId>,
+    #[cfg(feature = "timers")]
+    pub(crate) timers: Option<Timers>,
+    #[cfg(feature = "stream")]
+    pub(crate) async_iterators: Option<AsyncIterators>,
+    intrinsics: HashMap<Intrinsic, qc::Value<'static>>,
+}
+
+/// `Intrinsic` names a value which is captured when a context is created, before scripts can replace it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Intrinsic {
+    FunctionPrototype,
+    ObjectToString,
+    FunctionToString,
+    Number,
+    ParseInt,
+    ParseFloat,
+    EvalError,
+    URIError,
+    AggregateError,
+    SymbolIterator,
+    SymbolAsyncIterator,
+    IteratorPrototype,
+    Promise,
+    PromiseResolve,
+    PromiseThen,
+    NumberValueOf,
+    StringValueOf,
+    BooleanValueOf,
+    DateGetTime,
+    DateToISOString,
+}
+
+impl Intrinsic {
+    // `IteratorPrototype` comes after `SymbolIterator` which it is looked up by
+    const ALL: [Intrinsic; 20] = [
+        Intrinsic::FunctionPrototype,
+        Intrinsic::ObjectToString,
+        Intrinsic::FunctionToString,
+        Intrinsic::Number,
+        Intrinsic::ParseInt,
+        Intrinsic::ParseFloat,
+        Intrinsic::EvalError,
+        Intrinsic::URIError,
+        Intrinsic::AggregateError,
+        Intrinsic::SymbolIterator,
+        Intrinsic::SymbolAsyncIterator,
+        Intrinsic::IteratorPrototype,
+        Intrinsic::Promise,
+        Intrinsic::PromiseResolve,
+        Intrinsic::PromiseThen,
+        Intrinsic::NumberValueOf,
+        Intrinsic::StringValueOf,
+        Intrinsic::BooleanValueOf,
+        Intrinsic::DateGetTime,
+        Intrinsic::DateToISOString,
+    ];
+
+    /// Returns the path of the value from the global object.
+    fn path(self) -> &'static str {
+        match self {
+            Intrinsic::FunctionPrototype => "%Function.prototype%",
+            Intrinsic::ObjectToString => "Object.prototype.toString",
+            Intrinsic::FunctionToString => "Function.prototype.toString",
+            Intrinsic::Number => "Number",
+            Intrinsic::ParseInt => "parseInt",
+            Intrinsic::ParseFloat => "parseFloat",
+            Intrinsic::EvalError => "EvalError.prototype",
+            Intrinsic::URIError => "URIError.prototype",
+            Intrinsic::AggregateError => "AggregateError.prototype",
+            Intrinsic::SymbolIterator => "Symbol.iterator",
+            Intrinsic::SymbolAsyncIterator => "Symbol.asyncIterator",
+            Intrinsic::IteratorPrototype => "%IteratorPrototype%",
+            Intrinsic::Promise => "Promise",
+            Intrinsic::PromiseResolve => "Promise.resolve",
+            Intrinsic::PromiseThen => "Promise.prototype.then",
+            Intrinsic::NumberValueOf => "Number.prototype.valueOf",
+            Intrinsic::StringValueOf => "String.prototype.valueOf",
+            Intrinsic::BooleanValueOf => "Boolean.prototype.valueOf",
+            Intrinsic::DateGetTime => "Date.prototype.getTime",
+            Intrinsic::DateToISOString => "Date.prototype.toISOString",
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -63,6 +179,13 @@ impl<'q> Context<'q> {
         unsafe { &*(self.0.opaque() as *mut ContextOpaque) }
     }
 
+    /// Returns the id which identifies this context in the process.
+    #[cfg(feature = "worker")]
+    #[inline]
+    pub(crate) fn id(self) -> u64 {
+        self.opaque().id
+    }
+
     #[inline]
     pub(crate) fn opaque_mut(&mut self) -> &mut ContextOpaque {
         unsafe { &mut *(self.0.opaque() as *mut ContextOpaque) }
@@ -93,7 +216,7 @@ impl<'q> Context<'q> {
     }
 
     #[inline]
-    unsafe fn wrap_result_atom(self, val: qc::Atom<'q>) -> Result<Atom> {
+    unsafe fn wrap_result_atom(self, val: qc::Atom<'q>) -> Result<Atom<'q>> {
         if val.is_null() {
             Err(Error::with_str(ErrorKind::InternalError, "null atom"))
         } else {
@@ -101,6 +224,143 @@ impl<'q> Context<'q> {
         }
     }
 
+    /// Throws an error returned by the Rust function `callee`. The function becomes a frame of the stack.
+    pub(crate) fn throw_callback_error(self, e: Error, callee: &str) {
+        let thrown = e.thrown_value(self);
+        let kind = e.kind;
+        let code = e.code().map(str::to_owned);
+        let (name, message, mut frames, external) = match e.value {
+            ErrorValue::None => (None, "some error occured".to_owned(), Vec::new(), None),
+            ErrorValue::String(s) => (None, s, Vec::new(), None),
+            ErrorValue::JsError(mut data) => {
+                let frames = data.take_frames();
+                (data.name, data.message.unwrap_or_default(), frames, None)
+            }
+            ErrorValue::External(e) => (None, e.to_string(), Vec::new(), Some(e)),
+        };
+        if let Some(thrown) = thrown {
+            // the thrown value goes back unchanged, with the stack recorded where it was thrown
+            self.throw(thrown);
+            return;
+        }
+        // QuickJS does not record native functions, so the stack of an error thrown below
+        // this callback already contains its callers
+        let callers = self.caller_frames();
+        let same = |a: &JsStackFrame, b: &JsStackFrame| a.function == b.function && a.file == b.file;
+        let common = frames
+            .iter()
+            .rev()
+            .zip(callers.iter().rev())
+            .take_while(|(a, b)| same(a, b))
+            .count();
+        frames.truncate(frames.len() - common);
+        frames.push(JsStackFrame {
+            function: Some(callee.to_owned()).filter(|f| !f.is_empty()),
+            file: None,
+            line: None,
+            column: None,
+        });
+        frames.extend(callers);
+        let stack: String = frames.iter().map(|frame| format!("    {}\n", frame)).collect();
+        let error = self.new_error_of_kind(kind, &message);
+        if let Some(name) = name {
+            let _ = error.set("name", name);
+        }
+        if let Some(code) = code {
+            let _ = error.set("code", code);
+        }
+        if let Some(Ok(Some(cause))) = external.as_ref().map(|e| self.new_error_cause(e.as_ref())) {
+            let _ = error.define_property_value_from("cause", cause, PropFlags::CONFIGURABLE | PropFlags::WRITABLE);
+        }
+        let _ = error.set("stack", stack);
+        self.throw(error);
+    }
+
+    /// Throws an `InternalError` for a panic caught in the native function `callee`.
+    pub(crate) fn throw_panic(self, message: &str, callee: &str) {
+        self.throw_callback_error(Error::with_str(ErrorKind::InternalError, message), callee);
+        if self.runtime().is_poisoned() {
+            self.make_exception_uncatchable();
+        }
+    }
+
+    pub(crate) fn make_exception_uncatchable(self) {
+        let error = self.take_exception();
+        self.0.set_uncatchable_error(*error.as_raw(), true);
+        self.throw(error);
+    }
+
+    /// Creates an instance of the JS error constructor corresponding to `kind`.
+    fn new_error_of_kind(self, kind: ErrorKind, message: &str) -> Value<'q> {
+        match kind {
+            ErrorKind::SyntaxError => self.throw_syntax_error(message),
+            ErrorKind::TypeError => self.throw_type_error(message),
+            ErrorKind::ReferenceError => self.throw_reference_error(message),
+            ErrorKind::RangeError => self.throw_range_error(message),
+            _ => self.throw_internal_error(message),
+        };
+        let error = self.take_exception();
+        let proto = match kind {
+            ErrorKind::EvalError => Intrinsic::EvalError,
+            ErrorKind::URIError => Intrinsic::URIError,
+            ErrorKind::AggregateError => Intrinsic::AggregateError,
+            _ => return error,
+        };
+        // stays an InternalError if the context has no such constructor
+        if let Some(proto) = self.intrinsic(proto) {
+            let _ = error.as_raw().set_prototype(self.0, *proto.as_raw());
+        }
+        if kind == ErrorKind::AggregateError {
+            if let Ok(errors) = self.new_array() {
+                let _ = error.set("errors", errors);
+            }
+        }
+        error
+    }
+
+    /// Converts the sources of a Rust error into JS errors linked by `cause`.
+    /// A source which was thrown in JS becomes the cause itself.
+    fn new_error_cause(self, e: &(dyn StdError + 'static)) -> Result<Option<Value<'q>>> {
+        let source = match e.source() {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+        if let Some(thrown) = source.downcast_ref::<Error>().and_then(|e| e.thrown_value(self)) {
+            return Ok(Some(thrown));
+        }
+        let error = self.new_error()?;
+        error.define_property_value_from(
+            "message",
+            source.to_string(),
+            PropFlags::CONFIGURABLE | PropFlags::WRITABLE,
+        )?;
+        if let Some(cause) = self.new_error_cause(source)? {
+            error.define_property_value_from("cause", cause, PropFlags::CONFIGURABLE | PropFlags::WRITABLE)?;
+        }
+        Ok(Some(error.into()))
+    }
+
+    /// Returns the frames of the JS functions calling the current native function.
+    fn caller_frames(self) -> Vec<JsStackFrame> {
+        JsStackFrame::parse_stack(&self.native_stack())
+    }
+
+    /// Returns the stack of the JS functions calling the current native function, as QuickJS records it.
+    pub(crate) fn native_stack(self) -> String {
+        // QuickJS records the stack of an error thrown by a plain C function, whose own frame comes first
+        let throw = self.0.new_c_function(
+            qc::js_c_function!(|ctx, _this, _args| ctx.throw_internal_error("")),
+            "",
+            0,
+        );
+        let throw = Value::from_raw_parts(throw, self.0);
+        let _ = self
+            .0
+            .call(*throw.as_raw(), qc::Value::undefined(), &[] as &[qc::Value]);
+        let stack: String = self.take_exception().get("stack").unwrap_or_default();
+        stack.lines().skip(1).map(|line| format!("{}\n", line)).collect()
+    }
+
     #[inline]
     pub(crate) fn internal_js_error(self) -> Error {
         Error::from_js_error(ErrorKind::InternalError, self.take_exception())
@@ -108,6 +368,7 @@ impl<'q> Context<'q> {
 
     #[inline]
     pub fn eval(self, code: &str, filename: &str, eval_flags: EvalFlags) -> Result<Value<'q>> {
+        self.runtime().check_poisoned()?;
         unsafe { self.wrap_result(self.0.eval(code, filename, eval_flags)) }
     }
 
@@ -116,8 +377,15 @@ impl<'q> Context<'q> {
         R::from_qj(self.eval(code, filename, eval_flags)?)
     }
 
+    /// Compiles a script or a module into bytecode which can be serialized and cached.
+    #[inline]
+    pub fn compile(self, code: &str, filename: &str, eval_flags: EvalFlags) -> Result<CompiledScript> {
+        CompiledScript::compile(self, code, filename, eval_flags)
+    }
+
     #[inline]
     pub fn eval_function(self, func_obj: Value<'q>) -> Result<Value<'q>> {
+        self.runtime().check_poisoned()?;
         Value::dup(&func_obj);
         unsafe { self.wrap_result(self.0.eval_function(*func_obj.as_raw())) }
     }
@@ -129,11 +397,21 @@ impl<'q> Context<'q> {
 
     #[inline]
     pub fn call(self, func_obj: Value<'q>, this_obj: Value<'q>, args: &[Value<'q>]) -> Result<Value<'q>> {
+        self.runtime().check_poisoned()?;
         let qc_args: Vec<_> = args.iter().map(|v| *v.as_raw()).collect();
         let val = self.0.call(*func_obj.as_raw(), *this_obj.as_raw(), &qc_args);
         unsafe { self.wrap_result(val) }
     }
 
+    /// Calls `func_obj` as `new func_obj(...args)`.
+    #[inline]
+    pub fn call_constructor(self, func_obj: Value<'q>, args: &[Value<'q>]) -> Result<Value<'q>> {
+        self.runtime().check_poisoned()?;
+        let qc_args: Vec<_> = args.iter().map(|v| *v.as_raw()).collect();
+        let val = self.0.call_constructor(*func_obj.as_raw(), &qc_args);
+        unsafe { self.wrap_result(val) }
+    }
+
     #[inline]
     pub fn call_into<F, T, A, R>(self, func_obj: F, this_obj: T, args: A) -> Result<R>
     where
@@ -168,6 +446,12 @@ impl<'q> Context<'q> {
         unsafe { self.wrap_result(self.0.new_object()) }
     }
 
+    /// Creates an object whose prototype is `proto` like `Object.create`.
+    #[inline]
+    pub fn new_object_with_proto(self, proto: &Value<'q>) -> Result<Object<'q>> {
+        unsafe { self.wrap_result(self.0.new_object_proto(*proto.as_raw())) }
+    }
+
     #[inline]
     fn new_object_from_entries_raw<T: IntoIterator<Item = (Atom<'q>, Value<'q>)>>(self, vs: T) -> Result<Object<'q>> {
         let a = self.new_object()?;
@@ -229,10 +513,25 @@ impl<'q> Context<'q> {
         F: Fn(Context<'q>, Value<'q>, &[Value<'q>]) -> C + 'q,
     {
         self.register_class::<C>()?;
-        let f = self.new_function(
-            move |ctx, this, args| {
-                let mut obj = ctx.new_object_with_opaque(f(ctx, this.clone(), args))?;
-                C::constructor(obj.opaque_mut().unwrap(), ctx, this, args)?;
+        let f = self.new_function_with_info(
+            move |ctx, info| {
+                if !info.is_construct_call() {
+                    return Err(Error::with_str(
+                        ErrorKind::TypeError,
+                        &format!("class constructor {} must be called with new", C::name()),
+                    ));
+                }
+                // like C function data, missing arguments up to the length are `undefined`
+                let mut args = info.args().to_vec();
+                args.resize(args.len().max(C::constructor_length() as usize), ctx.undefined().into());
+                let new_target = info.new_target().clone();
+                let mut obj = ctx.new_object_with_opaque(f(ctx, new_target.clone(), &args))?;
+                // `new.target` differs from the constructor for subclasses
+                let proto: Value = new_target.get("prototype")?;
+                if proto.tag() == Tag::Object {
+                    obj.set_prototype(&proto)?;
+                }
+                C::constructor(obj.opaque_mut().unwrap(), ctx, new_target, &args)?;
                 Ok(obj.into())
             },
             C::name(),
@@ -264,10 +563,119 @@ impl<'q> Context<'q> {
     #[inline]
     fn new_array_from_raw<T: IntoIterator<Item = Value<'q>>>(self, vs: T) -> Result<Object<'q>> {
         let a = self.new_array()?;
+        self.set_array_elements(&a, 0, vs)?;
+        Ok(a)
+    }
+
+    pub fn new_array_from_f64(self, vs: &[f64]) -> Result<ArrayRef<'q>> {
+        let a: ArrayRef = self.new_array()?.try_into()?;
+        let values = vs.iter().map(|v| Value::from_raw_parts(self.0.new_float64(*v), self.0));
+        self.set_array_elements(&a, 0, values)?;
+        Ok(a)
+    }
+
+    pub fn new_array_from_i32(self, vs: &[i32]) -> Result<ArrayRef<'q>> {
+        let a: ArrayRef = self.new_array()?.try_into()?;
+        let values = vs.iter().map(|v| Value::from_raw_parts(self.0.new_int32(*v), self.0));
+        self.set_array_elements(&a, 0, values)?;
+        Ok(a)
+    }
+
+    /// Sets values to the elements of an array from `start`.
+    /// Setting the element at the length appends it to the storage of a fast array.
+    pub(crate) fn set_array_elements<T: IntoIterator<Item = Value<'q>>>(
+        self,
+        array: &Value<'q>,
+        start: u32,
+        vs: T,
+    ) -> Result<()> {
         for (i, v) in vs.into_iter().enumerate() {
-            a.set(i as i32, v)?;
+            Value::dup(&v);
+            let ret = array
+                .as_raw()
+                .set_property_uint32(self.0, start + i as u32, *v.as_raw());
+            self.map_err_to_exception(ret)?;
         }
-        Ok(a)
+        Ok(())
+    }
+
+    /// Returns the value of `intrinsic` captured when the context was created.
+    pub(crate) fn intrinsic(self, intrinsic: Intrinsic) -> Option<Value<'q>> {
+        let v = *self.opaque().intrinsics.get(&intrinsic)?;
+        Some(Value::restore(self, v))
+    }
+
+    /// Returns the value of `intrinsic`, or a `TypeError` if the context has no such value.
+    pub(crate) fn require_intrinsic(self, intrinsic: Intrinsic) -> Result<Value<'q>> {
+        self.intrinsic(intrinsic).ok_or_else(|| {
+            Error::with_str(
+                ErrorKind::TypeError,
+                &format!("{} is not available in this context", intrinsic.path()),
+            )
+        })
+    }
+
+    /// Captures the intrinsics which the context has and which are not captured yet.
+    pub(crate) fn capture_intrinsics(self) -> Result<()> {
+        for intrinsic in Intrinsic::ALL {
+            if self.opaque().intrinsics.contains_key(&intrinsic) {
+                continue;
+            }
+            if let Some(v) = self.look_up_intrinsic(intrinsic)? {
+                self.set_intrinsic(intrinsic, v);
+            }
+        }
+        Ok(())
+    }
+
+    fn look_up_intrinsic(self, intrinsic: Intrinsic) -> Result<Option<Value<'q>>> {
+        if intrinsic == Intrinsic::FunctionPrototype {
+            return Ok(Some(self.c_function_prototype()?));
+        }
+        if intrinsic == Intrinsic::IteratorPrototype {
+            // the prototype of the prototype of an array iterator
+            let symbol = match self.intrinsic(Intrinsic::SymbolIterator) {
+                Some(symbol) => symbol,
+                None => return Ok(None),
+            };
+            let array = self.new_array()?;
+            let iterator = self.call(array.get(symbol)?, array.into(), &[])?;
+            return Ok(Some(iterator.prototype()?.prototype()?));
+        }
+        let mut v: Value = self.global_object()?.into();
+        for key in intrinsic.path().split('.') {
+            if v.tag() != Tag::Object {
+                return Ok(None);
+            }
+            v = v.get(key)?;
+        }
+        Ok(Some(v).filter(|v| !v.is_undefined()))
+    }
+
+    fn set_intrinsic(mut self, intrinsic: Intrinsic, v: Value<'q>) {
+        let old = self.opaque_mut().intrinsics.insert(intrinsic, Value::persist(&v));
+        // frees the value captured before
+        drop(old.map(|old| Value::from_raw_parts(old, self.0)));
+    }
+
+    /// Returns `Function.prototype`, which is captured at the first call in raw contexts.
+    pub(crate) fn function_prototype(self) -> Result<Value<'q>> {
+        if let Some(proto) = self.intrinsic(Intrinsic::FunctionPrototype) {
+            return Ok(proto);
+        }
+        let proto = self.c_function_prototype()?;
+        self.set_intrinsic(Intrinsic::FunctionPrototype, proto.clone());
+        Ok(proto)
+    }
+
+    /// Returns the prototype of a fresh C function, which is `Function.prototype` of the realm
+    /// even if scripts have replaced the global `Function`.
+    fn c_function_prototype(self) -> Result<Value<'q>> {
+        let func = self
+            .0
+            .new_c_function(qc::js_c_function!(|_ctx, _this, _args| qc::Value::undefined()), "", 0);
+        let func: Value = unsafe { self.wrap_result(func)? };
+        func.prototype()
     }
 
     #[inline]
@@ -276,6 +684,19 @@ impl<'q> Context<'q> {
         self.new_array_from_raw(values)
     }
 
+    /// Creates a `SharedArrayBuffer` backed by the buffer.
+    /// The runtime must enable `SharedArrayBuffer` by `Runtime::enable_shared_array_buffer`.
+    pub fn new_shared_array_buffer(self, buf: &SharedBuffer) -> Result<Object<'q>> {
+        if !self.runtime().is_shared_array_buffer_enabled() {
+            return Err(Error::with_str(
+                ErrorKind::TypeError,
+                "SharedArrayBuffer is not enabled on the runtime",
+            ));
+        }
+        // QuickJS retains the buffer by sab_dup
+        unsafe { self.wrap_result(self.0.new_array_buffer(buf.as_ptr(), buf.len(), None, null_mut(), true)) }
+    }
+
     unsafe fn new_value<T: AsRef<Value<'q>>>(self, v: qc::Value<'q>) -> T {
         Value::from_raw_parts(v, self.0).into_unchecked()
     }
@@ -342,6 +763,85 @@ impl<'q> Context<'q> {
         self.new_callback(Box::new(func), name, length)
     }
 
+    /// Creates a function whose callback receives `this`, the arguments and `new.target` as a `CallInfo`.
+    /// Set the constructor bit to allow `new`.
+    #[inline]
+    pub fn new_function_with_info<F>(self, func: F, name: &str, length: i32) -> Result<Object<'q>>
+    where
+        F: for<'a> Fn(Context<'q>, CallInfo<'q, 'a>) -> Result<Value<'q>> + 'q,
+    {
+        native_function::new_function_with_info(self, Box::new(func), name, length)
+    }
+
+    /// Creates a JS iterator which pulls items from `iter` lazily, e.g. for `for...of`.
+    /// `iter` is dropped when it is exhausted or `return()` is called.
+    pub fn new_iterator<I>(self, iter: I) -> Result<Object<'q>>
+    where
+        I: IntoIterator,
+        I::IntoIter: 'q,
+        I::Item: IntoQj<'q>,
+    {
+        let state = Rc::new(RefCell::new(Some(iter.into_iter())));
+        // %IteratorPrototype% provides `[Symbol.iterator]() { return this; }`
+        let proto = self.intrinsic(Intrinsic::IteratorPrototype);
+        let obj = match &proto {
+            Some(proto) => self.new_object_with_proto(proto)?,
+            None => self.new_object()?,
+        };
+        let next = {
+            let state = state.clone();
+            self.new_function(
+                move |ctx, _this, _args| {
+                    let mut state = state
+                        .try_borrow_mut()
+                        .map_err(|_| Error::with_str(ErrorKind::TypeError, "iterator is already running"))?;
+                    let item = state.as_mut().and_then(|iter| iter.next());
+                    match item {
+                        Some(item) => iterator_result(ctx, item.into_qj(ctx)?, false),
+                        None => {
+                            *state = None;
+                            iterator_result(ctx, ctx.undefined().into(), true)
+                        }
+                    }
+                },
+                "next",
+                0,
+            )?
+        };
+        let return_ = self.new_function(
+            move |ctx, _this, args| {
+                let mut state = state
+                    .try_borrow_mut()
+                    .map_err(|_| Error::with_str(ErrorKind::TypeError, "iterator is already running"))?;
+                *state = None;
+                let value = args.first().cloned().unwrap_or_else(|| ctx.undefined().into());
+                iterator_result(ctx, value, true)
+            },
+            "return",
+            1,
+        )?;
+        let flags = PropFlags::WRITABLE | PropFlags::CONFIGURABLE;
+        obj.define_property_value_from("next", next, flags)?;
+        obj.define_property_value_from("return", return_, flags)?;
+        if let (None, Some(symbol_iterator)) = (proto, self.intrinsic(Intrinsic::SymbolIterator)) {
+            let iterator = self.new_function(|_ctx, this, _args| Ok(this), "[Symbol.iterator]", 0)?;
+            obj.define_property_value_from(symbol_iterator, iterator, flags)?;
+        }
+        Ok(obj)
+    }
+
+    /// Creates an async iterator whose `next` returns promises resolved as `stream` yields.
+    /// Waiting promises are resolved in `run_until_idle`, `run_for`, `wait_for_streams` and `block_on`.
+    #[cfg(feature = "stream")]
+    #[inline]
+    pub fn new_async_iterator<S>(self, stream: S) -> Result<Object<'q>>
+    where
+        S: futures_core::Stream + 'q,
+        S::Item: IntoQj<'q>,
+    {
+        stream::new_async_iterator(self, stream)
+    }
+
     #[inline]
     pub fn new_function_from<F, T, A, R>(self, func: F, name: &str, length: i32) -> Result<Object<'q>>
     where
@@ -357,8 +857,20 @@ impl<'q> Context<'q> {
         )
     }
 
+    /// Same as `new_function_from` but the `length` of the function is `A::LENGTH`.
+    #[inline]
+    pub fn new_function_auto<F, T, A, R>(self, func: F, name: &str) -> Result<Object<'q>>
+    where
+        F: Fn(Context<'q>, T, A) -> Result<R> + 'q,
+        T: FromQj<'q>,
+        A: FromQjMulti<'q>,
+        R: IntoQj<'q> + 'q,
+    {
+        self.new_function_from(func, name, A::LENGTH)
+    }
+
     #[inline]
-    pub(crate) fn new_callback<R>(self, func: Box<Callback<'q, 'q, R>>, _name: &str, length: i32) -> Result<Object<'q>>
+    pub(crate) fn new_callback<R>(self, func: Box<Callback<'q, 'q, R>>, name: &str, length: i32) -> Result<Object<'q>>
     where
         R: Into<Value<'q>> + 'q,
     {
@@ -380,7 +892,7 @@ impl<'q> Context<'q> {
             }
             let cb = qc::Value::from_raw(*func_value, ctx);
             log::debug!("load pointer from ArrayBuffer");
-            let func = cb.array_buffer_as_ref::<Box<Callback<R>>>(ctx).unwrap();
+            let data = cb.array_buffer_as_ref::<CallbackData<R>>(ctx).unwrap();
 
             log::debug!("this");
             let this = Value::from_raw_parts(this, ctx);
@@ -391,27 +903,24 @@ impl<'q> Context<'q> {
             let ctx = Context::from_raw(ctx);
 
             log::debug!("invoke start");
-            let r = (**func)(ctx, this, args.as_slice());
+            if ctx.runtime().is_poisoned() {
+                ctx.throw_callback_error(poisoned_error(), &data.name);
+                ctx.make_exception_uncatchable();
+                return qc::Value::exception().as_js_value();
+            }
+            let r = panic::catch(ctx.runtime(), || (*data.func)(ctx, this, args.as_slice()));
             let res = match r {
-                Ok(t) => {
+                Ok(Ok(t)) => {
                     let t = t.into();
                     Value::dup(&t);
                     t.as_raw().as_js_value()
                 }
-                Err(e) => {
-                    let v = e.value;
-                    match v {
-                        ErrorValue::None => {
-                            ctx.throw_internal_error("some error occured");
-                        }
-                        ErrorValue::String(s) => {
-                            ctx.throw_internal_error(&s);
-                        }
-                        ErrorValue::JsError(_) => (), // use original Error
-                        ErrorValue::External(e) => {
-                            ctx.throw_internal_error(&format!("{}", e));
-                        }
-                    };
+                Ok(Err(e)) => {
+                    ctx.throw_callback_error(e, &data.name);
+                    qc::Value::exception().as_js_value()
+                }
+                Err(message) => {
+                    ctx.throw_panic(&message, &data.name);
                     qc::Value::exception().as_js_value()
                 }
             };
@@ -421,12 +930,18 @@ impl<'q> Context<'q> {
         unsafe {
             log::debug!("save pointer to ArrayBuffer");
             // Box of Sized
-            let func_box = Box::new(func);
-            let cb = self.0.new_array_buffer_from_boxed(func_box);
+            let data = Box::new(CallbackData {
+                func,
+                name: name.to_owned(),
+            });
+            let cb = self.0.new_array_buffer_from_boxed(data);
             let _cb: Object = self.wrap_result(cb)?; // check errors
             log::debug!("new c function data");
             let cfd = self.0.new_c_function_data(Some(call::<R>), length, 0, &[cb]);
-            self.wrap_result(cfd)
+            let f: Object = self.wrap_result(cfd)?;
+            // C function data has `length` but no `name`
+            f.define_property_value_from("name", name, PropFlags::CONFIGURABLE)?;
+            Ok(f)
         }
     }
 
@@ -440,6 +955,17 @@ impl<'q> Context<'q> {
         unsafe { Value::from_raw_parts(qc::Value::null(), self.0).into_unchecked() }
     }
 
+    // promise
+
+    /// Creates a promise and returns it with its `resolve` and `reject` functions.
+    pub fn new_promise_capability(self) -> Result<(Object<'q>, Object<'q>, Object<'q>)> {
+        let (promise, [resolve, reject]) = self.0.new_promise_capability();
+        let resolve = Value::from_raw_parts(resolve, self.0);
+        let reject = Value::from_raw_parts(reject, self.0);
+        let promise: Object = unsafe { self.wrap_result(promise)? };
+        unsafe { Ok((promise, resolve.into_unchecked(), reject.into_unchecked())) }
+    }
+
     // exception
 
     /// Returns the pending exception (cannot be called twice).
@@ -504,6 +1030,94 @@ impl<'q> Context<'q> {
         )
     }
 
+    // console
+
+    /// Defines the global `console` whose messages are formatted like Node's `util.format` and written to `sink`.
+    #[cfg(feature = "console")]
+    #[inline]
+    pub fn install_console<S: ConsoleSink + 'static>(self, sink: S) -> Result<Object<'q>> {
+        console::install(self, sink)
+    }
+
+    // timers
+
+    /// Defines `setTimeout`, `clearTimeout`, `setInterval`, `clearInterval` and `queueMicrotask`.
+    /// Timers run in `run_until_idle` and `run_for`.
+    #[cfg(feature = "timers")]
+    #[inline]
+    pub fn install_timers(self, clock: Clock) -> Result<()> {
+        timers::install(self, clock)
+    }
+
+    /// Runs pending jobs, timers and woken async iterators until no timer remains.
+    /// It never returns while an interval is active, but it does not wait for streams; see `wait_for_streams`.
+    #[cfg(feature = "timers")]
+    #[inline]
+    pub fn run_until_idle(self) -> Result<()> {
+        timers::run_until_idle(self)
+    }
+
+    /// Runs pending jobs and timers which are due within `duration`.
+    #[cfg(feature = "timers")]
+    #[inline]
+    pub fn run_for(self, duration: Duration) -> Result<()> {
+        timers::run_for(self, duration)
+    }
+
+    /// Returns the number of active timers.
+    #[cfg(feature = "timers")]
+    #[inline]
+    pub fn timer_count(self) -> usize {
+        timers::count(self)
+    }
+
+    /// Polls `future` to completion, running pending jobs, timers and async iterators while it waits.
+    /// It blocks while nothing but another thread can wake `future`.
+    #[cfg(feature = "stream")]
+    #[inline]
+    pub fn block_on<F: Future>(self, future: F) -> Result<F::Output> {
+        stream::block_on(self, future)
+    }
+
+    /// Blocks until the stream of a waiting async iterator is woken, then resolves the requests it can.
+    /// Returns `false` without blocking if no async iterator is waiting.
+    #[cfg(feature = "stream")]
+    #[inline]
+    pub fn wait_for_streams(self) -> Result<bool> {
+        stream::wait(self)
+    }
+
+    // worker
+
+    /// Defines the global `Worker` class. `new Worker(moduleName)` runs the module on a new thread with its own runtime.
+    #[cfg(feature = "worker")]
+    #[inline]
+    pub fn install_worker(self, options: WorkerOptions) -> Result<Object<'q>> {
+        worker::install(self, options)
+    }
+
+    /// Dispatches the queued events of workers created in this context without blocking.
+    /// Returns the number of dispatched events.
+    #[cfg(feature = "worker")]
+    #[inline]
+    pub fn dispatch_worker_events(self) -> Result<usize> {
+        worker::dispatch(self, None)
+    }
+
+    /// Waits up to `timeout` for events of workers created in this context and dispatches them.
+    #[cfg(feature = "worker")]
+    #[inline]
+    pub fn wait_worker_events(self, timeout: Duration) -> Result<usize> {
+        worker::dispatch(self, Some(timeout))
+    }
+
+    /// Returns the number of running workers created in this context.
+    #[cfg(feature = "worker")]
+    #[inline]
+    pub fn worker_count(self) -> usize {
+        worker::count(self)
+    }
+
     // class
 
     pub(crate) fn register_class<T: Class + 'static>(&mut self) -> Result<qc::ClassId> {
@@ -542,17 +1156,30 @@ impl<'q> Context<'q> {
 pub struct ContextScope<'r>(Context<'r>);
 
 impl<'r> ContextScope<'r> {
-    fn new_internal(rt: Runtime, raw: bool) -> ContextScope {
+    fn new_internal(mut rt: Runtime, raw: bool) -> ContextScope {
+        rt.mark_context_created();
         let ctx = if raw {
             qc::Context::new_raw(rt.into())
         } else {
             qc::Context::new(rt.into())
         };
         let opaque = Box::new(ContextOpaque {
+            #[cfg(feature = "worker")]
+            id: NEXT_CONTEXT_ID.fetch_add(1, Ordering::Relaxed),
             registered_classes: HashSet::new(),
+            #[cfg(feature = "timers")]
+            timers: None,
+            #[cfg(feature = "stream")]
+            async_iterators: None,
+            intrinsics: HashMap::new(),
         });
         ctx.set_opaque(Box::into_raw(opaque) as *mut c_void);
-        ContextScope(Context(ctx))
+        let ctx = Context(ctx);
+        if !raw {
+            // captured before scripts can replace them
+            let _ = ctx.capture_intrinsics();
+        }
+        ContextScope(ctx)
     }
 
     pub fn new(rt: Runtime) -> ContextScope {
@@ -563,7 +1190,7 @@ impl<'r> ContextScope<'r> {
         Self::new_internal(rt, true)
     }
 
-    pub fn new_with_scope(rts: &RuntimeScope) -> ContextScope {
+    pub fn new_with_scope(rts: &RuntimeScope) -> ContextScope<'_> {
         ContextScope::new(rts.get())
     }
 
@@ -585,7 +1212,14 @@ impl Drop for ContextScope<'_> {
     fn drop(&mut self) {
         unsafe {
             // opaque must be bound until values in the context will be freed
-            let _opaque = Box::from_raw((self.0).0.opaque() as *mut ContextOpaque);
+            let mut opaque = Box::from_raw((self.0).0.opaque() as *mut ContextOpaque);
+            #[cfg(feature = "timers")]
+            drop(opaque.timers.take());
+            #[cfg(feature = "stream")]
+            drop(opaque.async_iterators.take());
+            for (_, v) in opaque.intrinsics.drain() {
+                self.0 .0.free_value(v);
+            }
             qc::Context::free(self.0 .0)
         }
     }
@@ -597,4 +1231,16 @@ impl fmt::Debug for ContextScope<'_> {
     }
 }
 
+pub(crate) fn iterator_result<'q>(ctx: Context<'q>, value: Value<'q>, done: bool) -> Result<Value<'q>> {
+    let result = ctx.new_object()?;
+    result.set("value", value)?;
+    result.set("done", done)?;
+    Ok(result.into())
+}
+
+struct CallbackData<'q, R> {
+    func: Box<Callback<'q, 'q, R>>,
+    name: String,
+}
+
 pub(crate) type Callback<'q, 'a, R> = dyn Fn(Context<'q>, Value<'q>, &'a [Value<'q>]) -> Result<R> + 'a;
@@ -24,6 +24,8 @@ macro_rules! fn_add_intrinsic {
         #[inline]
         fn $f(self) {
             self.as_raw().$f();
+            // captured before scripts can replace them
+            let _ = self.capture_intrinsics();
         }
     };
 }
@@ -2,6 +2,7 @@ use std::{
     collections::{BTreeMap, HashMap},
     convert::TryFrom,
     hash::{BuildHasher, Hash},
+    ops::{Deref, DerefMut},
 };
 
 use crate::{atom::Atom, context::Context, result::Result, value::Value, Error, ErrorKind};
@@ -51,7 +52,24 @@ impl<'q, K: Ord + IntoQjAtom<'q>, V: IntoQj<'q>> IntoQj<'q> for BTreeMap<K, V> {
 }
 
 pub trait FromQj<'q>: Sized {
+    /// `true` if the parameter may be omitted. Such parameters are not counted in the `length` of functions.
+    #[doc(hidden)]
+    const OPTIONAL: bool = false;
+
+    /// `true` if the parameter takes all the remaining arguments, which only the last parameter may do.
+    #[doc(hidden)]
+    const REST: bool = false;
+
     fn from_qj(v: Value<'q>) -> Result<Self>;
+
+    /// Converts the `index`-th argument of a function call.
+    #[doc(hidden)]
+    fn from_qj_args(args: &[Value<'q>], index: usize) -> Result<Self> {
+        match args.get(index) {
+            Some(v) => Self::from_qj(v.clone()),
+            None => Err(Error::with_str(ErrorKind::RangeError, &format!("index: {}", index))),
+        }
+    }
 }
 
 impl<'q> FromQj<'q> for Value<'q> {
@@ -70,6 +88,8 @@ where
 }
 
 impl<'q, T: FromQj<'q>> FromQj<'q> for Option<T> {
+    const OPTIONAL: bool = true;
+
     fn from_qj(v: Value<'q>) -> Result<Self> {
         if v.is_nullish() {
             Ok(None)
@@ -77,15 +97,20 @@ impl<'q, T: FromQj<'q>> FromQj<'q> for Option<T> {
             T::from_qj(v).map(Some)
         }
     }
+
+    fn from_qj_args(args: &[Value<'q>], index: usize) -> Result<Self> {
+        match args.get(index) {
+            Some(v) => Self::from_qj(v.clone()),
+            None => Ok(None),
+        }
+    }
 }
 
 impl<'q, T: FromQj<'q>> FromQj<'q> for Vec<T> {
     fn from_qj(v: Value<'q>) -> Result<Self> {
         if v.is_array() {
-            let len: i32 = v.get("length")?;
-            (0..len)
-                .map(|i| v.get(i).and_then(T::from_qj))
-                .collect::<Result<Vec<_>>>()
+            let len: u32 = v.get::<_, f64>("length")? as u32;
+            (0..len).map(|i| v.get_index(i)).collect::<Result<Vec<_>>>()
         } else {
             Err(Error::with_str(ErrorKind::TypeError, "not array"))
         }
@@ -114,7 +139,67 @@ impl<'q, K: Ord + FromQj<'q>, V: FromQj<'q>> FromQj<'q> for BTreeMap<K, V> {
     }
 }
 
+/// `Rest` receives the remaining arguments of a function call like `...rest`.
+/// It must be the last element of a `FromQjMulti` tuple, which is checked at compile time.
+///
+/// ```compile_fail
+/// use quijine::{Rest, Value};
+///
+/// quijine::context(|ctx| {
+///     ctx.new_function_auto(|_ctx, _this: Value, (_rest, _last): (Rest<i32>, i32)| Ok(()), "f")?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rest<T>(pub Vec<T>);
+
+impl<T> Rest<T> {
+    #[inline]
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for Rest<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Rest<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'q, T: FromQj<'q>> FromQj<'q> for Rest<T> {
+    const OPTIONAL: bool = true;
+    const REST: bool = true;
+
+    /// Converts an array.
+    fn from_qj(v: Value<'q>) -> Result<Self> {
+        Vec::from_qj(v).map(Rest)
+    }
+
+    fn from_qj_args(args: &[Value<'q>], index: usize) -> Result<Self> {
+        let rest = args.get(index..).unwrap_or_default();
+        rest.iter()
+            .map(|v| T::from_qj(v.clone()))
+            .collect::<Result<_>>()
+            .map(Rest)
+    }
+}
+
 pub trait FromQjMulti<'q>: Sized {
+    /// The number of parameters before the first optional one, used as the `length` of functions.
+    const LENGTH: i32 = 0;
+
+    #[doc(hidden)]
+    const REST_IS_LAST: () = ();
+
     fn from_qj_multi(v: &[Value<'q>]) -> Result<Self>;
 }
 
@@ -136,10 +221,30 @@ macro_rules! impl_from_qj_multi_for_tuple {
         where
             $($t: FromQj<'q>),+
         {
+            // fails to compile the conversion of a tuple with `Rest` before its last element
+            const REST_IS_LAST: () = {
+                let rest = [$($t::REST),+];
+                let mut i = 0;
+                while i + 1 < rest.len() {
+                    assert!(!rest[i], "`Rest` must be the last element of the arguments");
+                    i += 1;
+                }
+            };
+
+            const LENGTH: i32 = {
+                let optional = [$($t::OPTIONAL),+];
+                let mut n = 0;
+                while n < optional.len() && !optional[n] {
+                    n += 1;
+                }
+                n as i32
+            };
+
             fn from_qj_multi(v: &[Value<'q>]) -> Result<Self> {
-                let err = |i: usize| move || Error::with_str(ErrorKind::RangeError, &format!("index: {}", i));
+                #[allow(clippy::let_unit_value)]
+                let () = Self::REST_IS_LAST;
                 Ok((
-                    $($t::from_qj((v.get($k).ok_or_else(err($k))?.clone()))?,)+
+                    $($t::from_qj_args(v, $k)?,)+
                 ))
             }
         }
@@ -150,6 +255,14 @@ impl_from_qj_multi_for_tuple! { for (0 => T0) }
 impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1) }
 impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2) }
 impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8, 9 => T9) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8, 9 => T9, 10 => T10) }
+impl_from_qj_multi_for_tuple! { for (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8, 9 => T9, 10 => T10, 11 => T11) }
 
 pub trait IntoQjMulti<'q> {
     type Target: AsRef<[Value<'q>]>;
@@ -207,7 +320,15 @@ impl<'q> IntoQjMulti<'q> for () {
 impl_into_qj_multi_for_tuple! { for 1 => (0 => T0) }
 impl_into_qj_multi_for_tuple! { for 2 => (0 => T0, 1 => T1) }
 impl_into_qj_multi_for_tuple! { for 3 => (0 => T0, 1 => T1, 2 => T2) }
-impl_into_qj_multi_for_tuple! { for 4 => (0 => T0, 1 => T1, 2 => T2, 3 => T4) }
+impl_into_qj_multi_for_tuple! { for 4 => (0 => T0, 1 => T1, 2 => T2, 3 => T3) }
+impl_into_qj_multi_for_tuple! { for 5 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4) }
+impl_into_qj_multi_for_tuple! { for 6 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5) }
+impl_into_qj_multi_for_tuple! { for 7 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6) }
+impl_into_qj_multi_for_tuple! { for 8 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7) }
+impl_into_qj_multi_for_tuple! { for 9 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8) }
+impl_into_qj_multi_for_tuple! { for 10 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8, 9 => T9) }
+impl_into_qj_multi_for_tuple! { for 11 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8, 9 => T9, 10 => T10) }
+impl_into_qj_multi_for_tuple! { for 12 => (0 => T0, 1 => T1, 2 => T2, 3 => T3, 4 => T4, 5 => T5, 6 => T6, 7 => T7, 8 => T8, 9 => T9, 10 => T10, 11 => T11) }
 
 pub trait IntoQjAtom<'q> {
     fn into_qj_atom(self, ctx: Context<'q>) -> Result<Atom<'q>>;
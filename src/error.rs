@@ -1,4 +1,4 @@
-use crate::Value;
+use crate::{thrown::ThrownValue, Context, Value};
 use std::{error::Error as StdError, fmt, result::Result as StdResult, sync::Arc};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -30,6 +30,80 @@ impl fmt::Display for ErrorKind {
     }
 }
 
+/// `JsStackFrame` is a frame of the `stack` of a JS error. A frame without a file is a native function.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JsStackFrame {
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl JsStackFrame {
+    /// Parses a line like `    at f (file.js:3:5)`.
+    pub fn parse(line: &str) -> Option<JsStackFrame> {
+        let line = line.trim().strip_prefix("at ")?;
+        let (function, location) = match line.rfind(" (") {
+            Some(i) if line.ends_with(')') => (&line[..i], &line[i + 2..line.len() - 1]),
+            _ => ("", line),
+        };
+        let function = Some(function.to_owned()).filter(|f| !f.is_empty());
+        if location == "native" {
+            return Some(JsStackFrame {
+                function,
+                file: None,
+                line: None,
+                column: None,
+            });
+        }
+        let mut file = location;
+        let mut numbers = Vec::new();
+        while numbers.len() < 2 {
+            match file.rsplit_once(':').map(|(rest, n)| (rest, n.parse::<u32>())) {
+                Some((rest, Ok(n))) => {
+                    numbers.push(n);
+                    file = rest;
+                }
+                _ => break,
+            }
+        }
+        numbers.reverse();
+        Some(JsStackFrame {
+            function,
+            file: Some(file.to_owned()),
+            line: numbers.first().cloned(),
+            column: numbers.get(1).cloned(),
+        })
+    }
+
+    /// Parses the `stack` property of an error. Lines which are not frames are skipped.
+    pub fn parse_stack(stack: &str) -> Vec<JsStackFrame> {
+        stack.lines().filter_map(JsStackFrame::parse).collect()
+    }
+}
+
+impl fmt::Display for JsStackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.write_str("at ")?;
+        if let Some(function) = self.function.as_ref() {
+            f.write_fmt(format_args!("{} ", function))?;
+        }
+        match self.file.as_ref() {
+            None => f.write_str("(native)"),
+            Some(file) => {
+                f.write_fmt(format_args!("({}", file))?;
+                if let Some(line) = self.line {
+                    f.write_fmt(format_args!(":{}", line))?;
+                    if let Some(column) = self.column {
+                        f.write_fmt(format_args!(":{}", column))?;
+                    }
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct JsErrorData {
     pub name: Option<String>,
@@ -37,6 +111,26 @@ pub struct JsErrorData {
     pub file_name: Option<String>,
     pub line_number: Option<i32>,
     pub stack: Option<String>,
+    details: Box<JsErrorDetails>,
+}
+
+/// The parts of `JsErrorData` which are kept apart to keep `Error` small.
+#[derive(Debug)]
+struct JsErrorDetails {
+    frames: Vec<JsStackFrame>,
+    /// The thrown value itself, which is rethrown unchanged when the error goes back to JS.
+    thrown: Option<ThrownValue>,
+}
+
+impl JsErrorData {
+    /// Returns the frames parsed from `stack`.
+    pub fn frames(&self) -> &[JsStackFrame] {
+        &self.details.frames
+    }
+
+    pub(crate) fn take_frames(&mut self) -> Vec<JsStackFrame> {
+        std::mem::take(&mut self.details.frames)
+    }
 }
 
 impl fmt::Display for JsErrorData {
@@ -44,9 +138,12 @@ impl fmt::Display for JsErrorData {
         let empty = &"".to_owned();
         let name = self.name.as_ref().unwrap_or(empty);
         let message = self.message.as_ref().unwrap_or(empty);
-        f.write_fmt(format_args!("{}: {}", name, message))?;
-        if let Some(stack) = self.stack.as_ref() {
-            f.write_fmt(format_args!("\n{}", stack))?;
+        if self.name.is_some() {
+            f.write_fmt(format_args!("{}: ", name))?;
+        }
+        f.write_str(message)?;
+        for frame in self.frames() {
+            f.write_fmt(format_args!("\n    {}", frame))?;
         }
         Ok(())
     }
@@ -80,6 +177,7 @@ impl fmt::Debug for ErrorValue {
 pub struct Error {
     pub kind: ErrorKind,
     pub value: ErrorValue,
+    code: Option<String>,
 }
 
 impl Error {
@@ -87,6 +185,7 @@ impl Error {
         Error {
             kind,
             value: ErrorValue::String(message.to_owned()),
+            code: None,
         }
     }
 
@@ -94,6 +193,16 @@ impl Error {
         Error {
             kind,
             value: ErrorValue::External(external.into().into()),
+            code: None,
+        }
+    }
+
+    /// Creates an error whose `code` comes from `ErrorCode`.
+    pub fn with_code<E: ErrorCode>(kind: ErrorKind, external: E) -> Error {
+        let code = external.code();
+        Error {
+            code,
+            ..Error::with_external(kind, external)
         }
     }
 
@@ -111,21 +220,57 @@ impl Error {
                 Ok(s) => s,
                 Err(e) => format!(r##"{{"name":"SystemError","message":"can't convert error: {}"}}"##, e),
             }),
+            code: None,
         }
     }
 
     pub fn from_js_error<'q, T: Into<Value<'q>>>(kind: ErrorKind, value: T) -> Error {
         let value: Value<'q> = value.into();
-        let value = JsErrorData {
-            name: value.get("name").ok(),
-            message: value.get("message").ok(),
-            file_name: value.get("fileName").ok(),
-            line_number: value.get("lineNumber").ok(),
-            stack: value.get("stack").ok(),
+        let thrown = Some(value.context().runtime().thrown_registry().hold(&value));
+        let value = if value.is_error() {
+            let stack: Option<String> = value.get("stack").ok();
+            JsErrorData {
+                name: value.get("name").ok(),
+                message: value.get("message").ok(),
+                file_name: value.get("fileName").ok(),
+                line_number: value.get("lineNumber").ok(),
+                details: Box::new(JsErrorDetails {
+                    frames: stack.as_deref().map(JsStackFrame::parse_stack).unwrap_or_default(),
+                    thrown,
+                }),
+                stack,
+            }
+        } else {
+            // e.g. `throw 42`
+            JsErrorData {
+                name: None,
+                message: value.to_string().ok(),
+                file_name: None,
+                line_number: None,
+                stack: None,
+                details: Box::new(JsErrorDetails {
+                    frames: Vec::new(),
+                    thrown,
+                }),
+            }
         };
         Error {
             kind,
             value: ErrorValue::JsError(value),
+            code: None,
+        }
+    }
+
+    /// Returns the `code` property of the JS error thrown for this error.
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// Returns the value thrown in JS if the error comes from the runtime of `ctx`.
+    pub fn thrown_value<'q>(&self, ctx: Context<'q>) -> Option<Value<'q>> {
+        match &self.value {
+            ErrorValue::JsError(data) => data.details.thrown.as_ref()?.value(ctx),
+            _ => None,
         }
     }
 }
@@ -162,6 +307,12 @@ impl fmt::Debug for Error {
         fmt::Display::fmt(self, f)
     }
 }
+
+/// `ErrorCode` provides the `code` property of JS errors thrown for Rust errors, like `ENOENT` of Node.
+pub trait ErrorCode: StdError + Send + Sync + 'static {
+    fn code(&self) -> Option<String>;
+}
+
 pub trait ExternalError {
     fn to_qj_err(self) -> Error;
 }
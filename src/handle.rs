@@ -0,0 +1,190 @@
+use crate::{
+    context::Context,
+    error::{Error, ErrorKind},
+    result::Result,
+    runtime::{Runtime, RuntimeScope},
+};
+use quijine_core as qc;
+use std::{
+    fmt,
+    result::Result as StdResult,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+type Job = Box<dyn for<'q> FnOnce(Context<'q>) + Send>;
+
+struct Shared {
+    interrupted: AtomicBool,
+}
+
+/// `RuntimeHandle` is a handle of a runtime owned by a dedicated thread.
+///
+/// The thread keeps one context alive, runs posted jobs one by one and exits when all handles are dropped.
+#[derive(Clone)]
+pub struct RuntimeHandle {
+    sender: Sender<Job>,
+    shared: Arc<Shared>,
+}
+
+impl RuntimeHandle {
+    /// Spawns a new thread which owns a runtime and a context.
+    #[inline]
+    pub fn spawn() -> Result<RuntimeHandle> {
+        Self::spawn_with(|_rt| Ok(()), |_ctx| Ok(()))
+    }
+
+    /// Spawns a new thread, and initializes its runtime and context (e.g. limits, global objects).
+    pub fn spawn_with<R, C>(init_runtime: R, init_context: C) -> Result<RuntimeHandle>
+    where
+        R: FnOnce(Runtime) -> Result<()> + Send + 'static,
+        C: for<'q> FnOnce(Context<'q>) -> Result<()> + Send + 'static,
+    {
+        let (sender, receiver) = channel::<Job>();
+        let shared = Arc::new(Shared {
+            interrupted: AtomicBool::new(false),
+        });
+        let (ready_sender, ready_receiver) = sync_channel::<Result<()>>(1);
+        let thread_shared = shared.clone();
+        thread::Builder::new()
+            .name("quijine-runtime".to_owned())
+            .spawn(move || {
+                let rts = RuntimeScope::new();
+                let rt = rts.get();
+                rt.set_can_block(true);
+                let interrupt_shared = thread_shared.clone();
+                rt.set_interrupt_handler(move || interrupt_shared.interrupted.swap(false, Ordering::SeqCst));
+                if let Err(e) = rts.run(init_runtime) {
+                    let _ = ready_sender.send(Err(e));
+                    return;
+                }
+                let ctxs = rts.new_context_scope();
+                let ctx = ctxs.get();
+                if let Err(e) = init_context(ctx) {
+                    let _ = ready_sender.send(Err(e));
+                    return;
+                }
+                let _ = ready_sender.send(Ok(()));
+                for job in receiver {
+                    // an interruption requested while idle must not abort the next job
+                    thread_shared.interrupted.store(false, Ordering::SeqCst);
+                    // a panicking job must not take the runtime down with the other jobs
+                    if let Err(message) = qc::catch_panic(|| job(ctx)) {
+                        log::error!("panic in a posted job: {}", message);
+                    }
+                    if let Err(e) = rt.run_pending_jobs() {
+                        log::warn!("pending job failed: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| Error::with_external(ErrorKind::InternalError, e))?;
+        ready_receiver.recv().map_err(|_| terminated())??;
+        Ok(RuntimeHandle { sender, shared })
+    }
+
+    /// Posts a job without waiting for its completion.
+    pub fn post<F>(&self, f: F) -> Result<()>
+    where
+        F: for<'q> FnOnce(Context<'q>) + Send + 'static,
+    {
+        self.sender.send(Box::new(f)).map_err(|_| terminated())
+    }
+
+    /// Posts a job and returns a receiver of its result.
+    pub fn spawn_job<F, R>(&self, f: F) -> Result<JobResult<R>>
+    where
+        F: for<'q> FnOnce(Context<'q>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = sync_channel(1);
+        self.post(move |ctx| {
+            let _ = sender.send(catch_job(|| f(ctx)));
+        })?;
+        Ok(JobResult::new(receiver))
+    }
+
+    /// Posts a job and blocks the current thread until it completes.
+    #[inline]
+    pub fn call<F, R>(&self, f: F) -> Result<R>
+    where
+        F: for<'q> FnOnce(Context<'q>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.spawn_job(f)?.join()
+    }
+
+    /// Aborts the JavaScript code running on the runtime thread.
+    /// The running job receives an uncatchable `InternalError: interrupted`.
+    #[inline]
+    pub fn interrupt(&self) {
+        self.shared.interrupted.store(true, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Debug for RuntimeHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.write_str("RuntimeHandle")
+    }
+}
+
+/// `JobResult` is a oneshot receiver of a result of a job.
+pub struct JobResult<R>(Receiver<Result<R>>);
+
+impl<R> JobResult<R> {
+    #[inline]
+    pub(crate) fn new(receiver: Receiver<Result<R>>) -> Self {
+        JobResult(receiver)
+    }
+
+    /// Blocks the current thread until the job completes.
+    #[inline]
+    pub fn join(self) -> Result<R> {
+        self.0.recv().map_err(|_| terminated())?
+    }
+
+    /// Returns `None` if the job is not completed yet.
+    #[inline]
+    pub fn try_join(&self) -> Option<Result<R>> {
+        match self.0.try_recv() {
+            Ok(v) => Some(v),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(terminated())),
+        }
+    }
+
+    /// Returns `None` if the job is not completed within `timeout`.
+    #[inline]
+    pub fn join_timeout(&self, timeout: Duration) -> Option<Result<R>> {
+        match self.0.recv_timeout(timeout) {
+            Ok(v) => Some(v),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Err(terminated())),
+        }
+    }
+}
+
+impl<R> fmt::Debug for JobResult<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.write_str("JobResult")
+    }
+}
+
+/// Runs a job, turning a panic into an error of the job.
+pub(crate) fn catch_job<R, F: FnOnce() -> Result<R>>(f: F) -> Result<R> {
+    qc::catch_panic(f).unwrap_or_else(|message| {
+        Err(Error::with_str(
+            ErrorKind::InternalError,
+            &format!("panic in a job: {}", message),
+        ))
+    })
+}
+
+#[inline]
+pub(crate) fn terminated() -> Error {
+    Error::with_str(ErrorKind::InternalError, "the runtime thread has terminated")
+}
@@ -0,0 +1,705 @@
+use crate::{
+    context::{Context, Intrinsic},
+    flags::GpnFlags,
+    result::Result,
+    types::Tag,
+    value::Value,
+};
+use quijine_core::PromiseState;
+use std::{collections::HashMap, ffi::c_void};
+
+/// `InspectOptions` configures `Value::inspect`. The defaults follow Node's `util.inspect`.
+#[derive(Clone, Debug)]
+pub struct InspectOptions {
+    depth: Option<usize>,
+    colors: bool,
+    max_array_length: Option<usize>,
+    break_length: usize,
+}
+
+impl InspectOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many times nested objects are expanded. `None` expands them all. Defaults to 2.
+    #[inline]
+    pub fn depth(mut self, depth: Option<usize>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Styles the output with ANSI color codes. Defaults to `false`.
+    #[inline]
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Sets how many elements of arrays, typed arrays, `Map`s and `Set`s are shown. `None` shows them all.
+    /// Defaults to 100.
+    #[inline]
+    pub fn max_array_length(mut self, max_array_length: Option<usize>) -> Self {
+        self.max_array_length = max_array_length;
+        self
+    }
+
+    /// Sets the width above which entries are split into lines. Defaults to 80.
+    #[inline]
+    pub fn break_length(mut self, break_length: usize) -> Self {
+        self.break_length = break_length;
+        self
+    }
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        InspectOptions {
+            depth: Some(2),
+            colors: false,
+            max_array_length: Some(100),
+            break_length: 80,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Style {
+    Number,
+    String,
+    Boolean,
+    Undefined,
+    Null,
+    Symbol,
+    Special,
+    Date,
+    RegExp,
+}
+
+impl Style {
+    fn codes(self) -> (u8, u8) {
+        match self {
+            Style::Number | Style::Boolean => (33, 39),
+            Style::String | Style::Symbol => (32, 39),
+            Style::Undefined => (90, 39),
+            Style::Null => (1, 22),
+            Style::Special => (36, 39),
+            Style::Date => (35, 39),
+            Style::RegExp => (31, 39),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Object,
+    BoxedString,
+    Array,
+    TypedArray,
+    Map,
+    Set,
+}
+
+/// `Inspector` renders values like Node's `util.inspect`.
+pub(crate) struct Inspector<'q> {
+    ctx: Context<'q>,
+    options: InspectOptions,
+    object_to_string: Value<'q>,
+    function_to_string: Value<'q>,
+    // objects being rendered, to detect cycles
+    seen: Vec<*mut c_void>,
+    refs: HashMap<*mut c_void, usize>,
+}
+
+impl<'q> Inspector<'q> {
+    pub(crate) fn new(ctx: Context<'q>, options: InspectOptions) -> Result<Self> {
+        Ok(Inspector {
+            ctx,
+            options,
+            object_to_string: ctx.require_intrinsic(Intrinsic::ObjectToString)?,
+            function_to_string: ctx.require_intrinsic(Intrinsic::FunctionToString)?,
+            seen: Vec::new(),
+            refs: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn inspect(&mut self, v: &Value<'q>) -> Result<String> {
+        self.seen.clear();
+        self.refs.clear();
+        self.format_value(v, 0)
+    }
+
+    fn stylize(&self, s: &str, style: Style) -> String {
+        if !self.options.colors {
+            return s.to_owned();
+        }
+        let (open, close) = style.codes();
+        format!("\x1b[{}m{}\x1b[{}m", open, s, close)
+    }
+
+    fn format_value(&mut self, v: &Value<'q>, depth: usize) -> Result<String> {
+        match v.tag() {
+            Tag::Object => self.format_object(v, depth),
+            Tag::String => Ok(self.stylize(&quote(&v.to_string()?), Style::String)),
+            _ => self.format_primitive(v),
+        }
+    }
+
+    fn format_primitive(&self, v: &Value<'q>) -> Result<String> {
+        let style = match v.tag() {
+            Tag::Undefined | Tag::Uninitialized => Style::Undefined,
+            Tag::Null => Style::Null,
+            Tag::Bool => Style::Boolean,
+            Tag::Symbol => Style::Symbol,
+            Tag::String => Style::String,
+            _ => Style::Number,
+        };
+        Ok(self.stylize(&format_primitive(v)?, style))
+    }
+
+    fn format_object(&mut self, v: &Value<'q>, depth: usize) -> Result<String> {
+        let ptr = v.as_raw().ptr().unwrap();
+        if self.seen.contains(&ptr) {
+            let n = self.refs.len() + 1;
+            let n = *self.refs.entry(ptr).or_insert(n);
+            return Ok(self.stylize(&format!("[Circular *{}]", n), Style::Special));
+        }
+        let tag = self.to_string_tag(v)?;
+        if v.is_function() {
+            let s = self.format_function(v, &tag)?;
+            return Ok(self.stylize(&s, Style::Special));
+        }
+        if v.is_error() {
+            return format_error(v);
+        }
+        let boxed = match tag.as_str() {
+            "Number" | "String" | "Boolean" => self.unbox(v, &tag),
+            _ => None,
+        };
+        let kind = match tag.as_str() {
+            _ if v.is_array() => Kind::Array,
+            "String" if boxed.is_some() => Kind::BoxedString,
+            "Map" => Kind::Map,
+            "Set" => Kind::Set,
+            t if TYPED_ARRAYS.contains(&t) => Kind::TypedArray,
+            _ => Kind::Object,
+        };
+        let unboxed = boxed.is_some();
+        let special = match (tag.as_str(), boxed) {
+            (_, Some(boxed)) => Some(self.format_boxed(&boxed, &tag)?),
+            ("Date", _) => self.format_date(v)?,
+            ("RegExp", _) => Some(self.stylize(&self.call_method(v, "toString")?, Style::RegExp)),
+            _ => None,
+        };
+        let mut prefix = self.prefix(v)?;
+        if unboxed && prefix.as_deref() == Some(tag.as_str()) {
+            // the constructor is already named by `[Number: 1]`
+            prefix = None;
+        } else if !matches!(kind, Kind::Object | Kind::BoxedString) {
+            let name = prefix.take().unwrap_or_else(|| tag.clone());
+            let size: Value = v.get(if kind == Kind::Map || kind == Kind::Set {
+                "size"
+            } else {
+                "length"
+            })?;
+            let size = size.to_f64()?;
+            prefix = match kind {
+                Kind::Array if name == "Array" => None,
+                _ => Some(format!("{}({})", name, size)),
+            };
+        }
+        if self.options.depth.map_or(false, |max| depth > max) {
+            let s = match (special, kind) {
+                (Some(s), _) => s,
+                (None, Kind::Array) => self.stylize("[Array]", Style::Special),
+                (None, _) => self.stylize(&format!("[{}]", prefix.unwrap_or_else(|| tag.clone())), Style::Special),
+            };
+            return Ok(s);
+        }
+        self.seen.push(ptr);
+        let entries = self.entries(v, kind, depth);
+        self.seen.pop();
+        let entries = entries?;
+        let mut start = String::new();
+        if let Some(s) = special {
+            if entries.is_empty() {
+                return Ok(s);
+            }
+            start = format!("{} ", s);
+        }
+        if let Some(prefix) = prefix {
+            start = format!("{}{} ", start, prefix);
+        }
+        let bracket = matches!(kind, Kind::Array | Kind::TypedArray);
+        start.push_str(if bracket { "[" } else { "{" });
+        let end = if bracket { "]" } else { "}" };
+        let mut s = if entries.is_empty() {
+            format!("{}{}", start, end)
+        } else {
+            self.reduce_to_single_string(&start, &entries, end)
+        };
+        if let Some(n) = self.refs.get(&ptr) {
+            s = format!("<ref *{}> {}", n, s);
+        }
+        Ok(s)
+    }
+
+    fn entries(&mut self, v: &Value<'q>, kind: Kind, depth: usize) -> Result<Vec<String>> {
+        let mut entries = Vec::new();
+        let max = self.options.max_array_length.unwrap_or(usize::MAX);
+        let flags = GpnFlags::STRING_MASK | GpnFlags::SYMBOL_MASK | GpnFlags::ENUM_ONLY;
+        // the indices of a typed array are not enumerated because it has no holes and may be huge
+        let props = if kind == Kind::TypedArray {
+            Vec::new()
+        } else {
+            v.own_property_names(flags)?
+        };
+        let mut len = 0;
+        match kind {
+            Kind::Array => {
+                len = v.get::<_, Value>("length")?.to_f64()? as u32;
+                // holes are counted from the present indices, so a sparse array is not scanned index by index
+                let mut indices = Vec::new();
+                for prop in &props {
+                    if let Some(i) = array_index(&prop.atom().to_value()?)? {
+                        if i < len {
+                            indices.push(i);
+                        }
+                    }
+                }
+                indices.sort_unstable();
+                let mut indices = indices.into_iter().peekable();
+                let mut i = 0;
+                while i < len && entries.len() < max {
+                    match indices.peek() {
+                        Some(&j) if j == i => {
+                            indices.next();
+                            entries.push(self.format_value(&v.get(i)?, depth + 1)?);
+                            i += 1;
+                        }
+                        next => {
+                            let end = next.copied().unwrap_or(len);
+                            entries.push(self.empty_items((end - i) as usize));
+                            i = end;
+                        }
+                    }
+                }
+                if i < len {
+                    entries.push(more_items((len - i) as usize));
+                }
+            }
+            Kind::TypedArray => {
+                len = v.get::<_, Value>("length")?.to_f64()? as u32;
+                let n = len.min(max.try_into().unwrap_or(u32::MAX));
+                for i in 0..n {
+                    entries.push(self.format_value(&v.get(i)?, depth + 1)?);
+                }
+                if n < len {
+                    entries.push(more_items((len - n) as usize));
+                }
+            }
+            Kind::Map | Kind::Set => {
+                let mut count = 0;
+                for item in v.iterator()? {
+                    let item = item?;
+                    count += 1;
+                    if count > max {
+                        continue;
+                    }
+                    entries.push(if kind == Kind::Map {
+                        let key = self.format_value(&item.get(0)?, depth + 1)?;
+                        format!("{} => {}", key, self.format_value(&item.get(1)?, depth + 1)?)
+                    } else {
+                        self.format_value(&item, depth + 1)?
+                    });
+                }
+                if count > max {
+                    entries.push(more_items(count - max));
+                }
+            }
+            Kind::BoxedString => len = v.get::<_, Value>("length")?.to_f64()? as u32,
+            Kind::Object => {
+                if let Some(state) = v.promise_state() {
+                    let result = v.promise_result().unwrap();
+                    entries.push(match state {
+                        PromiseState::Pending => self.stylize("<pending>", Style::Special),
+                        PromiseState::Fulfilled => self.format_value(&result, depth + 1)?,
+                        PromiseState::Rejected => {
+                            let rejected = self.stylize("<rejected>", Style::Special);
+                            format!("{} {}", rejected, self.format_value(&result, depth + 1)?)
+                        }
+                    });
+                }
+            }
+        }
+        for prop in props {
+            let atom = prop.atom();
+            let key = atom.to_value()?;
+            let key = if key.tag() == Tag::Symbol {
+                format!("[{}]", self.format_primitive(&key)?)
+            } else {
+                let indexed = matches!(kind, Kind::Array | Kind::BoxedString);
+                if indexed && array_index(&key)?.map_or(false, |i| i < len) {
+                    continue;
+                }
+                format_key(&key.to_string()?)
+            };
+            let desc = match v.own_property(atom)? {
+                Some(desc) => desc,
+                None => continue,
+            };
+            let value = match desc.value_opt() {
+                Some(value) => self.format_value(value, depth + 1)?,
+                None => {
+                    let s = match (!desc.getter().is_undefined(), !desc.setter().is_undefined()) {
+                        (true, true) => "[Getter/Setter]",
+                        (true, false) => "[Getter]",
+                        _ => "[Setter]",
+                    };
+                    self.stylize(s, Style::Special)
+                }
+            };
+            entries.push(format!("{}: {}", key, value));
+        }
+        Ok(entries)
+    }
+
+    fn empty_items(&self, n: usize) -> String {
+        let s = format!("<{} empty item{}>", n, if n == 1 { "" } else { "s" });
+        self.stylize(&s, Style::Undefined)
+    }
+
+    fn format_function(&self, v: &Value<'q>, tag: &str) -> Result<String> {
+        let name = v.get::<_, Value>("name")?;
+        let name = if name.tag() == Tag::String {
+            name.to_string()?
+        } else {
+            String::new()
+        };
+        let source = self
+            .ctx
+            .call(self.function_to_string.clone(), v.clone(), &[])?
+            .to_string()?;
+        let kind = if source.starts_with("class") && source[5..].starts_with(|c: char| !c.is_alphanumeric()) {
+            "class"
+        } else {
+            tag
+        };
+        Ok(match (kind, name.is_empty()) {
+            ("class", true) => "[class (anonymous)]".to_owned(),
+            ("class", false) => format!("[class {}]", name),
+            (_, true) => format!("[{} (anonymous)]", kind),
+            (_, false) => format!("[{}: {}]", kind, name),
+        })
+    }
+
+    /// Returns the tag of `Object.prototype.toString`, e.g. `Array` of `[object Array]`.
+    fn to_string_tag(&self, v: &Value<'q>) -> Result<String> {
+        let s = self
+            .ctx
+            .call(self.object_to_string.clone(), v.clone(), &[])?
+            .to_string()?;
+        Ok(s.trim_start_matches("[object ").trim_end_matches(']').to_owned())
+    }
+
+    /// Returns the name of the class or the constructor unless the object is a plain object.
+    fn prefix(&self, v: &Value<'q>) -> Result<Option<String>> {
+        if let Some(name) = self.ctx.runtime().class_name_of(v) {
+            return Ok(Some(name.to_owned()));
+        }
+        let proto = v.prototype()?;
+        if proto.is_null() {
+            return Ok(Some("[Object: null prototype]".to_owned()));
+        }
+        let name = constructor_name(&proto)?;
+        Ok(name.filter(|name| name != "Object"))
+    }
+
+    /// Returns the primitive value of a `Number`, `String` or `Boolean` object, or `None` if `v` only
+    /// claims to be one by `Symbol.toStringTag`.
+    fn unbox(&self, v: &Value<'q>, tag: &str) -> Option<Value<'q>> {
+        let value_of = match tag {
+            "Number" => Intrinsic::NumberValueOf,
+            "String" => Intrinsic::StringValueOf,
+            _ => Intrinsic::BooleanValueOf,
+        };
+        let value_of = self.ctx.intrinsic(value_of)?;
+        self.ctx.call(value_of, v.clone(), &[]).ok()
+    }
+
+    fn format_boxed(&self, boxed: &Value<'q>, tag: &str) -> Result<String> {
+        let (s, style) = match boxed.tag() {
+            Tag::String => (quote(&boxed.to_string()?), Style::String),
+            Tag::Bool => (format_primitive(boxed)?, Style::Boolean),
+            _ => (format_primitive(boxed)?, Style::Number),
+        };
+        Ok(self.stylize(&format!("[{}: {}]", tag, s), style))
+    }
+
+    /// Returns the ISO string of a `Date`, or `None` if `v` only claims to be one by `Symbol.toStringTag`.
+    fn format_date(&self, v: &Value<'q>) -> Result<Option<String>> {
+        let time = match self
+            .ctx
+            .call(self.ctx.require_intrinsic(Intrinsic::DateGetTime)?, v.clone(), &[])
+        {
+            Ok(time) => time.to_f64()?,
+            Err(_) => return Ok(None),
+        };
+        let s = if time.is_nan() {
+            "Invalid Date".to_owned()
+        } else {
+            let to_iso_string = self.ctx.require_intrinsic(Intrinsic::DateToISOString)?;
+            self.ctx.call(to_iso_string, v.clone(), &[])?.to_string()?
+        };
+        Ok(Some(self.stylize(&s, Style::Date)))
+    }
+
+    fn call_method(&self, v: &Value<'q>, name: &str) -> Result<String> {
+        let f: Value = v.get(name)?;
+        self.ctx.call(f, v.clone(), &[])?.to_string()
+    }
+
+    fn reduce_to_single_string(&self, start: &str, entries: &[String], end: &str) -> String {
+        let total = visible_len(start) + entries.iter().map(|e| visible_len(e) + 2).sum::<usize>() + end.len();
+        if total < self.options.break_length && entries.iter().all(|e| !e.contains('\n')) {
+            return format!("{} {} {}", start, entries.join(", "), end);
+        }
+        let mut s = start.to_owned();
+        for (i, entry) in entries.iter().enumerate() {
+            s.push_str("\n  ");
+            s.push_str(&entry.replace('\n', "\n  "));
+            if i + 1 < entries.len() {
+                s.push(',');
+            }
+        }
+        s.push('\n');
+        s.push_str(end);
+        s
+    }
+}
+
+const TYPED_ARRAYS: &[&str] = &[
+    "Int8Array",
+    "Uint8Array",
+    "Uint8ClampedArray",
+    "Int16Array",
+    "Uint16Array",
+    "Int32Array",
+    "Uint32Array",
+    "Float32Array",
+    "Float64Array",
+    "BigInt64Array",
+    "BigUint64Array",
+];
+
+fn more_items(n: usize) -> String {
+    format!("... {} more item{}", n, if n == 1 { "" } else { "s" })
+}
+
+/// Returns the length without ANSI escape sequences.
+fn visible_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut escaped = false;
+    for c in s.chars() {
+        match c {
+            '\x1b' => escaped = true,
+            'm' if escaped => escaped = false,
+            _ if escaped => {}
+            _ => len += 1,
+        }
+    }
+    len
+}
+
+fn constructor_name(proto: &Value) -> Result<Option<String>> {
+    let ctor: Value = proto.get("constructor")?;
+    if !ctor.is_function() {
+        return Ok(None);
+    }
+    let name = ctor.get::<_, Value>("name")?.to_string()?;
+    Ok(Some(name).filter(|name| !name.is_empty()))
+}
+
+fn format_primitive(v: &Value) -> Result<String> {
+    Ok(match v.tag() {
+        Tag::Float64 if v.to_f64()? == 0.0 && v.to_f64()?.is_sign_negative() => "-0".to_owned(),
+        Tag::BigInt => format!("{}n", v.to_string()?),
+        Tag::Symbol => format_symbol(v)?,
+        _ => v.to_string()?,
+    })
+}
+
+fn format_symbol(v: &Value) -> Result<String> {
+    let description: Value = v.get("description")?;
+    Ok(if description.is_undefined() {
+        "Symbol()".to_owned()
+    } else {
+        format!("Symbol({})", description.to_string()?)
+    })
+}
+
+fn format_error(v: &Value) -> Result<String> {
+    let s = v.to_string()?;
+    let stack: Value = v.get("stack")?;
+    if stack.tag() != Tag::String {
+        return Ok(format!("[{}]", s));
+    }
+    let stack = stack.to_string()?;
+    let stack = stack.trim_end();
+    Ok(if stack.is_empty() {
+        format!("[{}]", s)
+    } else {
+        format!("{}\n{}", s, stack)
+    })
+}
+
+/// Quotes a string with single quotes unless it contains them.
+pub(crate) fn quote(s: &str) -> String {
+    let q = if !s.contains('\'') {
+        '\''
+    } else if !s.contains('"') {
+        '"'
+    } else if !s.contains('`') {
+        '`'
+    } else {
+        '\''
+    };
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(q);
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\\' => out.push_str("\\\\"),
+            c if c == q => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\x{:02X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push(q);
+    out
+}
+
+/// Returns the array index which `key` represents, e.g. `1` for `"1"` but not for `"01"`.
+fn array_index(key: &Value) -> Result<Option<u32>> {
+    if key.tag() != Tag::String {
+        return Ok(None);
+    }
+    let key = key.to_string()?;
+    Ok(key
+        .parse::<u32>()
+        .ok()
+        .filter(|&i| i != u32::MAX && i.to_string() == key))
+}
+
+/// Returns whether `key` can be written as an identifier, e.g. `a.b` instead of `a['b']`.
+pub(crate) fn is_identifier(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c == '_' || c == '$' || c.is_alphanumeric())
+}
+
+fn format_key(key: &str) -> String {
+    if is_identifier(key) {
+        key.to_owned()
+    } else {
+        quote(key)
+    }
+}
+
+/// Formats arguments like Node's `util.format`.
+#[cfg(feature = "console")]
+pub(crate) fn format<'q>(ctx: Context<'q>, args: &[Value<'q>]) -> Result<String> {
+    let mut inspector = Inspector::new(ctx, InspectOptions::default())?;
+    let mut out = String::new();
+    let mut rest = args;
+    if let Some(first) = args.first().filter(|v| v.tag() == Tag::String) {
+        let template = first.to_string()?;
+        rest = &args[1..];
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let spec = match chars.peek() {
+                Some(&spec) => spec,
+                None => {
+                    out.push(c);
+                    break;
+                }
+            };
+            if spec == '%' {
+                chars.next();
+                out.push('%');
+                continue;
+            }
+            if !"sdifjoOc".contains(spec) {
+                out.push(c);
+                continue;
+            }
+            chars.next();
+            let arg = match rest.split_first() {
+                Some((arg, tail)) => {
+                    rest = tail;
+                    arg
+                }
+                None => {
+                    out.push(c);
+                    out.push(spec);
+                    continue;
+                }
+            };
+            match spec {
+                's' => out.push_str(&match arg.tag() {
+                    Tag::String => arg.to_string()?,
+                    Tag::Object => inspector.inspect(arg)?,
+                    _ => format_primitive(arg)?,
+                }),
+                'd' | 'i' | 'f' => out.push_str(&format_number(ctx, arg, spec)?),
+                'j' => out.push_str(
+                    &ctx.json_stringify(arg.clone(), ctx.undefined().into(), ctx.undefined().into())
+                        .and_then(|s| s.to_string())
+                        .unwrap_or_else(|_| "[Circular]".to_owned()),
+                ),
+                'o' | 'O' => out.push_str(&inspector.inspect(arg)?),
+                // CSS is ignored
+                _ => {}
+            }
+        }
+    }
+    let mut first = rest.len() == args.len();
+    for arg in rest {
+        if !first {
+            out.push(' ');
+        }
+        first = false;
+        if arg.tag() == Tag::String {
+            out.push_str(&arg.to_string()?);
+        } else {
+            out.push_str(&inspector.inspect(arg)?);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "console")]
+fn format_number<'q>(ctx: Context<'q>, v: &Value<'q>, spec: char) -> Result<String> {
+    if v.tag() == Tag::BigInt {
+        return format_primitive(v);
+    }
+    if v.tag() == Tag::Object || v.tag() == Tag::Symbol {
+        return Ok("NaN".to_owned());
+    }
+    let f = ctx.require_intrinsic(match spec {
+        'd' => Intrinsic::Number,
+        'i' => Intrinsic::ParseInt,
+        _ => Intrinsic::ParseFloat,
+    })?;
+    format_primitive(&ctx.call(f, ctx.undefined().into(), std::slice::from_ref(v))?)
+}
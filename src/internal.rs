@@ -1,4 +1,4 @@
-use crate::{raw, Context, ModuleDef, Value};
+use crate::{panic, raw, runtime::poisoned_error, Context, ModuleDef, Value};
 use qc::AsJsValue;
 use quijine_core as qc;
 use std::os::raw::c_int;
@@ -29,6 +29,41 @@ pub fn convert_function_result(res: Value) -> raw::JSValue {
     res.as_raw().as_js_value()
 }
 
+/// This function is used by js_c_function macros to catch panics of `f`.
+#[doc(hidden)]
+pub fn catch_function_panic<'q, F>(ctx: Context<'q>, f: F) -> raw::JSValue
+where
+    F: FnOnce() -> Value<'q>,
+{
+    if ctx.runtime().is_poisoned() {
+        ctx.throw_callback_error(poisoned_error(), "");
+        ctx.make_exception_uncatchable();
+        return qc::Value::exception().as_js_value();
+    }
+    match panic::catch(ctx.runtime(), f) {
+        Ok(ret) => convert_function_result(ret),
+        Err(message) => {
+            ctx.throw_panic(&message, "");
+            qc::Value::exception().as_js_value()
+        }
+    }
+}
+
+/// This function is used by js_module_init_func macro to catch panics of `f`.
+#[doc(hidden)]
+pub fn catch_module_init_panic<F>(ctx: Context, f: F) -> c_int
+where
+    F: FnOnce() -> i32,
+{
+    match panic::catch(ctx.runtime(), f) {
+        Ok(ret) => ret as c_int,
+        Err(message) => {
+            ctx.throw_panic(&message, "");
+            -1
+        }
+    }
+}
+
 /// # Safety
 /// * A context must have valid lifetime.
 #[doc(hidden)]
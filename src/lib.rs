@@ -1,17 +1,36 @@
 mod atom;
 mod class;
+mod clone;
+#[cfg(feature = "console")]
+mod console;
 mod context;
 mod context_ext;
 mod convert;
 mod error;
 mod flags;
+mod handle;
+mod inspect;
 mod module;
+mod native_function;
+mod panic;
+mod pool;
 mod result;
 mod runtime;
+#[cfg(feature = "sandbox")]
+mod sandbox;
+mod script;
+mod shared;
+#[cfg(feature = "stream")]
+mod stream;
 mod string;
+mod thrown;
+#[cfg(feature = "timers")]
+mod timers;
 mod types;
 mod util;
 mod value;
+#[cfg(feature = "worker")]
+mod worker;
 
 #[cfg(feature = "c_function_list")]
 mod arena;
@@ -23,23 +42,43 @@ pub mod macros;
 #[doc(hidden)]
 pub mod internal;
 
-pub use quijine_core::raw;
+pub use quijine_core::{raw, PromiseState, QUICKJS_VERSION};
 
 pub use atom::{Atom, PropertyEnum};
 pub use class::{Class, ClassProperties};
+#[cfg(feature = "console")]
+pub use console::{ConsoleLevel, ConsoleSink, LogSink};
 pub use context::{Context, ContextScope};
 pub use context_ext::ContextAddIntrinsicExt;
-pub use convert::{FromQj, FromQjMulti, IntoQj, IntoQjAtom, IntoQjMulti};
-pub use error::{Error, ErrorKind, ErrorValue, ExternalError};
+pub use convert::{FromQj, FromQjMulti, IntoQj, IntoQjAtom, IntoQjMulti, Rest};
+pub use error::{Error, ErrorCode, ErrorKind, ErrorValue, ExternalError, JsErrorData, JsStackFrame};
 pub use flags::{EvalFlags, GpnFlags, PropFlags, ReadObjFlags, WriteObjFlags};
+#[cfg(feature = "stream")]
+pub use futures_core::Stream;
+pub use handle::{JobResult, RuntimeHandle};
+pub use inspect::InspectOptions;
 pub use module::ModuleDef;
+pub use native_function::CallInfo;
+pub use panic::PanicPolicy;
+pub use pool::{JobLimits, RuntimePool, RuntimePoolBuilder};
 pub use result::{ExternalResult, Result};
 pub use runtime::{Runtime, RuntimeScope};
+#[cfg(feature = "sandbox")]
+pub use sandbox::{Intrinsics, Sandbox};
+pub use script::{BytecodeError, CompiledScript};
+pub use shared::SharedBuffer;
+#[cfg(feature = "stream")]
+pub use stream::{message_queue, AsyncIter, MessageQueue, MessageSender, Next};
+pub use thrown::ThrownValue;
+#[cfg(feature = "timers")]
+pub use timers::Clock;
 pub use types::{
-    BigDecimal, BigFloat, BigInt, Bool, CatchOffset, ClassObject, Exception, Float64, FunctionBytecode, Int, Module,
-    Null, Object, String, Symbol, Undefined, Uninitialized, Variant,
+    ArrayIter, ArrayRef, BigDecimal, BigFloat, BigInt, Bool, CatchOffset, ClassObject, Exception, Float64, Function,
+    FunctionBytecode, Int, Module, Null, Object, String, Symbol, Undefined, Uninitialized, Variant,
 };
-pub use value::Value;
+pub use value::{PropertyDescriptor, Value};
+#[cfg(feature = "worker")]
+pub use worker::WorkerOptions;
 
 #[cfg(feature = "c_function_list")]
 pub use arena::{CStringArena, DefArena};
@@ -15,6 +15,7 @@ macro_rules! qj_vec {
 #[macro_export]
 macro_rules! js_c_function {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'q>(
             ctx: *mut $crate::raw::JSContext,
             this_val: $crate::raw::JSValue,
@@ -23,8 +24,7 @@ macro_rules! js_c_function {
         ) -> $crate::raw::JSValue {
             let f: fn($crate::Context<'q>, $crate::Value<'q>, &[$crate::Value<'q>]) -> $crate::Value<'q> = $f;
             let (ctx, this, args) = $crate::internal::convert_function_arguments::<'q>(ctx, this_val, argc, argv);
-            let ret = f(ctx, this, args.as_slice());
-            $crate::internal::convert_function_result(ret)
+            $crate::internal::catch_function_panic(ctx, || f(ctx, this, args.as_slice()))
         }
         Some(wrap)
     }};
@@ -33,6 +33,7 @@ macro_rules! js_c_function {
 #[macro_export]
 macro_rules! js_c_getter {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'q>(
             ctx: *mut $crate::raw::JSContext,
             this: $crate::raw::JSValue,
@@ -40,8 +41,7 @@ macro_rules! js_c_getter {
             let f: fn($crate::Context<'q>, $crate::Value<'q>) -> $crate::Value<'q> = $f;
             let ctx = $crate::internal::convert_context(ctx);
             let this = $crate::internal::convert_value_and_dup(this, ctx);
-            let ret = f(ctx, this);
-            $crate::internal::convert_function_result(ret)
+            $crate::internal::catch_function_panic(ctx, || f(ctx, this))
         }
         Some(wrap)
     }};
@@ -50,6 +50,7 @@ macro_rules! js_c_getter {
 #[macro_export]
 macro_rules! js_c_setter {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'q>(
             ctx: *mut $crate::raw::JSContext,
             this: $crate::raw::JSValue,
@@ -59,8 +60,7 @@ macro_rules! js_c_setter {
             let ctx = $crate::internal::convert_context(ctx);
             let this = $crate::internal::convert_value_and_dup(this, ctx);
             let val = $crate::internal::convert_value_and_dup(val, ctx);
-            let ret = f(ctx, this, val);
-            $crate::internal::convert_function_result(ret)
+            $crate::internal::catch_function_panic(ctx, || f(ctx, this, val))
         }
         Some(wrap)
     }};
@@ -69,6 +69,7 @@ macro_rules! js_c_setter {
 #[macro_export]
 macro_rules! js_module_init_func {
     ($f: expr) => {{
+        #[allow(clippy::extra_unused_lifetimes)]
         unsafe extern "C" fn wrap<'q>(
             ctx: *mut $crate::raw::JSContext,
             m: *mut $crate::raw::JSModuleDef,
@@ -76,7 +77,7 @@ macro_rules! js_module_init_func {
             let f: fn($crate::Context<'q>, $crate::ModuleDef<'q>) -> i32 = $f;
             let ctx = $crate::internal::convert_context(ctx);
             let m = $crate::internal::convert_module_def(m, ctx);
-            f(ctx, m) as ::std::os::raw::c_int
+            $crate::internal::catch_module_init_panic(ctx, || f(ctx, m))
         }
         Some(wrap)
     }};
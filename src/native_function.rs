@@ -0,0 +1,195 @@
+use crate::{
+    context::Context, panic, result::Result, runtime::poisoned_error, types::Object, value::Value, PropFlags, Runtime,
+};
+use quijine_core::{self as qc, raw, AsJsValue};
+use std::{
+    ffi::{c_void, CStr},
+    fmt,
+    os::raw::c_int,
+    ptr::null_mut,
+    result::Result as StdResult,
+    slice,
+};
+
+/// `CallInfo` describes a call of a function created by `Context::new_function_with_info`.
+pub struct CallInfo<'q, 'a> {
+    this: Value<'q>,
+    args: &'a [Value<'q>],
+    new_target: Value<'q>,
+    construct: bool,
+}
+
+impl<'q, 'a> CallInfo<'q, 'a> {
+    /// Returns the receiver of the call, or `undefined` in a construct call.
+    #[inline]
+    pub fn this(&self) -> &Value<'q> {
+        &self.this
+    }
+
+    /// Returns the arguments. Missing arguments are not filled with `undefined`.
+    #[inline]
+    pub fn args(&self) -> &'a [Value<'q>] {
+        self.args
+    }
+
+    /// Returns the `index`-th argument, or `undefined` if it is missing.
+    #[inline]
+    pub fn arg(&self, index: usize) -> Value<'q> {
+        match self.args.get(index) {
+            Some(v) => v.clone(),
+            None => self.this.context().undefined().into(),
+        }
+    }
+
+    /// Returns `new.target`, which is `undefined` unless the function is called with `new`.
+    #[inline]
+    pub fn new_target(&self) -> &Value<'q> {
+        &self.new_target
+    }
+
+    #[inline]
+    pub fn is_construct_call(&self) -> bool {
+        self.construct
+    }
+}
+
+impl fmt::Debug for CallInfo<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("CallInfo")
+            .field("this", &self.this)
+            .field("args", &self.args)
+            .field("new_target", &self.new_target)
+            .finish()
+    }
+}
+
+pub(crate) type NativeCallback<'q> = dyn for<'a> Fn(Context<'q>, CallInfo<'q, 'a>) -> Result<Value<'q>> + 'q;
+
+/// `NativeFunction` is the class of functions created by `Context::new_function_with_info`.
+/// Unlike C function data, its call handler receives `new.target`.
+struct NativeFunction<'q> {
+    func: Box<NativeCallback<'q>>,
+    name: String,
+}
+
+/// The key of the class id in a runtime.
+struct NativeFunctionClass;
+
+fn class_id(mut rt: Runtime) -> qc::ClassId {
+    if let Some(clz) = rt.class_id::<NativeFunctionClass>() {
+        return clz;
+    }
+    let clz = rt.get_or_register_class_id::<NativeFunctionClass>();
+    let class_name = CStr::from_bytes_with_nul(b"Function\0").unwrap();
+    let class_def = unsafe {
+        qc::ClassDef::from_raw(raw::JSClassDef {
+            class_name: class_name.as_ptr(),
+            finalizer: Some(finalize),
+            gc_mark: None,
+            call: Some(call),
+            exotic: null_mut(),
+        })
+    };
+    rt.new_class(clz, &class_def);
+    clz
+}
+
+unsafe extern "C" fn finalize(rt: *mut raw::JSRuntime, val: raw::JSValue) {
+    let rrt = qc::Runtime::from_raw(rt);
+    let rt = Runtime::from(rrt);
+    let clz = match rt.class_id::<NativeFunctionClass>() {
+        Some(clz) => clz,
+        None => return,
+    };
+    let p = qc::Value::from_raw_with_runtime(val, rrt).opaque(clz) as *mut NativeFunction;
+    if p.is_null() {
+        return;
+    }
+    let b = Box::from_raw(p);
+    if let Err(message) = panic::catch(rt, move || drop(b)) {
+        log::error!("panic in the finalizer of a function: {}", message);
+    }
+}
+
+unsafe extern "C" fn call(
+    ctx: *mut raw::JSContext,
+    func_obj: raw::JSValue,
+    this_val: raw::JSValue,
+    argc: c_int,
+    argv: *mut raw::JSValue,
+    flags: c_int,
+) -> raw::JSValue {
+    let rctx = qc::Context::from_raw(ctx);
+    let ctx = Context::from_raw(rctx);
+    let clz = class_id(ctx.runtime());
+    let data = &*(qc::Value::from_raw(func_obj, rctx).opaque(clz) as *const NativeFunction);
+    let args: Vec<_> = if argc > 0 {
+        slice::from_raw_parts(argv, argc as usize)
+            .iter()
+            .map(|v| {
+                let v = Value::from_raw_parts(qc::Value::from_raw(*v, rctx), rctx);
+                Value::dup(&v);
+                v
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let this = Value::from_raw_parts(qc::Value::from_raw(this_val, rctx), rctx);
+    Value::dup(&this);
+    let construct = flags & raw::JS_CALL_FLAG_CONSTRUCTOR as c_int != 0;
+    let info = if construct {
+        // `this_val` is `new.target` in a construct call
+        CallInfo {
+            this: ctx.undefined().into(),
+            args: &args,
+            new_target: this,
+            construct,
+        }
+    } else {
+        CallInfo {
+            this,
+            args: &args,
+            new_target: ctx.undefined().into(),
+            construct,
+        }
+    };
+    if ctx.runtime().is_poisoned() {
+        ctx.throw_callback_error(poisoned_error(), &data.name);
+        ctx.make_exception_uncatchable();
+        return qc::Value::exception().as_js_value();
+    }
+    match panic::catch(ctx.runtime(), || (*data.func)(ctx, info)) {
+        Ok(Ok(v)) => {
+            Value::dup(&v);
+            v.as_raw().as_js_value()
+        }
+        Ok(Err(e)) => {
+            ctx.throw_callback_error(e, &data.name);
+            qc::Value::exception().as_js_value()
+        }
+        Err(message) => {
+            ctx.throw_panic(&message, &data.name);
+            qc::Value::exception().as_js_value()
+        }
+    }
+}
+
+pub(crate) fn new_function_with_info<'q>(
+    ctx: Context<'q>,
+    func: Box<NativeCallback<'q>>,
+    name: &str,
+    length: i32,
+) -> Result<Object<'q>> {
+    let clz = class_id(ctx.runtime());
+    let proto = ctx.function_prototype()?;
+    let obj: Object = unsafe { ctx.wrap_result(ctx.as_raw().new_object_proto_class(*proto.as_raw(), clz))? };
+    let data = Box::new(NativeFunction {
+        func,
+        name: name.to_owned(),
+    });
+    obj.as_raw().set_opaque(Box::into_raw(data) as *mut c_void);
+    obj.define_property_value_from("length", length, PropFlags::CONFIGURABLE)?;
+    obj.define_property_value_from("name", name, PropFlags::CONFIGURABLE)?;
+    Ok(obj)
+}
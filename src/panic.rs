@@ -0,0 +1,32 @@
+use crate::runtime::Runtime;
+use quijine_core as qc;
+use std::{process, result::Result as StdResult};
+
+/// `PanicPolicy` decides how a runtime treats a panic caught in a native callback.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum PanicPolicy {
+    /// Throws an `InternalError` with the panic message.
+    #[default]
+    Throw,
+    /// Throws an uncatchable `InternalError` and rejects any further execution in the runtime.
+    Poison,
+    /// Aborts the process.
+    Abort,
+}
+
+/// Runs Rust code called from C. A panic is returned as its message after the policy of the runtime is applied.
+pub(crate) fn catch<R, F: FnOnce() -> R>(mut rt: Runtime, f: F) -> StdResult<R, String> {
+    let message = match qc::catch_panic(f) {
+        Ok(r) => return Ok(r),
+        Err(message) => message,
+    };
+    match rt.panic_policy() {
+        PanicPolicy::Throw => {}
+        PanicPolicy::Poison => rt.poison(),
+        PanicPolicy::Abort => {
+            log::error!("panic in a native callback: {}", message);
+            process::abort();
+        }
+    }
+    Err(message)
+}
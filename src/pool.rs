@@ -0,0 +1,327 @@
+use crate::{
+    context::Context,
+    error::{Error, ErrorKind},
+    flags::{EvalFlags, ReadObjFlags, WriteObjFlags},
+    handle::{catch_job, terminated, JobResult},
+    result::Result,
+    runtime::RuntimeScope,
+};
+use std::{
+    cell::Cell,
+    fmt,
+    rc::Rc,
+    result::Result as StdResult,
+    sync::{
+        mpsc::{channel, sync_channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+type PoolJob = Box<dyn for<'q> FnOnce(Result<Context<'q>>) + Send>;
+type InitContext = dyn for<'q> Fn(Context<'q>) -> Result<()> + Send + Sync;
+
+/// `JobLimits` restricts resources which a job of [`RuntimePool`] can consume.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct JobLimits {
+    /// Bytes which the job can allocate in addition to the prepared context.
+    pub memory_limit: Option<usize>,
+    /// Wall-clock time after which the running JavaScript code is interrupted.
+    pub time_limit: Option<Duration>,
+}
+
+struct Message {
+    job: PoolJob,
+    limits: JobLimits,
+}
+
+struct Preload {
+    bytecodes: Vec<Vec<u8>>,
+    init_context: Option<Arc<InitContext>>,
+    shared_array_buffer: bool,
+}
+
+/// `RuntimePoolBuilder` configures a [`RuntimePool`].
+pub struct RuntimePoolBuilder {
+    size: usize,
+    scripts: Vec<(String, String, EvalFlags)>,
+    bytecodes: Vec<Vec<u8>>,
+    init_context: Option<Arc<InitContext>>,
+    limits: JobLimits,
+    shared_array_buffer: bool,
+}
+
+impl RuntimePoolBuilder {
+    /// Sets the number of runtimes (worker threads). Defaults to 1.
+    #[inline]
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Compiles a script or module once and evaluates its bytecode in every context before a job runs.
+    #[inline]
+    pub fn preload(mut self, code: &str, filename: &str, eval_flags: EvalFlags) -> Self {
+        self.scripts.push((code.to_owned(), filename.to_owned(), eval_flags));
+        self
+    }
+
+    /// Evaluates bytecode written by `Context::write_object` with `WriteObjFlags::BYTECODE` in every context.
+    #[inline]
+    pub fn preload_bytecode(mut self, bytecode: Vec<u8>) -> Self {
+        self.bytecodes.push(bytecode);
+        self
+    }
+
+    /// Sets a function which initializes every context (e.g. host functions) before the bytecode is evaluated.
+    #[inline]
+    pub fn init_context<F>(mut self, f: F) -> Self
+    where
+        F: for<'q> Fn(Context<'q>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.init_context = Some(Arc::new(f));
+        self
+    }
+
+    /// Enables `SharedArrayBuffer` and blocking `Atomics.wait` on every runtime.
+    /// The memory of `SharedArrayBuffer`s is not counted by the memory limit.
+    #[inline]
+    pub fn shared_array_buffer(mut self, enabled: bool) -> Self {
+        self.shared_array_buffer = enabled;
+        self
+    }
+
+    /// Sets the default limits of jobs.
+    #[inline]
+    pub fn limits(mut self, limits: JobLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[inline]
+    pub fn memory_limit(mut self, memory_limit: usize) -> Self {
+        self.limits.memory_limit = Some(memory_limit);
+        self
+    }
+
+    #[inline]
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.limits.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Compiles the preloaded scripts and spawns worker threads.
+    pub fn build(self) -> Result<RuntimePool> {
+        if self.size == 0 {
+            return Err(Error::with_str(ErrorKind::RangeError, "the pool size must be positive"));
+        }
+        let mut bytecodes = compile_scripts(&self.scripts)?;
+        bytecodes.extend(self.bytecodes);
+        let preload = Arc::new(Preload {
+            bytecodes,
+            init_context: self.init_context,
+            shared_array_buffer: self.shared_array_buffer,
+        });
+        let (sender, receiver) = channel::<Message>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let mut workers = Vec::with_capacity(self.size);
+        for i in 0..self.size {
+            let receiver = receiver.clone();
+            let preload = preload.clone();
+            let worker = thread::Builder::new()
+                .name(format!("quijine-pool-{}", i))
+                .spawn(move || run_worker(&receiver, &preload))
+                .map_err(|e| Error::with_external(ErrorKind::InternalError, e))?;
+            workers.push(worker);
+        }
+        Ok(RuntimePool {
+            sender: Some(sender),
+            workers,
+            limits: self.limits,
+        })
+    }
+}
+
+impl Default for RuntimePoolBuilder {
+    fn default() -> Self {
+        RuntimePoolBuilder {
+            size: 1,
+            scripts: Vec::new(),
+            bytecodes: Vec::new(),
+            init_context: None,
+            limits: JobLimits::default(),
+            shared_array_buffer: false,
+        }
+    }
+}
+
+impl fmt::Debug for RuntimePoolBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("RuntimePoolBuilder")
+            .field("size", &self.size)
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+/// `RuntimePool` keeps runtimes on worker threads and runs each job in a fresh context.
+///
+/// Every context is initialized with the preloaded bytecode, so that global state never leaks between jobs.
+/// Dropping the pool waits for the queued jobs and the worker threads.
+pub struct RuntimePool {
+    sender: Option<Sender<Message>>,
+    workers: Vec<JoinHandle<()>>,
+    limits: JobLimits,
+}
+
+impl RuntimePool {
+    #[inline]
+    pub fn builder() -> RuntimePoolBuilder {
+        RuntimePoolBuilder::default()
+    }
+
+    /// Returns the number of runtimes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Posts a job with the default limits and returns a receiver of its result.
+    #[inline]
+    pub fn spawn_job<F, R>(&self, f: F) -> Result<JobResult<R>>
+    where
+        F: for<'q> FnOnce(Context<'q>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.spawn_job_with_limits(self.limits, f)
+    }
+
+    /// Posts a job with the given limits and returns a receiver of its result.
+    pub fn spawn_job_with_limits<F, R>(&self, limits: JobLimits, f: F) -> Result<JobResult<R>>
+    where
+        F: for<'q> FnOnce(Context<'q>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = sync_channel(1);
+        let job: PoolJob = Box::new(move |ctx| {
+            // a panicking job must not kill the worker thread, or the pool would lose capacity
+            let _ = sender.send(ctx.and_then(|ctx| catch_job(|| f(ctx))));
+        });
+        self.sender
+            .as_ref()
+            .ok_or_else(terminated)?
+            .send(Message { job, limits })
+            .map_err(|_| terminated())?;
+        Ok(JobResult::new(receiver))
+    }
+
+    /// Posts a job with the default limits and blocks the current thread until it completes.
+    #[inline]
+    pub fn call<F, R>(&self, f: F) -> Result<R>
+    where
+        F: for<'q> FnOnce(Context<'q>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.spawn_job(f)?.join()
+    }
+
+    /// Posts a job with the given limits and blocks the current thread until it completes.
+    #[inline]
+    pub fn call_with_limits<F, R>(&self, limits: JobLimits, f: F) -> Result<R>
+    where
+        F: for<'q> FnOnce(Context<'q>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.spawn_job_with_limits(limits, f)?.join()
+    }
+}
+
+impl Drop for RuntimePool {
+    fn drop(&mut self) {
+        // workers exit when the channel is closed
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl fmt::Debug for RuntimePool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("RuntimePool")
+            .field("size", &self.size())
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+fn compile_scripts(scripts: &[(String, String, EvalFlags)]) -> Result<Vec<Vec<u8>>> {
+    if scripts.is_empty() {
+        return Ok(Vec::new());
+    }
+    crate::context(|ctx| {
+        scripts
+            .iter()
+            .map(|(code, filename, eval_flags)| {
+                let func = ctx.eval(code, filename, *eval_flags | EvalFlags::FLAG_COMPILE_ONLY)?;
+                ctx.write_object(func, WriteObjFlags::BYTECODE)
+            })
+            .collect()
+    })
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Message>>, preload: &Preload) {
+    let rts = RuntimeScope::new();
+    let rt = rts.get();
+    if preload.shared_array_buffer {
+        rt.enable_shared_array_buffer().expect("no context is created yet");
+        rt.set_can_block(true);
+    }
+    let deadline: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+    let handler_deadline = deadline.clone();
+    rt.set_interrupt_handler(move || handler_deadline.get().map_or(false, |d| Instant::now() >= d));
+    loop {
+        let message = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Message { job, limits } = match message {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        {
+            let ctxs = rts.new_context_scope();
+            let ctx = ctxs.get();
+            match prepare_context(ctx, preload) {
+                Ok(()) => {
+                    if let Some(memory_limit) = limits.memory_limit {
+                        let used = rt.memory_usage().malloc_size.max(0) as usize;
+                        rt.set_memory_limit(used.saturating_add(memory_limit));
+                    }
+                    deadline.set(limits.time_limit.map(|t| Instant::now() + t));
+                    job(Ok(ctx));
+                    // pending jobs keep the context alive, so they must be drained under the limits
+                    if let Err(e) = rt.run_pending_jobs() {
+                        log::warn!("pending job failed: {}", e);
+                    }
+                    deadline.set(None);
+                    rt.set_memory_limit(usize::MAX);
+                }
+                Err(e) => job(Err(e)),
+            }
+        }
+        rt.run_gc();
+    }
+}
+
+fn prepare_context(ctx: Context, preload: &Preload) -> Result<()> {
+    if let Some(init_context) = &preload.init_context {
+        init_context(ctx)?;
+    }
+    for bytecode in &preload.bytecodes {
+        let func = ctx.read_object(bytecode, ReadObjFlags::BYTECODE)?;
+        ctx.eval_function(func)?;
+    }
+    Ok(())
+}
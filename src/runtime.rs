@@ -1,14 +1,22 @@
+#[cfg(feature = "worker")]
+use crate::worker::WorkerRegistry;
 use crate::{
-    class::Class,
     context::{Context, ContextScope},
+    error::{Error, ErrorKind},
+    panic::{self, PanicPolicy},
     result::Result,
+    shared::shared_array_buffer_functions,
+    thrown::ThrownRegistry,
+    value::Value,
 };
-use quijine_core as qc;
+use quijine_core::{self as qc, raw};
 use std::{
     any::TypeId,
     collections::{HashMap, HashSet},
-    ffi::{c_void, CString},
+    ffi::{c_void, CStr, CString},
     fmt,
+    os::raw::c_int,
+    ptr::null_mut,
     result::Result as StdResult,
 };
 
@@ -16,6 +24,15 @@ pub struct RuntimeOpaque {
     registered_classes: HashMap<TypeId, qc::ClassId>,
     class_defs: HashMap<qc::ClassId, qc::ClassDef>,
     class_names: HashSet<CString>,
+    interrupt_handler: Option<Box<Box<InterruptHandler>>>,
+    // SharedArrayBuffers can be cloned only if their memory is not owned by a runtime
+    shared_array_buffer_enabled: bool,
+    context_created: bool,
+    #[cfg(feature = "worker")]
+    pub(crate) worker_registry: Option<WorkerRegistry>,
+    thrown_registry: ThrownRegistry,
+    panic_policy: PanicPolicy,
+    poisoned: bool,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -54,9 +71,119 @@ impl<'r> Runtime<'r> {
 
     #[inline]
     pub fn run_gc(self) {
+        self.thrown_registry().collect(self);
         self.0.run_gc();
     }
 
+    #[inline]
+    pub fn memory_usage(self) -> raw::JSMemoryUsage {
+        self.0.compute_memory_usage()
+    }
+
+    /// Allows `Atomics.wait` to block the current thread.
+    #[inline]
+    pub fn set_can_block(self, can_block: bool) {
+        self.0.set_can_block(can_block)
+    }
+
+    /// Backs `SharedArrayBuffer`s with `SharedBuffer`, so that they can be shared with other runtimes.
+    /// It must be called before creating a context.
+    pub fn enable_shared_array_buffer(mut self) -> Result<()> {
+        if self.opaque().context_created {
+            return Err(Error::with_str(
+                ErrorKind::InternalError,
+                "SharedArrayBuffer must be enabled before creating a context",
+            ));
+        }
+        self.0
+            .set_shared_array_buffer_functions(&shared_array_buffer_functions());
+        self.opaque_mut().shared_array_buffer_enabled = true;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_shared_array_buffer_enabled(self) -> bool {
+        self.opaque().shared_array_buffer_enabled
+    }
+
+    /// Sets a handler called periodically while JavaScript is running.
+    /// The execution is aborted with an uncatchable `InternalError` if it returns `true`.
+    pub fn set_interrupt_handler<F>(mut self, handler: F)
+    where
+        F: FnMut() -> bool + 'static,
+    {
+        unsafe extern "C" fn call(rt: *mut raw::JSRuntime, opaque: *mut c_void) -> c_int {
+            let rt = Runtime::from(qc::Runtime::from_raw(rt));
+            let handler = &mut *(opaque as *mut Box<InterruptHandler>);
+            // a panicking handler interrupts the execution
+            panic::catch(rt, handler).unwrap_or(true) as c_int
+        }
+        // double boxing keeps the address of the handler stable
+        let mut handler: Box<Box<InterruptHandler>> = Box::new(Box::new(handler));
+        let opaque = handler.as_mut() as *mut Box<InterruptHandler> as *mut c_void;
+        self.0.set_interrupt_handler(Some(call), opaque);
+        // the previous handler is dropped here
+        self.opaque_mut().interrupt_handler = Some(handler);
+    }
+
+    #[inline]
+    pub fn clear_interrupt_handler(mut self) {
+        self.0.set_interrupt_handler(None, null_mut());
+        self.opaque_mut().interrupt_handler = None;
+    }
+
+    /// Sets how panics in native callbacks are handled.
+    #[inline]
+    pub fn set_panic_policy(mut self, policy: PanicPolicy) {
+        self.opaque_mut().panic_policy = policy;
+    }
+
+    #[inline]
+    pub fn panic_policy(self) -> PanicPolicy {
+        self.opaque().panic_policy
+    }
+
+    /// Returns `true` if a panic has poisoned the runtime under `PanicPolicy::Poison`.
+    #[inline]
+    pub fn is_poisoned(self) -> bool {
+        self.opaque().poisoned
+    }
+
+    #[inline]
+    pub(crate) fn poison(&mut self) {
+        self.opaque_mut().poisoned = true;
+    }
+
+    pub(crate) fn check_poisoned(self) -> Result<()> {
+        if self.is_poisoned() {
+            return Err(poisoned_error());
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_job_pending(self) -> bool {
+        self.0.is_job_pending()
+    }
+
+    /// Executes a pending job (e.g. a reaction of `Promise`).
+    /// Returns `false` if no job is pending.
+    pub fn execute_pending_job(self) -> Result<bool> {
+        self.check_poisoned()?;
+        let (ret, ctx) = self.0.execute_pending_job();
+        if ret < 0 {
+            let ctx = Context::from_raw(ctx.expect("context of a failed job"));
+            return Err(ctx.internal_js_error());
+        }
+        Ok(ret > 0)
+    }
+
+    /// Executes pending jobs until the queue becomes empty.
+    pub fn run_pending_jobs(self) -> Result<()> {
+        while self.execute_pending_job()? {}
+        Ok(())
+    }
+
     #[inline]
     pub(crate) fn opaque(&self) -> &RuntimeOpaque {
         unsafe { &*(self.0.opaque() as *mut RuntimeOpaque) }
@@ -67,16 +194,31 @@ impl<'r> Runtime<'r> {
         unsafe { &mut *(self.0.opaque() as *mut RuntimeOpaque) }
     }
 
+    #[inline]
+    pub(crate) fn thrown_registry(&self) -> &ThrownRegistry {
+        &self.opaque().thrown_registry
+    }
+
+    #[inline]
+    pub(crate) fn mark_context_created(&mut self) {
+        self.opaque_mut().context_created = true;
+    }
+
+    #[inline]
+    pub(crate) fn is_same_runtime<'s>(&self, other: &Runtime<'s>) -> bool {
+        std::ptr::eq(self.opaque(), other.opaque())
+    }
+
     #[inline]
     pub(crate) fn new_class(&self, id: qc::ClassId, class_def: &qc::ClassDef) {
         self.0.new_class(id, class_def)
     }
 
-    pub(crate) fn class_id<T: Class + 'static>(&self) -> Option<qc::ClassId> {
+    pub(crate) fn class_id<T: 'static>(&self) -> Option<qc::ClassId> {
         self.opaque().registered_classes.get(&TypeId::of::<T>()).cloned()
     }
 
-    pub(crate) fn get_or_register_class_id<T: Class + 'static>(&mut self) -> qc::ClassId {
+    pub(crate) fn get_or_register_class_id<T: 'static>(&mut self) -> qc::ClassId {
         let class_id = self.class_id::<T>();
         if let Some(class_id) = class_id {
             return class_id;
@@ -90,6 +232,16 @@ impl<'r> Runtime<'r> {
         self.opaque_mut().class_names.insert(class_name);
     }
 
+    /// Returns the name of the class if the object is an instance of a class registered by `Class`.
+    pub(crate) fn class_name_of(&self, v: &Value) -> Option<&str> {
+        let (_, class_def) = self
+            .opaque()
+            .class_defs
+            .iter()
+            .find(|(class_id, _)| !v.as_raw().opaque(**class_id).is_null())?;
+        unsafe { CStr::from_ptr(class_def.as_ref().class_name) }.to_str().ok()
+    }
+
     pub(crate) fn class_name(&self, class_name: &CString) -> Option<&std::ffi::CString> {
         self.opaque().class_names.get(class_name)
     }
@@ -116,6 +268,14 @@ impl RuntimeScope {
             registered_classes: HashMap::new(),
             class_defs: HashMap::new(),
             class_names: HashSet::new(),
+            interrupt_handler: None,
+            shared_array_buffer_enabled: false,
+            context_created: false,
+            #[cfg(feature = "worker")]
+            worker_registry: None,
+            thrown_registry: ThrownRegistry::default(),
+            panic_policy: PanicPolicy::default(),
+            poisoned: false,
         });
         rt.set_opaque(Box::into_raw(opaque) as *mut c_void);
         RuntimeScope(Runtime::from(rt))
@@ -127,7 +287,7 @@ impl RuntimeScope {
     }
 
     #[inline]
-    pub fn new_context_scope(&self) -> ContextScope {
+    pub fn new_context_scope(&self) -> ContextScope<'_> {
         ContextScope::new_with_scope(self)
     }
 
@@ -153,7 +313,12 @@ impl Drop for RuntimeScope {
     fn drop(&mut self) {
         unsafe {
             // opaque must be bound until values in the runtime will be freed
-            let _opaque = Box::from_raw((self.0).0.opaque() as *mut RuntimeOpaque);
+            #[cfg_attr(not(feature = "worker"), allow(unused_mut))]
+            let mut opaque = Box::from_raw((self.0).0.opaque() as *mut RuntimeOpaque);
+            // workers hold their Worker objects, which must be freed before the runtime
+            #[cfg(feature = "worker")]
+            drop(opaque.worker_registry.take());
+            opaque.thrown_registry.close(self.0);
             qc::Runtime::free(self.0.into())
         }
     }
@@ -170,3 +335,9 @@ impl fmt::Debug for RuntimeScope {
         f.write_str(format!("RuntimeScope({:?})", self.0).as_str())
     }
 }
+
+pub(crate) fn poisoned_error() -> Error {
+    Error::with_str(ErrorKind::InternalError, "the runtime is poisoned by a panic")
+}
+
+pub(crate) type InterruptHandler = dyn FnMut() -> bool;
@@ -0,0 +1,266 @@
+use crate::{
+    context::{Context, ContextScope},
+    context_ext::ContextAddIntrinsicExt,
+    error::{Error, ErrorKind},
+    flags::{EvalFlags, PropFlags},
+    result::Result,
+    runtime::Runtime,
+    value::Value,
+};
+use bitflags::bitflags;
+#[cfg(feature = "toml")]
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+bitflags! {
+    /// intrinsics added to a sandboxed context in addition to the base objects
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct Intrinsics: u32 {
+        const DATE = 1 << 0;
+        const STRING_NORMALIZE = 1 << 1;
+        const REG_EXP = 1 << 2;
+        const JSON = 1 << 3;
+        const PROXY = 1 << 4;
+        const MAP_SET = 1 << 5;
+        const TYPED_ARRAYS = 1 << 6;
+        const PROMISE = 1 << 7;
+        const BIG_INT = 1 << 8;
+    }
+}
+
+/// Freezes builtins reachable from the global object, and makes the global bindings read-only.
+const FREEZE_BUILTINS: &str = r#"
+(() => {
+    // Set may be unavailable, so frozen objects are regarded as visited
+    const freeze = (o) => {
+        if ((typeof o !== "object" && typeof o !== "function") || o === null) return;
+        if (o === globalThis || Object.isFrozen(o)) return;
+        Object.freeze(o);
+        for (const key of Reflect.ownKeys(o)) {
+            const desc = Object.getOwnPropertyDescriptor(o, key);
+            freeze(desc.value);
+            freeze(desc.get);
+            freeze(desc.set);
+        }
+        freeze(Object.getPrototypeOf(o));
+    };
+    freeze(Object.getPrototypeOf(globalThis));
+    for (const key of Reflect.ownKeys(globalThis)) {
+        freeze(globalThis[key]);
+        Object.defineProperty(globalThis, key, { writable: false, configurable: false });
+    }
+})()
+"#;
+
+/// `Profile` is the TOML form of `Sandbox`.
+#[cfg(feature = "toml")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    intrinsics: Option<Vec<String>>,
+    freeze_builtins: Option<bool>,
+    allow_eval: Option<bool>,
+    capabilities: Option<BTreeSet<String>>,
+    memory_limit: Option<usize>,
+    max_stack_size: Option<usize>,
+}
+
+/// `Sandbox` is a profile of a context for untrusted scripts.
+///
+/// A profile can be declared in Rust or loaded from TOML with the `toml` feature:
+///
+/// ```toml
+/// intrinsics = ["json", "map_set", "promise"]
+/// freeze_builtins = true
+/// allow_eval = false
+/// capabilities = ["console", "fs.read"]
+/// memory_limit = 16_777_216
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sandbox {
+    intrinsics: Intrinsics,
+    freeze_builtins: bool,
+    allow_eval: bool,
+    capabilities: BTreeSet<String>,
+    memory_limit: Option<usize>,
+    max_stack_size: Option<usize>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Sandbox {
+            intrinsics: Intrinsics::all(),
+            freeze_builtins: true,
+            allow_eval: false,
+            capabilities: BTreeSet::new(),
+            memory_limit: None,
+            max_stack_size: None,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Creates a profile with all intrinsics, frozen builtins, no `eval` and no capabilities.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn intrinsics(mut self, intrinsics: Intrinsics) -> Self {
+        self.intrinsics = intrinsics;
+        self
+    }
+
+    pub fn freeze_builtins(mut self, freeze_builtins: bool) -> Self {
+        self.freeze_builtins = freeze_builtins;
+        self
+    }
+
+    /// Allows `eval`, `Function` and the other constructors compiling strings.
+    pub fn allow_eval(mut self, allow_eval: bool) -> Self {
+        self.allow_eval = allow_eval;
+        self
+    }
+
+    /// Grants a capability. A capability also grants its sub-capabilities, e.g. `fs` grants `fs.read`.
+    pub fn grant(mut self, capability: &str) -> Self {
+        self.capabilities.insert(capability.to_owned());
+        self
+    }
+
+    pub fn memory_limit(mut self, memory_limit: Option<usize>) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    pub fn max_stack_size(mut self, max_stack_size: Option<usize>) -> Self {
+        self.max_stack_size = max_stack_size;
+        self
+    }
+
+    /// Loads a profile from TOML. Missing keys keep the defaults of `Sandbox::new`.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(source: &str) -> Result<Sandbox> {
+        let profile: Profile = toml::from_str(source)
+            .map_err(|e| Error::with_str(ErrorKind::SyntaxError, &format!("invalid profile: {}", e)))?;
+        let mut sandbox = Sandbox::new();
+        if let Some(names) = profile.intrinsics {
+            sandbox.intrinsics = Intrinsics::empty();
+            for name in names {
+                sandbox.intrinsics |= Intrinsics::from_name(&name.to_uppercase())
+                    .ok_or_else(|| Error::with_str(ErrorKind::RangeError, &format!("unknown intrinsic `{}`", name)))?;
+            }
+        }
+        if let Some(freeze_builtins) = profile.freeze_builtins {
+            sandbox.freeze_builtins = freeze_builtins;
+        }
+        if let Some(allow_eval) = profile.allow_eval {
+            sandbox.allow_eval = allow_eval;
+        }
+        if let Some(capabilities) = profile.capabilities {
+            sandbox.capabilities = capabilities;
+        }
+        sandbox.memory_limit = profile.memory_limit.or(sandbox.memory_limit);
+        sandbox.max_stack_size = profile.max_stack_size.or(sandbox.max_stack_size);
+        Ok(sandbox)
+    }
+
+    pub fn is_granted(&self, capability: &str) -> bool {
+        let mut capability = capability;
+        loop {
+            if self.capabilities.contains(capability) {
+                return true;
+            }
+            match capability.rfind('.') {
+                Some(i) => capability = &capability[..i],
+                None => return false,
+            }
+        }
+    }
+
+    /// Creates a context following the profile. The limits of the profile are applied to `rt`.
+    pub fn new_context_scope<'r>(&self, rt: Runtime<'r>) -> Result<ContextScope<'r>> {
+        if let Some(memory_limit) = self.memory_limit {
+            rt.set_memory_limit(memory_limit);
+        }
+        if let Some(max_stack_size) = self.max_stack_size {
+            rt.set_max_stack_size(max_stack_size);
+        }
+        let ctxs = ContextScope::new_raw(rt);
+        self.setup(ctxs.get())?;
+        Ok(ctxs)
+    }
+
+    fn setup(&self, ctx: Context) -> Result<()> {
+        ctx.add_intrinsic_base_objects();
+        // needed to evaluate scripts, but the global `eval` is removed below
+        ctx.add_intrinsic_eval();
+        let intrinsics: [(Intrinsics, fn(Context)); 9] = [
+            (Intrinsics::DATE, |ctx| ctx.add_intrinsic_date()),
+            (Intrinsics::STRING_NORMALIZE, |ctx| ctx.add_intrinsic_string_normalize()),
+            (Intrinsics::REG_EXP, |ctx| {
+                ctx.add_intrinsic_reg_exp_compiler();
+                ctx.add_intrinsic_reg_exp();
+            }),
+            (Intrinsics::JSON, |ctx| ctx.add_intrinsic_json()),
+            (Intrinsics::PROXY, |ctx| ctx.add_intrinsic_proxy()),
+            (Intrinsics::MAP_SET, |ctx| ctx.add_intrinsic_map_set()),
+            (Intrinsics::TYPED_ARRAYS, |ctx| ctx.add_intrinsic_typed_arrays()),
+            (Intrinsics::PROMISE, |ctx| ctx.add_intrinsic_promise()),
+            (Intrinsics::BIG_INT, |ctx| ctx.add_intrinsic_big_int()),
+        ];
+        for (flag, add) in intrinsics {
+            if self.intrinsics.contains(flag) {
+                add(ctx);
+            }
+        }
+        if !self.allow_eval {
+            disable_eval(ctx, self.intrinsics.contains(Intrinsics::PROMISE))?;
+        }
+        if self.freeze_builtins {
+            ctx.eval(FREEZE_BUILTINS, "<sandbox>", EvalFlags::TYPE_GLOBAL)?;
+        }
+        Ok(())
+    }
+
+    /// Defines the global function `name` if `capability` is granted. Returns whether it is defined.
+    /// The function can neither be replaced nor modified by scripts.
+    pub fn expose<'q, F>(&self, ctx: Context<'q>, capability: &str, name: &str, length: i32, func: F) -> Result<bool>
+    where
+        F: Fn(Context<'q>, Value<'q>, &[Value<'q>]) -> Result<Value<'q>> + 'q,
+    {
+        if !self.is_granted(capability) {
+            return Ok(false);
+        }
+        let func = ctx.new_function(func, name, length)?;
+        func.freeze()?;
+        ctx.global_object()?
+            .define_property_value_from(name, func, PropFlags::ENUMERABLE)?;
+        Ok(true)
+    }
+}
+
+/// Removes the global `eval` and `Function`, and the `constructor`s of function prototypes compiling strings.
+fn disable_eval(ctx: Context, async_functions: bool) -> Result<()> {
+    let disabled = ctx.new_function(
+        |_ctx, _this, _args| {
+            Err(Error::with_str(
+                ErrorKind::EvalError,
+                "code generation from strings is disallowed",
+            ))
+        },
+        "Function",
+        1,
+    )?;
+    let mut functions = vec!["(function () {})", "(function* () {})"];
+    if async_functions {
+        functions.extend(["(async function () {})", "(async function* () {})"]);
+    }
+    for function in functions {
+        let proto = ctx.eval(function, "<sandbox>", EvalFlags::TYPE_GLOBAL)?.prototype()?;
+        proto.define_property_value_from("constructor", disabled.clone(), PropFlags::empty())?;
+    }
+    let global = ctx.global_object()?;
+    global.define_property_value_from("eval", ctx.undefined(), PropFlags::empty())?;
+    global.define_property_value_from("Function", ctx.undefined(), PropFlags::empty())?;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use crate::{
+    context::Context,
+    error::Error,
+    flags::{EvalFlags, ReadObjFlags, WriteObjFlags},
+    result::Result,
+    value::Value,
+};
+use quijine_core::QUICKJS_VERSION;
+use std::{convert::TryInto, error::Error as StdError, fmt, result::Result as StdResult};
+
+const MAGIC: &[u8; 4] = b"QJBC";
+const FORMAT_VERSION: u8 = 1;
+
+/// `CompiledScript` is bytecode of a script or a module which can be cached and evaluated later.
+///
+/// The serialized form starts with a header recording the QuickJS version and the eval flags,
+/// so that a blob written by another version of QuickJS is refused instead of being misread.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CompiledScript {
+    eval_flags: EvalFlags,
+    bytecode: Vec<u8>,
+}
+
+impl CompiledScript {
+    pub(crate) fn compile(ctx: Context, code: &str, filename: &str, eval_flags: EvalFlags) -> Result<CompiledScript> {
+        let eval_flags = eval_flags - EvalFlags::FLAG_COMPILE_ONLY;
+        let func = ctx.eval(code, filename, eval_flags | EvalFlags::FLAG_COMPILE_ONLY)?;
+        let bytecode = ctx.write_object(func, WriteObjFlags::BYTECODE)?;
+        Ok(CompiledScript { eval_flags, bytecode })
+    }
+
+    /// Returns the flags given to `Context::compile`.
+    #[inline]
+    pub fn eval_flags(&self) -> EvalFlags {
+        self.eval_flags
+    }
+
+    #[inline]
+    pub fn is_module(&self) -> bool {
+        self.eval_flags & EvalFlags::TYPE_MASK == EvalFlags::TYPE_MODULE
+    }
+
+    /// Returns the raw bytecode without the header.
+    #[inline]
+    pub fn bytecode(&self) -> &[u8] {
+        &self.bytecode
+    }
+
+    /// Serializes the script with the header.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let version = QUICKJS_VERSION.as_bytes();
+        let mut buf = Vec::with_capacity(MAGIC.len() + 2 + version.len() + 4 + self.bytecode.len());
+        buf.extend_from_slice(MAGIC);
+        buf.push(FORMAT_VERSION);
+        buf.push(version.len() as u8);
+        buf.extend_from_slice(version);
+        buf.extend_from_slice(&self.eval_flags.bits().to_le_bytes());
+        buf.extend_from_slice(&self.bytecode);
+        buf
+    }
+
+    /// Deserializes a script serialized by `to_bytes`.
+    /// Fails with a `BytecodeError` as the source if the blob is broken or written by another version of QuickJS.
+    pub fn from_bytes(buf: &[u8]) -> Result<CompiledScript> {
+        let rest = buf
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid(BytecodeError::NotCompiled))?;
+        let (&format_version, rest) = rest.split_first().ok_or_else(|| invalid(BytecodeError::Truncated))?;
+        if format_version != FORMAT_VERSION {
+            return Err(invalid(BytecodeError::UnsupportedFormat(format_version)));
+        }
+        let (&version_len, rest) = rest.split_first().ok_or_else(|| invalid(BytecodeError::Truncated))?;
+        let version_len = version_len as usize;
+        if rest.len() < version_len + 4 {
+            return Err(invalid(BytecodeError::Truncated));
+        }
+        let (version, rest) = rest.split_at(version_len);
+        if version != QUICKJS_VERSION.as_bytes() {
+            return Err(invalid(BytecodeError::Stale(
+                String::from_utf8_lossy(version).into_owned(),
+            )));
+        }
+        let (flags, bytecode) = rest.split_at(4);
+        let bits = u32::from_le_bytes(flags.try_into().unwrap());
+        let eval_flags = EvalFlags::from_bits(bits).ok_or_else(|| invalid(BytecodeError::UnknownFlags(bits)))?;
+        Ok(CompiledScript {
+            eval_flags,
+            bytecode: bytecode.to_vec(),
+        })
+    }
+
+    /// Reads the bytecode into the context without evaluating it.
+    #[inline]
+    pub fn load<'q>(&self, ctx: Context<'q>) -> Result<Value<'q>> {
+        ctx.read_object(&self.bytecode, ReadObjFlags::BYTECODE)
+    }
+
+    /// Evaluates the script in the context.
+    /// For a module, it returns the result of the module evaluation.
+    #[inline]
+    pub fn run<'q>(&self, ctx: Context<'q>) -> Result<Value<'q>> {
+        ctx.eval_function(self.load(ctx)?)
+    }
+}
+
+impl fmt::Debug for CompiledScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("CompiledScript")
+            .field("eval_flags", &self.eval_flags)
+            .field("bytecode_len", &self.bytecode.len())
+            .finish()
+    }
+}
+
+/// `BytecodeError` is the source of the error of `CompiledScript::from_bytes`.
+/// The cached blob should be discarded and the script compiled again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BytecodeError {
+    NotCompiled,
+    Truncated,
+    UnsupportedFormat(u8),
+    /// The blob was compiled by another version of QuickJS, which is recorded.
+    Stale(String),
+    UnknownFlags(u32),
+}
+
+impl fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        match self {
+            BytecodeError::NotCompiled => f.write_str("not a compiled script"),
+            BytecodeError::Truncated => f.write_str("truncated header"),
+            BytecodeError::UnsupportedFormat(v) => f.write_fmt(format_args!("unsupported format version: {}", v)),
+            BytecodeError::Stale(version) => f.write_fmt(format_args!(
+                "stale bytecode: compiled by QuickJS {} but QuickJS {} is running",
+                version, QUICKJS_VERSION
+            )),
+            BytecodeError::UnknownFlags(bits) => f.write_fmt(format_args!("unknown flags: {:#x}", bits)),
+        }
+    }
+}
+
+impl StdError for BytecodeError {}
+
+#[inline]
+fn invalid(e: BytecodeError) -> Error {
+    Error::external(e)
+}
@@ -0,0 +1,209 @@
+use lazy_static::lazy_static;
+use quijine_core::raw;
+use std::{
+    alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout},
+    collections::HashSet,
+    ffi::c_void,
+    fmt,
+    ptr::{null_mut, NonNull},
+    result::Result as StdResult,
+    slice,
+    sync::{
+        atomic::{fence, AtomicI32, AtomicU8, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+// keeps the data aligned for Float64Array and BigInt64Array
+const ALIGN: usize = 16;
+const HEADER_SIZE: usize = 16;
+
+#[repr(C)]
+struct Header {
+    ref_count: AtomicUsize,
+    len: usize,
+}
+
+lazy_static! {
+    // data pointers of live buffers, used to tell them from memory owned by a runtime
+    static ref BUFFERS: Mutex<HashSet<usize>> = Mutex::new(HashSet::new());
+}
+
+#[inline]
+fn layout(len: usize) -> Option<Layout> {
+    Layout::from_size_align(HEADER_SIZE.checked_add(len.max(1))?, ALIGN).ok()
+}
+
+/// Returns a pointer to zeroed data whose reference count is 1, or null if allocation fails.
+fn allocate(len: usize) -> *mut u8 {
+    let layout = match layout(len) {
+        Some(layout) => layout,
+        None => return null_mut(),
+    };
+    unsafe {
+        let ptr = alloc_zeroed(layout);
+        if ptr.is_null() {
+            return ptr;
+        }
+        (ptr as *mut Header).write(Header {
+            ref_count: AtomicUsize::new(1),
+            len,
+        });
+        let data = ptr.add(HEADER_SIZE);
+        BUFFERS.lock().unwrap().insert(data as usize);
+        data
+    }
+}
+
+#[inline]
+unsafe fn header<'a>(data: *mut u8) -> &'a Header {
+    &*(data.sub(HEADER_SIZE) as *const Header)
+}
+
+#[inline]
+unsafe fn retain(data: *mut u8) {
+    header(data).ref_count.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Retains the buffer unless its last reference has been released. Returns whether it is retained.
+unsafe fn retain_live(data: *mut u8) -> bool {
+    let ref_count = &header(data).ref_count;
+    let mut count = ref_count.load(Ordering::Relaxed);
+    while count != 0 {
+        match ref_count.compare_exchange_weak(count, count + 1, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return true,
+            Err(actual) => count = actual,
+        }
+    }
+    false
+}
+
+unsafe fn release(data: *mut u8) {
+    let header = header(data);
+    if header.ref_count.fetch_sub(1, Ordering::Release) != 1 {
+        return;
+    }
+    fence(Ordering::Acquire);
+    let len = header.len;
+    BUFFERS.lock().unwrap().remove(&(data as usize));
+    dealloc(data.sub(HEADER_SIZE), layout(len).unwrap());
+}
+
+unsafe extern "C" fn sab_alloc(_opaque: *mut c_void, size: raw::c_size_t) -> *mut c_void {
+    allocate(size as usize) as *mut c_void
+}
+
+unsafe extern "C" fn sab_free(_opaque: *mut c_void, ptr: *mut c_void) {
+    release(ptr as *mut u8)
+}
+
+unsafe extern "C" fn sab_dup(_opaque: *mut c_void, ptr: *mut c_void) {
+    retain(ptr as *mut u8)
+}
+
+pub(crate) fn shared_array_buffer_functions() -> raw::JSSharedArrayBufferFunctions {
+    raw::JSSharedArrayBufferFunctions {
+        sab_alloc: Some(sab_alloc),
+        sab_free: Some(sab_free),
+        sab_dup: Some(sab_dup),
+        sab_opaque: null_mut(),
+    }
+}
+
+/// `SharedBuffer` is reference-counted memory which backs `SharedArrayBuffer`s.
+///
+/// It can be sent to other threads and wrapped by `SharedArrayBuffer`s of runtimes
+/// where `Runtime::enable_shared_array_buffer` is called.
+pub struct SharedBuffer {
+    data: NonNull<u8>,
+}
+
+unsafe impl Send for SharedBuffer {}
+unsafe impl Sync for SharedBuffer {}
+
+impl SharedBuffer {
+    /// Allocates zeroed memory.
+    pub fn new(len: usize) -> SharedBuffer {
+        let data = allocate(len);
+        match NonNull::new(data) {
+            Some(data) => SharedBuffer { data },
+            None => handle_alloc_error(layout(len).unwrap_or_else(Layout::new::<Header>)),
+        }
+    }
+
+    /// Returns a new reference if `data` is owned by a `SharedBuffer`.
+    pub(crate) fn from_data(data: *const u8) -> Option<SharedBuffer> {
+        let buffers = BUFFERS.lock().unwrap();
+        if !buffers.contains(&(data as usize)) {
+            return None;
+        }
+        let data = data as *mut u8;
+        // the lock keeps the memory of a registered buffer, but its last reference may be
+        // released already, and then `release` is waiting for the lock to free it
+        if !unsafe { retain_live(data) } {
+            return None;
+        }
+        NonNull::new(data).map(|data| SharedBuffer { data })
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        unsafe { header(self.data.as_ptr()).len }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.data.as_ptr()
+    }
+
+    /// # Safety
+    /// The memory must not be modified by other threads while the slice is alive.
+    #[inline]
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        slice::from_raw_parts(self.as_ptr(), self.len())
+    }
+
+    #[inline]
+    pub fn as_atomic_u8(&self) -> &[AtomicU8] {
+        unsafe { slice::from_raw_parts(self.as_ptr() as *const AtomicU8, self.len()) }
+    }
+
+    /// Returns the memory as `Int32Array` sees it. Trailing bytes are ignored.
+    #[inline]
+    pub fn as_atomic_i32(&self) -> &[AtomicI32] {
+        unsafe { slice::from_raw_parts(self.as_ptr() as *const AtomicI32, self.len() / 4) }
+    }
+
+    /// Returns `true` if both buffers share the same memory.
+    #[inline]
+    pub fn ptr_eq(&self, other: &SharedBuffer) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Clone for SharedBuffer {
+    fn clone(&self) -> Self {
+        unsafe { retain(self.as_ptr()) };
+        SharedBuffer { data: self.data }
+    }
+}
+
+impl Drop for SharedBuffer {
+    fn drop(&mut self) {
+        unsafe { release(self.as_ptr()) }
+    }
+}
+
+impl fmt::Debug for SharedBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("SharedBuffer")
+            .field("ptr", &self.data)
+            .field("len", &self.len())
+            .finish()
+    }
+}
@@ -0,0 +1,663 @@
+use crate::{
+    context::{iterator_result, Context, Intrinsic},
+    convert::IntoQj,
+    error::{Error, ErrorKind},
+    result::Result,
+    types::{Object, Tag},
+    value::Value,
+    PropFlags,
+};
+use futures_core::Stream;
+use quijine_core as qc;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+    future::Future,
+    mem::{take, transmute},
+    pin::Pin,
+    rc::Rc,
+    result::Result as StdResult,
+    sync::{Arc, Condvar, Mutex},
+    task::{self, Poll, Wake, Waker},
+};
+
+/// `Next` is the future returned by `AsyncIter::next`.
+#[derive(Debug)]
+pub struct Next<'a, S: ?Sized>(&'a mut S);
+
+impl<S: Stream + Unpin + ?Sized> Future for Next<'_, S> {
+    type Output = Option<S::Item>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.0).poll_next(cx)
+    }
+}
+
+// message queue
+
+struct Queue<T> {
+    items: VecDeque<T>,
+    senders: usize,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// Creates a queue whose items can be sent from any thread and received as a `Stream`.
+/// The stream ends when all senders are dropped.
+pub fn message_queue<T>() -> (MessageSender<T>, MessageQueue<T>) {
+    let queue = Arc::new(Mutex::new(Queue {
+        items: VecDeque::new(),
+        senders: 1,
+        closed: false,
+        waker: None,
+    }));
+    (MessageSender(queue.clone()), MessageQueue(queue))
+}
+
+/// `MessageSender` is the sending half of `message_queue`.
+pub struct MessageSender<T>(Arc<Mutex<Queue<T>>>);
+
+impl<T> MessageSender<T> {
+    /// Sends `item`. Returns it back if the `MessageQueue` has been dropped.
+    pub fn send(&self, item: T) -> StdResult<(), T> {
+        let waker = {
+            let mut queue = self.0.lock().unwrap();
+            if queue.closed {
+                return Err(item);
+            }
+            queue.items.push_back(item);
+            queue.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for MessageSender<T> {
+    fn clone(&self) -> Self {
+        self.0.lock().unwrap().senders += 1;
+        MessageSender(self.0.clone())
+    }
+}
+
+impl<T> Drop for MessageSender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut queue = self.0.lock().unwrap();
+            queue.senders -= 1;
+            if queue.senders > 0 {
+                return;
+            }
+            queue.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for MessageSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("MessageSender").finish_non_exhaustive()
+    }
+}
+
+/// `MessageQueue` is the receiving half of `message_queue`.
+pub struct MessageQueue<T>(Arc<Mutex<Queue<T>>>);
+
+impl<T> Stream for MessageQueue<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<T>> {
+        let mut queue = self.0.lock().unwrap();
+        if let Some(item) = queue.items.pop_front() {
+            Poll::Ready(Some(item))
+        } else if queue.senders == 0 {
+            Poll::Ready(None)
+        } else {
+            queue.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for MessageQueue<T> {
+    fn drop(&mut self) {
+        let mut queue = self.0.lock().unwrap();
+        queue.closed = true;
+        queue.items.clear();
+    }
+}
+
+impl<T> fmt::Debug for MessageQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("MessageQueue").finish_non_exhaustive()
+    }
+}
+
+// wakers
+
+/// The id woken by the future of `block_on`. Async iterators have positive ids.
+const TASK: u64 = 0;
+
+/// `Signal` records the ids woken from any thread.
+#[derive(Default)]
+struct Signal {
+    woken: Mutex<BTreeSet<u64>>,
+    cond: Condvar,
+}
+
+impl Signal {
+    fn take(&self, id: u64) -> bool {
+        self.woken.lock().unwrap().remove(&id)
+    }
+
+    /// Blocks until some id is woken.
+    fn wait(&self) {
+        let mut woken = self.woken.lock().unwrap();
+        while woken.is_empty() {
+            woken = self.cond.wait(woken).unwrap();
+        }
+    }
+}
+
+struct Wakeup {
+    id: u64,
+    signal: Arc<Signal>,
+}
+
+impl Wake for Wakeup {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.signal.woken.lock().unwrap().insert(self.id);
+        self.signal.cond.notify_all();
+    }
+}
+
+// Rust to JS
+
+/// `Waiting` keeps the requests of an async iterator waiting for its stream.
+struct Waiting {
+    poller: qc::Value<'static>,
+    // the resolving functions of the promises returned by `next`
+    requests: VecDeque<[qc::Value<'static>; 2]>,
+}
+
+/// `AsyncIterators` are the async iterators of a context waiting for their streams.
+pub(crate) struct AsyncIterators {
+    runtime: qc::Runtime<'static>,
+    signal: Arc<Signal>,
+    next_id: u64,
+    waiting: BTreeMap<u64, Waiting>,
+}
+
+impl AsyncIterators {
+    fn new(runtime: qc::Runtime<'static>) -> Self {
+        AsyncIterators {
+            runtime,
+            signal: Arc::new(Signal::default()),
+            next_id: TASK + 1,
+            waiting: BTreeMap::new(),
+        }
+    }
+
+    fn free(&self, waiting: Waiting) {
+        unsafe {
+            self.runtime.free_value(waiting.poller);
+            for [resolve, reject] in waiting.requests {
+                self.runtime.free_value(resolve);
+                self.runtime.free_value(reject);
+            }
+        }
+    }
+}
+
+impl Drop for AsyncIterators {
+    fn drop(&mut self) {
+        for (_, waiting) in take(&mut self.waiting) {
+            self.free(waiting);
+        }
+    }
+}
+
+#[inline]
+fn registry<'a>(ctx: &'a mut Context) -> &'a mut AsyncIterators {
+    let runtime = unsafe { transmute::<qc::Runtime, qc::Runtime<'static>>(ctx.runtime().into()) };
+    ctx.opaque_mut()
+        .async_iterators
+        .get_or_insert_with(|| AsyncIterators::new(runtime))
+}
+
+type StreamState<S> = Rc<RefCell<Option<Pin<Box<S>>>>>;
+
+pub(crate) fn new_async_iterator<'q, S>(mut ctx: Context<'q>, stream: S) -> Result<Object<'q>>
+where
+    S: Stream + 'q,
+    S::Item: IntoQj<'q>,
+{
+    let (id, signal) = {
+        let registry = registry(&mut ctx);
+        let id = registry.next_id;
+        registry.next_id += 1;
+        (id, registry.signal.clone())
+    };
+    let waker = Waker::from(Arc::new(Wakeup { id, signal }));
+    let state: StreamState<S> = Rc::new(RefCell::new(Some(Box::pin(stream))));
+    let obj = ctx.new_object()?;
+    let next = {
+        let state = state.clone();
+        let waker = waker.clone();
+        ctx.new_function(
+            move |mut ctx, _this, _args| {
+                let (promise, resolve, reject) = ctx.new_promise_capability()?;
+                // JS values captured by a closure cannot be collected as a part of cycles,
+                // so the poller is created for each wait
+                if !registry(&mut ctx).waiting.contains_key(&id) {
+                    let poller = new_poller(ctx, id, state.clone(), waker.clone())?;
+                    let waiting = Waiting {
+                        poller: Value::persist(&poller),
+                        requests: VecDeque::new(),
+                    };
+                    registry(&mut ctx).waiting.insert(id, waiting);
+                }
+                let waiting = registry(&mut ctx).waiting.get_mut(&id).unwrap();
+                waiting
+                    .requests
+                    .push_back([Value::persist(&resolve), Value::persist(&reject)]);
+                poll_stream(ctx, id, &state, &waker)?;
+                Ok(promise.into())
+            },
+            "next",
+            0,
+        )?
+    };
+    let return_ = ctx.new_function(
+        move |ctx, _this, args| {
+            state
+                .try_borrow_mut()
+                .map_err(|_| Error::with_str(ErrorKind::TypeError, "async iterator is already running"))?
+                .take();
+            // the requests still waiting end as well
+            poll_stream(ctx, id, &state, &waker)?;
+            let (promise, resolve, _reject) = ctx.new_promise_capability()?;
+            let value = args.first().cloned().unwrap_or_else(|| ctx.undefined().into());
+            ctx.call(
+                resolve.into(),
+                ctx.undefined().into(),
+                &[iterator_result(ctx, value, true)?],
+            )?;
+            Ok(promise.into())
+        },
+        "return",
+        1,
+    )?;
+    let flags = PropFlags::WRITABLE | PropFlags::CONFIGURABLE;
+    obj.define_property_value_from("next", next, flags)?;
+    obj.define_property_value_from("return", return_, flags)?;
+    if let Some(symbol_async_iterator) = ctx.intrinsic(Intrinsic::SymbolAsyncIterator) {
+        let async_iterator = ctx.new_function(|_ctx, this, _args| Ok(this), "[Symbol.asyncIterator]", 0)?;
+        obj.define_property_value_from(symbol_async_iterator, async_iterator, flags)?;
+    }
+    Ok(obj)
+}
+
+fn new_poller<'q, S>(ctx: Context<'q>, id: u64, state: StreamState<S>, waker: Waker) -> Result<Object<'q>>
+where
+    S: Stream + 'q,
+    S::Item: IntoQj<'q>,
+{
+    ctx.new_function(
+        move |ctx, _this, _args| {
+            poll_stream(ctx, id, &state, &waker)?;
+            Ok(ctx.undefined().into())
+        },
+        "poll",
+        0,
+    )
+}
+
+/// Resolves the waiting requests of the async iterator `id` while its stream is ready.
+fn poll_stream<'q, S>(mut ctx: Context<'q>, id: u64, state: &StreamState<S>, waker: &Waker) -> Result<()>
+where
+    S: Stream + 'q,
+    S::Item: IntoQj<'q>,
+{
+    let mut cx = task::Context::from_waker(waker);
+    loop {
+        let waiting = match registry(&mut ctx).waiting.get(&id) {
+            Some(waiting) => !waiting.requests.is_empty(),
+            None => false,
+        };
+        if !waiting {
+            let registry = registry(&mut ctx);
+            if let Some(waiting) = registry.waiting.remove(&id) {
+                registry.free(waiting);
+            }
+            return Ok(());
+        }
+        let item = {
+            let mut state = state
+                .try_borrow_mut()
+                .map_err(|_| Error::with_str(ErrorKind::TypeError, "async iterator is already running"))?;
+            let polled = match state.as_mut() {
+                Some(stream) => stream.as_mut().poll_next(&mut cx),
+                None => Poll::Ready(None),
+            };
+            match polled {
+                Poll::Ready(Some(item)) => Some(item),
+                Poll::Ready(None) => {
+                    // drops the stream as soon as it ends
+                    *state = None;
+                    None
+                }
+                Poll::Pending => return Ok(()),
+            }
+        };
+        let [resolve, reject] = registry(&mut ctx)
+            .waiting
+            .get_mut(&id)
+            .and_then(|waiting| waiting.requests.pop_front())
+            .unwrap();
+        let resolve = Value::from_raw_parts(resolve, ctx.as_raw());
+        let reject = Value::from_raw_parts(reject, ctx.as_raw());
+        let (func, value) = match item.map(|item| item.into_qj(ctx)) {
+            Some(Ok(value)) => (resolve, iterator_result(ctx, value, false)?),
+            None => (resolve, iterator_result(ctx, ctx.undefined().into(), true)?),
+            Some(Err(e)) => {
+                ctx.throw_callback_error(e, "next");
+                (reject, ctx.take_exception())
+            }
+        };
+        ctx.call(func, ctx.undefined().into(), &[value])?;
+    }
+}
+
+/// Polls the async iterators whose streams have been woken. Returns `false` if there is no such iterator.
+pub(crate) fn poll_woken(mut ctx: Context) -> Result<bool> {
+    let raw = ctx.as_raw();
+    let pollers: Vec<_> = {
+        let registry = match ctx.opaque_mut().async_iterators.as_mut() {
+            Some(registry) => registry,
+            None => return Ok(false),
+        };
+        let woken = {
+            let mut woken = registry.signal.woken.lock().unwrap();
+            let task = woken.remove(&TASK);
+            let ids = take(&mut *woken);
+            if task {
+                woken.insert(TASK);
+            }
+            ids
+        };
+        woken
+            .iter()
+            .filter_map(|id| registry.waiting.get(id))
+            .map(|waiting| {
+                raw.dup_value(waiting.poller);
+                Value::from_raw_parts(waiting.poller, raw)
+            })
+            .collect()
+    };
+    let polled = !pollers.is_empty();
+    for poller in pollers {
+        ctx.call(poller, ctx.undefined().into(), &[])?;
+    }
+    Ok(polled)
+}
+
+/// Blocks until a stream is woken, then polls the woken async iterators.
+/// Returns `false` without blocking if no async iterator is waiting.
+pub(crate) fn wait(ctx: Context) -> Result<bool> {
+    let signal = match ctx.opaque().async_iterators.as_ref() {
+        Some(registry) if !registry.waiting.is_empty() => registry.signal.clone(),
+        _ => return Ok(false),
+    };
+    // `block_on` may have been woken after it returned
+    signal.take(TASK);
+    signal.wait();
+    poll_woken(ctx)?;
+    Ok(true)
+}
+
+// JS to Rust
+
+#[derive(Default)]
+struct Settled<'q> {
+    result: Option<StdResult<Value<'q>, Value<'q>>>,
+    waker: Option<Waker>,
+}
+
+fn settle<'q>(settled: &RefCell<Settled<'q>>, result: StdResult<Value<'q>, Value<'q>>) {
+    let waker = {
+        let mut settled = settled.borrow_mut();
+        settled.result = Some(result);
+        settled.waker.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// `AsyncIter` consumes a JS async iterable as a `Stream`. Its futures are driven by `Context::block_on`.
+/// Dropping it before the end calls `return` of the iterator like `break` in `for await`.
+pub struct AsyncIter<'q> {
+    iterator: Value<'q>,
+    next: Value<'q>,
+    pending: Option<Rc<RefCell<Settled<'q>>>>,
+    done: bool,
+}
+
+impl<'q> AsyncIter<'q> {
+    /// Gets the iterator by `Symbol.asyncIterator`, or by `Symbol.iterator` for sync iterables.
+    pub(crate) fn new(iterable: &Value<'q>) -> Result<Self> {
+        let ctx = iterable.context();
+        let mut method: Value = ctx.undefined().into();
+        for symbol in [Intrinsic::SymbolAsyncIterator, Intrinsic::SymbolIterator] {
+            if let Some(symbol) = ctx.intrinsic(symbol) {
+                method = iterable.get(symbol)?;
+            }
+            if !method.is_undefined() && !method.is_null() {
+                break;
+            }
+        }
+        if !method.is_function() {
+            return Err(Error::with_str(ErrorKind::TypeError, "value is not async iterable"));
+        }
+        let iterator = ctx.call(method, iterable.clone(), &[])?;
+        let next = iterator.get("next")?;
+        Ok(AsyncIter {
+            iterator,
+            next,
+            pending: None,
+            done: false,
+        })
+    }
+
+    /// Returns a future which resolves to the next item, like `StreamExt::next` of `futures`.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> Next<'_, Self> {
+        Next(self)
+    }
+
+    /// Calls `next` and subscribes to the settlement of its result.
+    fn request(&self) -> Result<Rc<RefCell<Settled<'q>>>> {
+        let ctx = self.iterator.context();
+        let result = ctx.call(self.next.clone(), self.iterator.clone(), &[])?;
+        let promise_ctor = ctx.require_intrinsic(Intrinsic::Promise)?;
+        let resolve = ctx.require_intrinsic(Intrinsic::PromiseResolve)?;
+        let promise = ctx.call(resolve, promise_ctor, &[result])?;
+        let settled = Rc::new(RefCell::new(Settled::default()));
+        let on_fulfilled = {
+            let settled = settled.clone();
+            ctx.new_function(
+                move |ctx, _this, args| {
+                    settle(
+                        &settled,
+                        Ok(args.first().cloned().unwrap_or_else(|| ctx.undefined().into())),
+                    );
+                    Ok(ctx.undefined().into())
+                },
+                "",
+                1,
+            )?
+        };
+        let on_rejected = {
+            let settled = settled.clone();
+            ctx.new_function(
+                move |ctx, _this, args| {
+                    settle(
+                        &settled,
+                        Err(args.first().cloned().unwrap_or_else(|| ctx.undefined().into())),
+                    );
+                    Ok(ctx.undefined().into())
+                },
+                "",
+                1,
+            )?
+        };
+        ctx.call(
+            ctx.require_intrinsic(Intrinsic::PromiseThen)?,
+            promise,
+            &[on_fulfilled.into(), on_rejected.into()],
+        )?;
+        Ok(settled)
+    }
+
+    fn finish(&mut self, result: StdResult<Value<'q>, Value<'q>>) -> Option<Result<Value<'q>>> {
+        let result = match result {
+            Ok(result) => result,
+            Err(reason) => {
+                self.done = true;
+                return Some(Err(Error::from_js_error(ErrorKind::InternalError, reason)));
+            }
+        };
+        if result.tag() != Tag::Object {
+            self.done = true;
+            return Some(Err(Error::with_str(
+                ErrorKind::TypeError,
+                "iterator result is not an object",
+            )));
+        }
+        match result.get::<_, Value>("done").and_then(|done| done.to_bool()) {
+            Ok(true) => {
+                self.done = true;
+                None
+            }
+            Ok(false) => Some(result.get("value")),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'q> Stream for AsyncIter<'q> {
+    type Item = Result<Value<'q>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let settled = match this.pending.take() {
+            Some(settled) => settled,
+            None => match this.request() {
+                Ok(settled) => settled,
+                Err(e) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            },
+        };
+        let result = {
+            let mut s = settled.borrow_mut();
+            match s.result.take() {
+                Some(result) => result,
+                None => {
+                    s.waker = Some(cx.waker().clone());
+                    drop(s);
+                    this.pending = Some(settled);
+                    return Poll::Pending;
+                }
+            }
+        };
+        Poll::Ready(this.finish(result))
+    }
+}
+
+impl Drop for AsyncIter<'_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let ctx = self.iterator.context();
+        if let Ok(return_) = self.iterator.get::<_, Value>("return") {
+            if return_.is_function() {
+                let _ = ctx.call(return_, self.iterator.clone(), &[]);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AsyncIter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("AsyncIter")
+            .field("iterator", &self.iterator)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+// executor
+
+#[cfg(feature = "timers")]
+#[inline]
+fn run_timer(ctx: Context) -> Result<bool> {
+    crate::timers::run_next(ctx, None)
+}
+
+#[cfg(not(feature = "timers"))]
+#[inline]
+fn run_timer(_ctx: Context) -> Result<bool> {
+    Ok(false)
+}
+
+/// Polls `future` to completion, running pending jobs, timers and async iterators while it waits.
+pub(crate) fn block_on<F: Future>(mut ctx: Context, future: F) -> Result<F::Output> {
+    let signal = registry(&mut ctx).signal.clone();
+    let waker = Waker::from(Arc::new(Wakeup {
+        id: TASK,
+        signal: signal.clone(),
+    }));
+    let mut cx = task::Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    let rt = ctx.runtime();
+    let result = (|| loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Ok(output);
+        }
+        loop {
+            rt.run_pending_jobs()?;
+            poll_woken(ctx)?;
+            if signal.take(TASK) {
+                break;
+            }
+            if rt.is_job_pending() || run_timer(ctx)? {
+                continue;
+            }
+            signal.wait();
+        }
+    })();
+    // a wakeup left behind would make every later wait return at once
+    signal.take(TASK);
+    result
+}
@@ -0,0 +1,108 @@
+use crate::{context::Context, runtime::Runtime, value::Value};
+use quijine_core as qc;
+use std::{
+    collections::HashMap,
+    fmt,
+    mem::forget,
+    result::Result as StdResult,
+    sync::{Arc, Mutex},
+};
+
+struct RawValue(qc::Value<'static>);
+
+// values are only touched on the thread of their runtime; other threads merely move them to the garbage
+unsafe impl Send for RawValue {}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    values: HashMap<u64, RawValue>,
+    garbage: Vec<RawValue>,
+    closed: bool,
+}
+
+/// `ThrownRegistry` keeps values thrown in a runtime alive while `ThrownValue`s refer to them.
+#[derive(Default)]
+pub(crate) struct ThrownRegistry(Arc<Mutex<Registry>>);
+
+impl ThrownRegistry {
+    pub(crate) fn hold(&self, value: &Value) -> ThrownValue {
+        let rt = value.context().runtime();
+        let v = value.clone();
+        let raw = qc::Value::into_raw(*v.as_raw());
+        forget(v);
+        let mut registry = self.0.lock().unwrap();
+        free_all(rt, registry.garbage.drain(..));
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry
+            .values
+            .insert(id, RawValue(unsafe { qc::Value::from_raw_static(raw) }));
+        ThrownValue {
+            id,
+            registry: self.0.clone(),
+        }
+    }
+
+    pub(crate) fn restore<'q>(&self, ctx: Context<'q>, thrown: &ThrownValue) -> Option<Value<'q>> {
+        if !Arc::ptr_eq(&self.0, &thrown.registry) {
+            return None;
+        }
+        let registry = self.0.lock().unwrap();
+        let v = registry.values.get(&thrown.id)?.0;
+        ctx.as_raw().dup_value(v);
+        Some(Value::from_raw_parts(v, ctx.as_raw()))
+    }
+
+    /// Frees dropped values. It must be called on the thread of the runtime.
+    pub(crate) fn collect(&self, rt: Runtime) {
+        let mut registry = self.0.lock().unwrap();
+        free_all(rt, registry.garbage.drain(..));
+    }
+
+    /// Frees all values before the runtime is freed.
+    pub(crate) fn close(&self, rt: Runtime) {
+        let mut registry = self.0.lock().unwrap();
+        registry.closed = true;
+        free_all(rt, registry.garbage.drain(..));
+        free_all(rt, registry.values.drain().map(|(_, v)| v));
+    }
+}
+
+fn free_all(rt: Runtime, values: impl Iterator<Item = RawValue>) {
+    let rt: qc::Runtime = rt.into();
+    for v in values {
+        unsafe { rt.free_value(v.0) };
+    }
+}
+
+/// `ThrownValue` is a handle to a value thrown in JS. The value is alive until the handle or its runtime is dropped.
+pub struct ThrownValue {
+    id: u64,
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl ThrownValue {
+    /// Returns the thrown value if it belongs to the runtime of `ctx` and the runtime is alive.
+    pub fn value<'q>(&self, ctx: Context<'q>) -> Option<Value<'q>> {
+        ctx.runtime().thrown_registry().restore(ctx, self)
+    }
+}
+
+impl Drop for ThrownValue {
+    fn drop(&mut self) {
+        let mut registry = self.registry.lock().unwrap();
+        if registry.closed {
+            return;
+        }
+        if let Some(v) = registry.values.remove(&self.id) {
+            registry.garbage.push(v);
+        }
+    }
+}
+
+impl fmt::Debug for ThrownValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.write_fmt(format_args!("ThrownValue({})", self.id))
+    }
+}
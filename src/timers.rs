@@ -0,0 +1,316 @@
+use crate::{
+    context::Context,
+    error::{Error, ErrorKind},
+    result::Result,
+    value::Value,
+};
+use quijine_core::{self as qc, raw};
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::{take, transmute},
+    os::raw::c_int,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "stream")]
+use crate::stream::poll_woken as poll_streams;
+
+/// `Clock` is the time source of timers installed by `Context::install_timers`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Clock {
+    /// Waits for the wall-clock time.
+    Real,
+    /// Never sleeps. The time advances only when the event loop reaches the next timer or the end of `run_for`.
+    Virtual,
+}
+
+struct Timer {
+    callback: qc::Value<'static>,
+    args: Vec<qc::Value<'static>>,
+    interval: Option<Duration>,
+    // the key in the queue, or `None` from when `run_next` dequeues the timer until it is rescheduled or cleared
+    key: Option<(Duration, u64)>,
+}
+
+/// `Timers` are the timers of a context, ordered by their deadlines.
+pub(crate) struct Timers {
+    runtime: qc::Runtime<'static>,
+    clock: Clock,
+    origin: Instant,
+    now: Duration,
+    next_id: i32,
+    next_seq: u64,
+    queue: BTreeMap<(Duration, u64), i32>,
+    timers: HashMap<i32, Timer>,
+}
+
+impl Timers {
+    fn new(runtime: qc::Runtime<'static>, clock: Clock) -> Self {
+        Timers {
+            runtime,
+            clock,
+            origin: Instant::now(),
+            now: Duration::ZERO,
+            next_id: 1,
+            next_seq: 0,
+            queue: BTreeMap::new(),
+            timers: HashMap::new(),
+        }
+    }
+
+    fn now(&self) -> Duration {
+        match self.clock {
+            Clock::Real => self.origin.elapsed(),
+            Clock::Virtual => self.now,
+        }
+    }
+
+    /// Sleeps or advances the virtual clock until `deadline`.
+    fn wait_until(&mut self, deadline: Duration) {
+        match self.clock {
+            Clock::Real => {
+                let now = self.now();
+                if deadline > now {
+                    thread::sleep(deadline - now);
+                }
+            }
+            Clock::Virtual => self.now = self.now.max(deadline),
+        }
+    }
+
+    /// Returns an id which is not used by a live timer. Ids wrap around to 1 after `i32::MAX`.
+    fn next_id(&mut self) -> i32 {
+        loop {
+            let id = self.next_id;
+            self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+            if !self.timers.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+
+    fn schedule(&mut self, id: i32, delay: Duration) {
+        let key = (self.now() + delay, self.next_seq);
+        self.next_seq += 1;
+        self.queue.insert(key, id);
+        if let Some(timer) = self.timers.get_mut(&id) {
+            timer.key = Some(key);
+        }
+    }
+
+    fn next_deadline(&self) -> Option<Duration> {
+        self.queue.keys().next().map(|(deadline, _)| *deadline)
+    }
+
+    fn clear(&mut self, id: i32) {
+        if let Some(timer) = self.timers.remove(&id) {
+            if let Some(key) = timer.key {
+                self.queue.remove(&key);
+            }
+            self.free(timer);
+        }
+    }
+
+    fn free(&self, timer: Timer) {
+        unsafe {
+            self.runtime.free_value(timer.callback);
+            for arg in timer.args {
+                self.runtime.free_value(arg);
+            }
+        }
+    }
+}
+
+impl Drop for Timers {
+    fn drop(&mut self) {
+        for (_, timer) in take(&mut self.timers) {
+            self.free(timer);
+        }
+    }
+}
+
+fn timers_mut<'a>(ctx: &'a mut Context) -> Result<&'a mut Timers> {
+    ctx.opaque_mut()
+        .timers
+        .as_mut()
+        .ok_or_else(|| Error::with_str(ErrorKind::InternalError, "timers are not installed"))
+}
+
+/// Converts a delay in milliseconds like `setTimeout` does.
+fn to_delay(v: Option<&Value>) -> Result<Duration> {
+    let ms = match v {
+        Some(v) => v.to_f64()?,
+        None => 0.0,
+    };
+    Ok(if ms.is_finite() && ms > 0.0 {
+        Duration::from_secs_f64(ms.min(i32::MAX as f64) / 1000.0)
+    } else {
+        Duration::ZERO
+    })
+}
+
+fn add_timer<'q>(mut ctx: Context<'q>, args: &[Value<'q>], repeat: bool) -> Result<Value<'q>> {
+    let callback = match args.first() {
+        Some(f) if f.is_function() => f,
+        _ => return Err(Error::with_str(ErrorKind::TypeError, "callback is not a function")),
+    };
+    let delay = to_delay(args.get(1))?;
+    let timer = Timer {
+        callback: Value::persist(callback),
+        args: args.iter().skip(2).map(Value::persist).collect(),
+        // like browsers, an interval never runs more often than every millisecond
+        interval: if repeat {
+            Some(delay.max(Duration::from_millis(1)))
+        } else {
+            None
+        },
+        key: None,
+    };
+    let timers = timers_mut(&mut ctx)?;
+    let id = timers.next_id();
+    timers.timers.insert(id, timer);
+    timers.schedule(id, delay);
+    Ok(ctx.new_int32(id).into())
+}
+
+fn clear_timer<'q>(mut ctx: Context<'q>, _this: Value<'q>, args: &[Value<'q>]) -> Result<Value<'q>> {
+    if let Some(id) = args.first() {
+        let id = id.to_i32()?;
+        timers_mut(&mut ctx)?.clear(id);
+    }
+    Ok(ctx.undefined().into())
+}
+
+unsafe extern "C" fn run_microtask(ctx: *mut raw::JSContext, _argc: c_int, argv: *mut raw::JSValue) -> raw::JSValue {
+    let ctx = qc::Context::from_raw(ctx);
+    let func = qc::Value::from_raw(*argv, ctx);
+    qc::Value::into_raw(ctx.call(func, qc::Value::undefined(), &[] as &[qc::Value]))
+}
+
+pub(crate) fn install(mut ctx: Context, clock: Clock) -> Result<()> {
+    let runtime = unsafe { transmute::<qc::Runtime, qc::Runtime<'static>>(ctx.runtime().into()) };
+    let timers = Timers::new(runtime, clock);
+    ctx.opaque_mut().timers = Some(timers);
+    let global = ctx.global_object()?;
+    let set_timeout = ctx.new_function(|ctx, _this, args| add_timer(ctx, args, false), "setTimeout", 2)?;
+    global.set("setTimeout", set_timeout)?;
+    let set_interval = ctx.new_function(|ctx, _this, args| add_timer(ctx, args, true), "setInterval", 2)?;
+    global.set("setInterval", set_interval)?;
+    global.set("clearTimeout", ctx.new_function(clear_timer, "clearTimeout", 1)?)?;
+    global.set("clearInterval", ctx.new_function(clear_timer, "clearInterval", 1)?)?;
+    let queue_microtask = ctx.new_function(
+        |ctx, _this, args| {
+            let callback = match args.first() {
+                Some(f) if f.is_function() => f,
+                _ => return Err(Error::with_str(ErrorKind::TypeError, "callback is not a function")),
+            };
+            if ctx.as_raw().enqueue_job(Some(run_microtask), &[*callback.as_raw()]) < 0 {
+                return Err(ctx.internal_js_error());
+            }
+            Ok(ctx.undefined().into())
+        },
+        "queueMicrotask",
+        1,
+    )?;
+    global.set("queueMicrotask", queue_microtask)?;
+    Ok(())
+}
+
+/// Runs the earliest timer if its deadline is not after `end`. Returns `false` if there is no such timer.
+pub(crate) fn run_next(ctx: Context, end: Option<Duration>) -> Result<bool> {
+    let mut this = ctx;
+    let (callback, args) = {
+        let timers = match this.opaque_mut().timers.as_mut() {
+            Some(timers) => timers,
+            None => return Ok(false),
+        };
+        let deadline = match timers.next_deadline() {
+            Some(deadline) if end.map_or(true, |end| deadline <= end) => deadline,
+            _ => return Ok(false),
+        };
+        timers.wait_until(deadline);
+        let key = *timers.queue.keys().next().unwrap();
+        let id = timers.queue.remove(&key).unwrap();
+        let timer = timers.timers.get_mut(&id).unwrap();
+        timer.key = None;
+        let callback = Value::restore(ctx, timer.callback);
+        let args: Vec<_> = timer.args.iter().map(|v| Value::restore(ctx, *v)).collect();
+        match timer.interval {
+            Some(interval) => timers.schedule(id, interval),
+            None => timers.clear(id),
+        }
+        (callback, args)
+    };
+    ctx.call(callback, ctx.undefined().into(), &args)?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "stream"))]
+#[inline]
+fn poll_streams(_ctx: Context) -> Result<bool> {
+    Ok(false)
+}
+
+/// Runs pending jobs, woken async iterators and timers until no timer remains.
+pub(crate) fn run_until_idle(ctx: Context) -> Result<()> {
+    let rt = ctx.runtime();
+    loop {
+        rt.run_pending_jobs()?;
+        if !poll_streams(ctx)? && !run_next(ctx, None)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Runs pending jobs and timers whose deadlines come within `duration`, then waits for the rest of `duration`.
+pub(crate) fn run_for(mut ctx: Context, duration: Duration) -> Result<()> {
+    let rt = ctx.runtime();
+    let end = match ctx.opaque().timers.as_ref() {
+        Some(timers) => timers.now() + duration,
+        None => loop {
+            rt.run_pending_jobs()?;
+            if !poll_streams(ctx)? {
+                return Ok(());
+            }
+        },
+    };
+    loop {
+        rt.run_pending_jobs()?;
+        if !poll_streams(ctx)? && !run_next(ctx, Some(end))? {
+            break;
+        }
+    }
+    if let Some(timers) = ctx.opaque_mut().timers.as_mut() {
+        timers.wait_until(end);
+    }
+    Ok(())
+}
+
+/// Returns the number of active timers.
+pub(crate) fn count(ctx: Context) -> usize {
+    ctx.opaque().timers.as_ref().map_or(0, |timers| timers.timers.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{timers_mut, Clock};
+    use crate::{context, EvalFlags, Result};
+
+    #[test]
+    fn wrap_ids() -> Result<()> {
+        context(|mut ctx| {
+            ctx.install_timers(Clock::Virtual)?;
+            let eval = move |code: &str| ctx.eval_into::<i32>(code, "<input>", EvalFlags::TYPE_GLOBAL);
+            eval("globalThis.log = []; setTimeout(() => log.push('a'), 10)")?;
+            timers_mut(&mut ctx)?.next_id = i32::MAX;
+            assert_eq!(i32::MAX, eval("setTimeout(() => log.push('b'), 20)")?);
+            // 1 is still used by the first timer
+            assert_eq!(2, eval("setTimeout(() => log.push('c'), 30)")?);
+            ctx.run_until_idle()?;
+            let log: String = ctx.eval_into("log.join()", "<input>", EvalFlags::TYPE_GLOBAL)?;
+            assert_eq!("a,b,c", log);
+            Ok(())
+        })
+    }
+}
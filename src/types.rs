@@ -1,12 +1,13 @@
 use crate::{
     class::Class,
     context::Context,
-    convert::IntoQj,
+    convert::{FromQj, IntoQj, IntoQjMulti},
     error::{Error, ErrorKind},
     result::Result,
     util::Opaque,
     value::Value,
 };
+use quijine_core as qc;
 pub use quijine_core::ValueTag as Tag;
 use std::{
     any::type_name,
@@ -167,6 +168,166 @@ impl_as_ref_value! { for Object }
 impl_try_from_value! { Value for Object if v => v.tag() == Tag::Object }
 impl_deref! { HasPtr for Object }
 
+/// `ArrayRef` is an array object, accessed by index without creating atoms.
+#[derive(Clone, Debug)]
+#[repr(transparent)]
+pub struct ArrayRef<'q>(Object<'q>);
+impl_as_ref_value! { for ArrayRef }
+impl_try_from_value! { Value for ArrayRef if v => v.is_array() }
+impl_deref! { Object for ArrayRef }
+
+impl<'q> TryFrom<Object<'q>> for ArrayRef<'q> {
+    type Error = Error;
+
+    fn try_from(v: Object<'q>) -> StdResult<Self, Self::Error> {
+        Value::from(v).try_into()
+    }
+}
+
+impl<'q> ArrayRef<'q> {
+    pub fn len(&self) -> Result<u32> {
+        let len: f64 = self.0.get("length")?;
+        Ok(len as u32)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    #[inline]
+    pub fn get<R: FromQj<'q>>(&self, index: u32) -> Result<R> {
+        self.get_index(index)
+    }
+
+    /// Appends a value and returns the new length.
+    pub fn push<V: IntoQj<'q>>(&self, val: V) -> Result<u32> {
+        let len = self.len()?;
+        self.set_index(len, val)?;
+        Ok(len + 1)
+    }
+
+    /// Appends values.
+    pub fn extend<V: IntoQj<'q>, I: IntoIterator<Item = V>>(&self, vs: I) -> Result<()> {
+        let ctx = self.context();
+        let values = vs.into_iter().map(|v| v.into_qj(ctx)).collect::<Result<Vec<_>>>()?;
+        ctx.set_array_elements(self, self.len()?, values)
+    }
+
+    /// Iterates over the elements up to the length at the time of the call.
+    pub fn iter(&self) -> Result<ArrayIter<'q>> {
+        Ok(ArrayIter {
+            array: self.clone(),
+            index: 0,
+            len: self.len()?,
+        })
+    }
+
+    pub fn to_f64_vec(&self) -> Result<Vec<f64>> {
+        self.to_vec_with(|v, ctx| v.to_f64(ctx))
+    }
+
+    pub fn to_i32_vec(&self) -> Result<Vec<i32>> {
+        self.to_vec_with(|v, ctx| v.to_i32(ctx))
+    }
+
+    fn to_vec_with<T, F>(&self, f: F) -> Result<Vec<T>>
+    where
+        F: Fn(qc::Value<'q>, qc::Context<'q>) -> Option<T>,
+    {
+        let ctx = self.context();
+        let len = self.len()?;
+        let mut vs = Vec::with_capacity(len as usize);
+        for i in 0..len {
+            let v = self.as_raw().property_uint32(ctx.as_raw(), i);
+            if v.is_exception() {
+                return Err(ctx.internal_js_error());
+            }
+            let x = f(v, ctx.as_raw());
+            unsafe { ctx.as_raw().free_value(v) };
+            match x {
+                Some(x) => vs.push(x),
+                None => {
+                    // a conversion may fail with or without throwing
+                    let e = ctx.take_exception();
+                    let mut message = format!("cannot convert the element at index {}", i);
+                    if !e.is_null() {
+                        message += &format!(": {}", e.to_string().unwrap_or_default());
+                    }
+                    return Err(Error::with_str(ErrorKind::TypeError, &message));
+                }
+            }
+        }
+        Ok(vs)
+    }
+}
+
+pub struct ArrayIter<'q> {
+    array: ArrayRef<'q>,
+    index: u32,
+    len: u32,
+}
+
+impl<'q> Iterator for ArrayIter<'q> {
+    type Item = Result<Value<'q>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            return None;
+        }
+        let v = self.array.get_index(self.index);
+        self.index += 1;
+        Some(v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = (self.len - self.index) as usize;
+        (n, Some(n))
+    }
+}
+
+/// `Function` is a callable object.
+#[derive(Clone, Debug)]
+#[repr(transparent)]
+pub struct Function<'q>(Object<'q>);
+impl_as_ref_value! { for Function }
+impl_try_from_value! { Value for Function if v => v.is_function() }
+impl_deref! { Object for Function }
+
+impl<'q> TryFrom<Object<'q>> for Function<'q> {
+    type Error = Error;
+
+    fn try_from(v: Object<'q>) -> StdResult<Self, Self::Error> {
+        Value::from(v).try_into()
+    }
+}
+
+impl<'q> Function<'q> {
+    /// Calls the function with `this` as `undefined`.
+    #[inline]
+    pub fn call<A: IntoQjMulti<'q>, R: FromQj<'q>>(&self, args: A) -> Result<R> {
+        let ctx = self.context();
+        ctx.call_into(self.clone(), ctx.undefined(), args)
+    }
+
+    #[inline]
+    pub fn call_with_this<T: IntoQj<'q>, A: IntoQjMulti<'q>, R: FromQj<'q>>(&self, this: T, args: A) -> Result<R> {
+        self.context().call_into(self.clone(), this, args)
+    }
+
+    /// Calls the function as `new f(...args)`.
+    pub fn construct<A: IntoQjMulti<'q>>(&self, args: A) -> Result<Object<'q>> {
+        let ctx = self.context();
+        let args = args.into_qj_multi(ctx)?;
+        let obj = ctx.call_constructor(self.clone().into(), args.as_ref())?;
+        obj.try_into()
+    }
+
+    /// Returns a closure which converts its arguments and calls the function, e.g. `f.into_rust_fn::<(i32, i32), i32>()`.
+    pub fn into_rust_fn<A: IntoQjMulti<'q>, R: FromQj<'q>>(self) -> impl Fn(A) -> Result<R> + 'q {
+        move |args| self.call(args)
+    }
+}
+
 // values
 #[derive(Clone, Debug)]
 #[repr(transparent)]
@@ -307,6 +468,24 @@ impl<'q> fmt::Debug for Variant<'q> {
 #[repr(transparent)]
 pub struct ClassObject<'q, C: Class + 'static>(Object<'q>, PhantomData<C>);
 
+impl<'q, C: Class + 'static> ClassObject<'q, C> {
+    /// Finds the object holding `C` in `v` and its prototype chain,
+    /// e.g. the instance of `C` a JS object inherits from with `Object.create`.
+    pub fn try_from_instance(v: Value<'q>) -> Result<Self> {
+        let mut current = v;
+        while current.tag() == Tag::Object {
+            if current.opaque::<C>().is_some() {
+                return Ok(unsafe { current.as_any::<Self>().clone() });
+            }
+            current = current.prototype()?;
+        }
+        Err(Error::with_str(
+            ErrorKind::TypeError,
+            &format!("not an instance of {}", C::name()),
+        ))
+    }
+}
+
 impl<'q, C: Class + 'static> Clone for ClassObject<'q, C> {
     fn clone(&self) -> Self {
         ClassObject(self.0.clone(), PhantomData)
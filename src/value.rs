@@ -1,20 +1,26 @@
+#[cfg(feature = "stream")]
+use crate::stream::AsyncIter;
 use crate::{
     atom::{Atom, PropertyEnum},
     class::Class,
-    context::Context,
+    clone::structured_clone,
+    context::{Context, Intrinsic},
     convert::{FromQj, IntoQj, IntoQjAtom},
     error::{Error, ErrorKind},
+    inspect::{InspectOptions, Inspector},
     result::Result,
     runtime::Runtime,
+    shared::SharedBuffer,
     string::CString as QjCString,
     types::{Tag, Variant},
     IntoQjMulti,
 };
-use qc::{GpnFlags, PropFlags};
+use qc::{GpnFlags, PromiseState, PropFlags};
 use quijine_core as qc;
 #[cfg(feature = "debug_leak")]
 use std::sync::atomic;
 use std::{
+    collections::HashSet,
     convert::TryInto,
     ffi::c_void,
     fmt,
@@ -108,6 +114,23 @@ impl<'q> Value<'q> {
         this.debug_trace("dup");
     }
 
+    /// Returns a new reference to the value which can be kept beyond the scope of the context, e.g. in its
+    /// opaque. The reference must be freed by the runtime.
+    #[inline]
+    pub(crate) fn persist(this: &Self) -> qc::Value<'static> {
+        let v = this.clone();
+        let raw = qc::Value::into_raw(v.value);
+        forget(v);
+        unsafe { qc::Value::from_raw_static(raw) }
+    }
+
+    /// Returns a new reference to a value kept by `Value::persist`.
+    #[inline]
+    pub(crate) fn restore(ctx: Context<'q>, v: qc::Value<'static>) -> Value<'q> {
+        ctx.as_raw().dup_value(v);
+        Value::from_raw_parts(v, ctx.as_raw())
+    }
+
     #[allow(unused_variables)]
     #[inline]
     fn debug_trace(&self, name: &str) {
@@ -157,6 +180,22 @@ impl<'q> Value<'q> {
         self.value.is_error(self.context)
     }
 
+    /// Returns the state of a promise, or `None` if `self` is not a promise.
+    #[inline]
+    pub fn promise_state(&self) -> Option<PromiseState> {
+        self.context.promise_state(self.value)
+    }
+
+    /// Returns the value or the reason a promise was settled with, `undefined` while it is
+    /// pending, or `None` if `self` is not a promise.
+    #[inline]
+    pub fn promise_result(&self) -> Option<Value<'q>> {
+        let value = self.context.promise_result(self.value)?;
+        let value = Value::from_raw_parts(value, self.context);
+        Value::dup(&value);
+        Some(value)
+    }
+
     // conversion
 
     #[inline]
@@ -211,7 +250,7 @@ impl<'q> Value<'q> {
     }
 
     #[inline]
-    pub fn to_c_string(&self) -> Result<QjCString> {
+    pub fn to_c_string(&self) -> Result<QjCString<'q>> {
         self.ok_or_type_error(
             self.value
                 .to_c_string(self.context)
@@ -286,6 +325,71 @@ impl<'q> Value<'q> {
         self.has_property(key.into_qj_atom(self.context())?)
     }
 
+    #[inline]
+    pub fn has_own<K>(&self, key: K) -> Result<bool>
+    where
+        K: IntoQjAtom<'q>,
+    {
+        Ok(self.own_property(key.into_qj_atom(self.context())?)?.is_some())
+    }
+
+    /// Gets a property, or returns the default value if it is `undefined`.
+    #[inline]
+    pub fn get_or_default<K, R>(&self, key: K) -> Result<R>
+    where
+        K: IntoQjAtom<'q>,
+        R: FromQj<'q> + Default,
+    {
+        let v = self.property(key.into_qj_atom(self.context())?)?;
+        if v.is_undefined() {
+            Ok(R::default())
+        } else {
+            R::from_qj(v)
+        }
+    }
+
+    /// Gets an indexed property without creating an atom.
+    #[inline]
+    pub fn get_index<R>(&self, index: u32) -> Result<R>
+    where
+        R: FromQj<'q>,
+    {
+        let v = unsafe {
+            self.context()
+                .wrap_result(self.value.property_uint32(self.context, index))?
+        };
+        R::from_qj(v)
+    }
+
+    /// Sets an indexed property without creating an atom.
+    #[inline]
+    pub fn set_index<V>(&self, index: u32, val: V) -> Result<bool>
+    where
+        V: IntoQj<'q>,
+    {
+        let val = val.into_qj(self.context())?;
+        Value::dup(&val);
+        let ret = self.value.set_property_uint32(self.context, index, *val.as_raw());
+        self.context().map_err_to_exception(ret)
+    }
+
+    /// Deletes a property. With `PropFlags::THROW`, an error is thrown if the property is not configurable.
+    #[inline]
+    pub fn delete_property(&self, key: Atom<'q>, flags: PropFlags) -> Result<bool> {
+        let ret = self.value.delete_property(self.context, *key.as_raw(), flags);
+        self.context().map_err_to_exception(ret)
+    }
+
+    /// Deletes a property like the `delete` operator in sloppy mode.
+    /// Returns `false` if the property is not configurable.
+    #[inline]
+    pub fn delete<K>(&self, key: K) -> Result<bool>
+    where
+        K: IntoQjAtom<'q>,
+    {
+        self.delete_property(key.into_qj_atom(self.context())?, PropFlags::empty())
+    }
+
     #[inline]
     pub fn is_extensible(&self) -> Result<bool> {
         self.context()
@@ -301,14 +405,10 @@ impl<'q> Value<'q> {
     #[inline]
     pub fn own_property_names(&self, flags: GpnFlags) -> Result<Vec<PropertyEnum<'q>>> {
         if let Ok(vs) = self.value.own_property_names(self.context, flags) {
+            // the atoms are owned by the returned enums
             Ok(vs
-                .iter()
-                .map(|v| {
-                    let v = PropertyEnum::from_raw_parts(v.clone(), self.context);
-                    // must be dupped
-                    PropertyEnum::dup(&v);
-                    v
-                })
+                .into_iter()
+                .map(|v| PropertyEnum::from_raw_parts(v, self.context))
                 .collect())
         } else {
             Err(Context::from_raw(self.context).internal_js_error())
@@ -316,7 +416,7 @@ impl<'q> Value<'q> {
     }
 
     #[inline]
-    pub fn own_property(&self, prop: Atom<'q>) -> Result<Option<PropertyDescriptor>> {
+    pub fn own_property(&self, prop: Atom<'q>) -> Result<Option<PropertyDescriptor<'q>>> {
         let ret = self.value.own_property(self.context, *prop.as_raw());
         self.context()
             .map_err_to_exception(ret)
@@ -394,6 +494,152 @@ impl<'q> Value<'q> {
         )
     }
 
+    /// Defines a property like `Object.defineProperty`.
+    pub fn define_property_from<K: IntoQjAtom<'q>>(&self, prop: K, desc: &PropertyDescriptor<'q>) -> Result<bool> {
+        check_descriptor(desc)?;
+        self.define_property(
+            prop.into_qj_atom(self.context())?,
+            desc.value().clone(),
+            desc.getter().clone(),
+            desc.setter().clone(),
+            desc.flags() - PropFlags::GETSET,
+        )
+    }
+
+    /// Defines properties like `Object.defineProperties`. Returns `false` when a property cannot be defined.
+    ///
+    /// All descriptors are validated before any property is defined, but this is not atomic:
+    /// the properties defined before a property which cannot be defined are kept, as in JS.
+    pub fn define_properties<K, I>(&self, props: I) -> Result<bool>
+    where
+        K: IntoQjAtom<'q>,
+        I: IntoIterator<Item = (K, PropertyDescriptor<'q>)>,
+    {
+        let props: Vec<_> = props.into_iter().collect();
+        for (_, desc) in &props {
+            check_descriptor(desc)?;
+        }
+        for (prop, desc) in props {
+            if !self.define_property_from(prop, &desc)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the descriptors of all own properties like `Object.getOwnPropertyDescriptors`, in key order.
+    pub fn own_property_descriptors(&self) -> Result<Vec<(Atom<'q>, PropertyDescriptor<'q>)>> {
+        let mut descs = Vec::new();
+        for key in self.own_keys()? {
+            let atom = key.atom();
+            if let Some(desc) = self.own_property(atom.clone())? {
+                descs.push((atom, desc));
+            }
+        }
+        Ok(descs)
+    }
+
+    // integrity
+
+    fn own_keys(&self) -> Result<Vec<PropertyEnum<'q>>> {
+        self.own_property_names(GpnFlags::STRING_MASK | GpnFlags::SYMBOL_MASK)
+    }
+
+    fn set_integrity_level(&self, frozen: bool) -> Result<bool> {
+        if self.tag() != Tag::Object {
+            return Ok(true);
+        }
+        if !self.prevent_extensions()? {
+            return Ok(false);
+        }
+        let undefined: Value = self.context().undefined().into();
+        for key in self.own_keys()? {
+            let atom = key.atom();
+            let mut flags = PropFlags::HAS_CONFIGURABLE | PropFlags::THROW;
+            if frozen {
+                match self.own_property(atom.clone())? {
+                    Some(desc) if desc.flags().contains(PropFlags::GETSET) => {}
+                    Some(_) => flags |= PropFlags::HAS_WRITABLE,
+                    None => continue,
+                }
+            }
+            self.define_property(atom, undefined.clone(), undefined.clone(), undefined.clone(), flags)?;
+        }
+        Ok(true)
+    }
+
+    fn test_integrity_level(&self, frozen: bool) -> Result<bool> {
+        if self.tag() != Tag::Object {
+            return Ok(true);
+        }
+        if self.is_extensible()? {
+            return Ok(false);
+        }
+        for key in self.own_keys()? {
+            if let Some(desc) = self.own_property(key.atom())? {
+                let flags = desc.flags();
+                if flags.contains(PropFlags::CONFIGURABLE) {
+                    return Ok(false);
+                }
+                if frozen && !flags.contains(PropFlags::GETSET) && flags.contains(PropFlags::WRITABLE) {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Makes all properties non-configurable and prevents extensions like `Object.seal`.
+    /// Returns `false` if the object refuses it (e.g. a `Proxy`).
+    #[inline]
+    pub fn seal(&self) -> Result<bool> {
+        self.set_integrity_level(false)
+    }
+
+    /// Makes all properties read-only and non-configurable and prevents extensions like `Object.freeze`.
+    #[inline]
+    pub fn freeze(&self) -> Result<bool> {
+        self.set_integrity_level(true)
+    }
+
+    /// Freezes the object and all objects reachable through its own properties, including accessors.
+    /// Prototypes are left as they are.
+    pub fn deep_freeze(&self) -> Result<bool> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![self.clone()];
+        while let Some(value) = stack.pop() {
+            match value.value.ptr() {
+                Some(p) if value.tag() == Tag::Object => {
+                    if !visited.insert(p) {
+                        continue;
+                    }
+                }
+                _ => continue,
+            }
+            if !value.freeze()? {
+                return Ok(false);
+            }
+            for key in value.own_keys()? {
+                if let Some(desc) = value.own_property(key.atom())? {
+                    stack.extend([desc.value(), desc.getter(), desc.setter()].into_iter().cloned());
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns `true` for primitives, and for objects in the state given by `Object.seal`.
+    #[inline]
+    pub fn is_sealed(&self) -> Result<bool> {
+        self.test_integrity_level(false)
+    }
+
+    /// Returns `true` for primitives, and for objects in the state given by `Object.freeze`.
+    #[inline]
+    pub fn is_frozen(&self) -> Result<bool> {
+        self.test_integrity_level(true)
+    }
+
     // function
 
     #[inline]
@@ -420,10 +666,33 @@ impl<'q> Value<'q> {
     // class
 
     #[inline]
-    pub fn prototype(&self) -> Result<Value> {
+    pub fn prototype(&self) -> Result<Value<'q>> {
         unsafe { self.context().wrap_result(self.value.prototype(self.context)) }
     }
 
+    /// Sets the prototype like `Object.setPrototypeOf`.
+    #[inline]
+    pub fn set_prototype(&self, proto: &Value<'q>) -> Result<()> {
+        let ret = self.value.set_prototype(self.context, *proto.as_raw());
+        self.context().map_err_to_exception(ret).map(|_| ())
+    }
+
+    /// Returns the result of `this instanceof ctor`.
+    #[inline]
+    pub fn instance_of(&self, ctor: &Value<'q>) -> Result<bool> {
+        self.value
+            .is_instance_of(self.context, *ctor.as_raw())
+            .ok_or_else(|| self.context().internal_js_error())
+    }
+
+    /// Returns a `Stream` over this async iterable, like `for await` does.
+    #[cfg(feature = "stream")]
+    #[inline]
+    pub fn async_iter(&self) -> Result<AsyncIter<'q>> {
+        AsyncIter::new(self)
+    }
+
+    #[allow(clippy::mut_from_ref)]
     #[inline]
     fn opaque_internal<C: Class + 'static>(&self) -> Option<&mut C> {
         let rt = Runtime::from(self.context.runtime());
@@ -489,11 +758,40 @@ impl<'q> Value<'q> {
 
     #[inline]
     fn iterator_raw(&self) -> Result<Value<'q>> {
-        let symbol: Value = self.context().global_object()?.get("Symbol")?;
-        let iterator: Value = symbol.get("iterator")?;
+        let iterator = self.context().require_intrinsic(Intrinsic::SymbolIterator)?;
         self.call_method(iterator, &[])
     }
 
+    /// Returns the memory of a `SharedArrayBuffer` created in a runtime which enables `SharedArrayBuffer`.
+    pub fn shared_array_buffer(&self) -> Option<SharedBuffer> {
+        if !self.context().runtime().is_shared_array_buffer_enabled() {
+            return None;
+        }
+        match call_with_context!(self, array_buffer) {
+            Some(bytes) => SharedBuffer::from_data(bytes.as_ptr()),
+            None => {
+                // not an ArrayBuffer
+                self.context().take_exception();
+                None
+            }
+        }
+    }
+
+    /// Copies the value into another context, which may belong to another runtime.
+    ///
+    /// Functions, symbols and accessor properties can't be cloned, and the error tells where they are.
+    /// Plain objects and arrays are copied directly if both contexts share a runtime.
+    #[inline]
+    pub fn structured_clone_to<'p>(&self, ctx: Context<'p>) -> Result<Value<'p>> {
+        structured_clone(self, ctx)
+    }
+
+    /// Renders the value like Node's `util.inspect`.
+    #[inline]
+    pub fn inspect(&self, options: &InspectOptions) -> Result<String> {
+        Inspector::new(self.context(), options.clone())?.inspect(self)
+    }
+
     #[inline]
     pub fn iterator(&self) -> Result<impl Iterator<Item = Result<Value<'q>>>> {
         let iterator = self.iterator_raw()?;
@@ -570,6 +868,19 @@ impl fmt::Debug for Value<'_> {
     }
 }
 
+fn check_descriptor(desc: &PropertyDescriptor) -> Result<()> {
+    if desc.is_accessor() && desc.is_data() {
+        return Err(Error::with_str(
+            ErrorKind::TypeError,
+            "invalid property descriptor: both accessors and a value or writable attribute are specified",
+        ));
+    }
+    Ok(())
+}
+
+/// `PropertyDescriptor` is a property descriptor like the object passed to `Object.defineProperty`.
+/// An absent field keeps the current attribute of an existing property or falls back to the default.
+#[derive(Clone, Debug)]
 pub struct PropertyDescriptor<'q> {
     flags: PropFlags,
     value: Value<'q>,
@@ -580,33 +891,143 @@ pub struct PropertyDescriptor<'q> {
 impl<'q> PropertyDescriptor<'q> {
     #[inline]
     pub(crate) fn from_raw_parts(desc: qc::PropertyDescriptor<'q>, ctx: qc::Context<'q>) -> PropertyDescriptor<'q> {
+        let mut flags = desc.flags() | PropFlags::HAS_ENUMERABLE | PropFlags::HAS_CONFIGURABLE;
+        if flags.contains(PropFlags::GETSET) {
+            flags |= PropFlags::HAS_GET | PropFlags::HAS_SET;
+        } else {
+            flags |= PropFlags::HAS_VALUE | PropFlags::HAS_WRITABLE;
+        }
         PropertyDescriptor {
-            flags: desc.flags(),
+            flags,
             value: Value::from_raw_parts(desc.value(), ctx),
             getter: Value::from_raw_parts(desc.getter(), ctx),
             setter: Value::from_raw_parts(desc.setter(), ctx),
         }
     }
 
+    /// Creates an empty descriptor.
+    #[inline]
+    pub fn new(ctx: Context<'q>) -> Self {
+        let undefined: Value = ctx.undefined().into();
+        PropertyDescriptor {
+            flags: PropFlags::empty(),
+            value: undefined.clone(),
+            getter: undefined.clone(),
+            setter: undefined,
+        }
+    }
+
+    #[inline]
+    pub fn with_value<V: Into<Value<'q>>>(mut self, value: V) -> Self {
+        self.value = value.into();
+        self.flags |= PropFlags::HAS_VALUE;
+        self
+    }
+
+    #[inline]
+    pub fn with_getter<V: Into<Value<'q>>>(mut self, getter: V) -> Self {
+        self.getter = getter.into();
+        self.flags |= PropFlags::HAS_GET;
+        self
+    }
+
+    #[inline]
+    pub fn with_setter<V: Into<Value<'q>>>(mut self, setter: V) -> Self {
+        self.setter = setter.into();
+        self.flags |= PropFlags::HAS_SET;
+        self
+    }
+
+    #[inline]
+    pub fn with_writable(self, writable: bool) -> Self {
+        self.with_attribute(PropFlags::HAS_WRITABLE, PropFlags::WRITABLE, writable)
+    }
+
+    #[inline]
+    pub fn with_enumerable(self, enumerable: bool) -> Self {
+        self.with_attribute(PropFlags::HAS_ENUMERABLE, PropFlags::ENUMERABLE, enumerable)
+    }
+
+    #[inline]
+    pub fn with_configurable(self, configurable: bool) -> Self {
+        self.with_attribute(PropFlags::HAS_CONFIGURABLE, PropFlags::CONFIGURABLE, configurable)
+    }
+
+    fn with_attribute(mut self, has: PropFlags, flag: PropFlags, b: bool) -> Self {
+        self.flags |= has;
+        self.flags.set(flag, b);
+        self
+    }
+
+    fn attribute(&self, has: PropFlags, flag: PropFlags) -> Option<bool> {
+        self.flags.contains(has).then(|| self.flags.contains(flag))
+    }
+
+    /// Returns the flags for `Value::define_property`, including `GETSET` for an accessor descriptor.
     #[inline]
     pub fn flags(&self) -> PropFlags {
-        self.flags
+        let mut flags = self.flags;
+        flags.set(PropFlags::GETSET, self.is_accessor());
+        flags
     }
 
+    /// Returns the value, which is `undefined` if it is absent.
     #[inline]
     pub fn value(&self) -> &Value<'q> {
         &self.value
     }
 
+    /// Returns the getter, which is `undefined` if it is absent.
     #[inline]
     pub fn getter(&self) -> &Value<'q> {
         &self.getter
     }
 
+    /// Returns the setter, which is `undefined` if it is absent.
     #[inline]
     pub fn setter(&self) -> &Value<'q> {
         &self.setter
     }
+
+    #[inline]
+    pub fn value_opt(&self) -> Option<&Value<'q>> {
+        self.flags.contains(PropFlags::HAS_VALUE).then_some(&self.value)
+    }
+
+    #[inline]
+    pub fn getter_opt(&self) -> Option<&Value<'q>> {
+        self.flags.contains(PropFlags::HAS_GET).then_some(&self.getter)
+    }
+
+    #[inline]
+    pub fn setter_opt(&self) -> Option<&Value<'q>> {
+        self.flags.contains(PropFlags::HAS_SET).then_some(&self.setter)
+    }
+
+    #[inline]
+    pub fn writable(&self) -> Option<bool> {
+        self.attribute(PropFlags::HAS_WRITABLE, PropFlags::WRITABLE)
+    }
+
+    #[inline]
+    pub fn enumerable(&self) -> Option<bool> {
+        self.attribute(PropFlags::HAS_ENUMERABLE, PropFlags::ENUMERABLE)
+    }
+
+    #[inline]
+    pub fn configurable(&self) -> Option<bool> {
+        self.attribute(PropFlags::HAS_CONFIGURABLE, PropFlags::CONFIGURABLE)
+    }
+
+    #[inline]
+    pub fn is_accessor(&self) -> bool {
+        self.flags.intersects(PropFlags::HAS_GET | PropFlags::HAS_SET)
+    }
+
+    #[inline]
+    pub fn is_data(&self) -> bool {
+        self.flags.intersects(PropFlags::HAS_VALUE | PropFlags::HAS_WRITABLE)
+    }
 }
 
 pub struct ValueIterator<'q> {
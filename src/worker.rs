@@ -0,0 +1,518 @@
+use crate::{
+    class::{Class, ClassProperties},
+    clone::{deserialize, serialize, Serialized},
+    context::Context,
+    error::{Error, ErrorKind},
+    flags::{EvalFlags, PropFlags},
+    result::Result,
+    runtime::{Runtime, RuntimeScope},
+    types::Object,
+    value::Value,
+};
+use quijine_core as qc;
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    mem::{forget, transmute},
+    rc::Rc,
+    result::Result as StdResult,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+type Loader = dyn Fn(&str) -> Result<String> + Send + Sync;
+type InitContext = dyn for<'q> Fn(Context<'q>) -> Result<()> + Send + Sync;
+
+/// `WorkerOptions` configures the `Worker` class installed by `Context::install_worker`.
+#[derive(Clone)]
+pub struct WorkerOptions {
+    max_workers: usize,
+    terminate_timeout: Duration,
+    loader: Arc<Loader>,
+    init_context: Option<Arc<InitContext>>,
+}
+
+impl WorkerOptions {
+    /// `loader` returns the source of the module given to `new Worker(moduleName)`.
+    pub fn new<L>(loader: L) -> Self
+    where
+        L: Fn(&str) -> Result<String> + Send + Sync + 'static,
+    {
+        WorkerOptions {
+            max_workers: 4,
+            terminate_timeout: Duration::from_millis(500),
+            loader: Arc::new(loader),
+            init_context: None,
+        }
+    }
+
+    /// Sets the number of workers which can run at the same time in a runtime. Defaults to 4.
+    #[inline]
+    pub fn max_workers(mut self, max_workers: usize) -> Self {
+        self.max_workers = max_workers;
+        self
+    }
+
+    /// Sets how long dropping a runtime waits for each worker to exit after interrupting it. Defaults to 500 ms.
+    /// A worker which does not exit in time (e.g. blocked in `Atomics.wait` or the loader) is detached.
+    #[inline]
+    pub fn terminate_timeout(mut self, timeout: Duration) -> Self {
+        self.terminate_timeout = timeout;
+        self
+    }
+
+    /// Sets a function which initializes the context of every worker (e.g. host functions).
+    #[inline]
+    pub fn init_context<F>(mut self, f: F) -> Self
+    where
+        F: for<'q> Fn(Context<'q>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.init_context = Some(Arc::new(f));
+        self
+    }
+}
+
+impl fmt::Debug for WorkerOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> StdResult<(), fmt::Error> {
+        f.debug_struct("WorkerOptions")
+            .field("max_workers", &self.max_workers)
+            .field("terminate_timeout", &self.terminate_timeout)
+            .finish()
+    }
+}
+
+enum ToWorker {
+    Message(Serialized),
+    Terminate,
+}
+
+enum Event {
+    Message(Serialized),
+    Error(String),
+    Exit,
+}
+
+struct Shared {
+    terminated: AtomicBool,
+}
+
+impl Shared {
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        self.terminated.load(Ordering::SeqCst)
+    }
+}
+
+/// `Entry` keeps a running worker and its `Worker` object alive.
+struct Entry {
+    object: qc::Value<'static>,
+    // the id of the context owning `object`
+    context: u64,
+    runtime: qc::Runtime<'static>,
+    sender: Sender<ToWorker>,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+    // disconnected when the thread exits
+    exited: Receiver<()>,
+    terminate_timeout: Duration,
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        // the interrupt handler of the worker aborts running JavaScript code
+        self.shared.terminated.store(true, Ordering::SeqCst);
+        let _ = self.sender.send(ToWorker::Terminate);
+        if let Some(thread) = self.thread.take() {
+            match self.exited.recv_timeout(self.terminate_timeout) {
+                Err(RecvTimeoutError::Disconnected) => {
+                    let _ = thread.join();
+                }
+                _ => log::warn!(
+                    "detached {} which did not exit in {:?}",
+                    thread.thread().name().unwrap_or("a worker"),
+                    self.terminate_timeout
+                ),
+            }
+        }
+        unsafe { self.runtime.free_value(self.object) }
+    }
+}
+
+/// `ExitGuard` reports the exit of a worker thread when it is dropped.
+struct ExitGuard {
+    id: u64,
+    sender: Sender<(u64, Event)>,
+}
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        let _ = self.sender.send((self.id, Event::Exit));
+    }
+}
+
+/// `WorkerRegistry` tracks the workers spawned by a runtime. Dropping it terminates them.
+pub(crate) struct WorkerRegistry {
+    options: WorkerOptions,
+    next_id: u64,
+    entries: BTreeMap<u64, Entry>,
+    inbox: Receiver<(u64, Event)>,
+    inbox_sender: Sender<(u64, Event)>,
+    pending: VecDeque<(u64, Event)>,
+}
+
+impl WorkerRegistry {
+    fn new(options: WorkerOptions) -> Self {
+        let (inbox_sender, inbox) = channel();
+        WorkerRegistry {
+            options,
+            next_id: 0,
+            entries: BTreeMap::new(),
+            inbox,
+            inbox_sender,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// `Worker` is the parent-side handle of a worker.
+struct Worker {
+    sender: Sender<ToWorker>,
+    shared: Arc<Shared>,
+}
+
+impl Class for Worker {
+    fn name() -> &'static str {
+        "Worker"
+    }
+
+    fn define_properties<'q, P: ClassProperties<'q, Self>>(properties: &mut P) -> Result<()> {
+        properties.define_method(
+            "postMessage",
+            |v, ctx, _this, (message,): (Value,)| {
+                let sab = ctx.runtime().is_shared_array_buffer_enabled();
+                let message = serialize(&message, sab)?;
+                // a terminated worker ignores messages
+                let _ = v.sender.send(ToWorker::Message(message));
+                Ok(())
+            },
+            1,
+        )?;
+        properties.define_method(
+            "terminate",
+            |v, _ctx, _this, _args: ()| {
+                v.shared.terminated.store(true, Ordering::SeqCst);
+                let _ = v.sender.send(ToWorker::Terminate);
+                Ok(())
+            },
+            0,
+        )?;
+        Ok(())
+    }
+}
+
+pub(crate) fn install<'q>(mut ctx: Context<'q>, options: WorkerOptions) -> Result<Object<'q>> {
+    let mut rt = ctx.runtime();
+    match rt.worker_registry_mut() {
+        Some(registry) => registry.options = options,
+        None => rt.set_worker_registry(WorkerRegistry::new(options)),
+    }
+    ctx.register_class::<Worker>()?;
+    let ctor = ctx.new_function_with_info(
+        |ctx, info| {
+            if !info.is_construct_call() {
+                return Err(Error::with_str(ErrorKind::TypeError, "Worker must be called with new"));
+            }
+            let module_name = match info.args().first() {
+                Some(v) => v.to_string()?,
+                None => return Err(Error::with_str(ErrorKind::TypeError, "module name is required")),
+            };
+            spawn(ctx, module_name)
+        },
+        Worker::name(),
+        1,
+    )?;
+    ctor.set_constructor_bit(true)?;
+    ctor.set_constructor(ctx.class_proto::<Worker>()?)?;
+    ctx.global_object()?.define_property_value_from(
+        Worker::name(),
+        ctor.clone(),
+        PropFlags::WRITABLE | PropFlags::CONFIGURABLE,
+    )?;
+    Ok(ctor)
+}
+
+fn spawn<'q>(ctx: Context<'q>, module_name: String) -> Result<Value<'q>> {
+    let mut rt = ctx.runtime();
+    let sab = rt.is_shared_array_buffer_enabled();
+    let (options, inbox_sender, id) = {
+        let registry = rt.worker_registry_mut().ok_or_else(not_installed)?;
+        if registry.entries.len() >= registry.options.max_workers {
+            return Err(Error::with_str(ErrorKind::RangeError, "too many workers"));
+        }
+        registry.next_id += 1;
+        (
+            registry.options.clone(),
+            registry.inbox_sender.clone(),
+            registry.next_id,
+        )
+    };
+    let (sender, receiver) = channel();
+    let shared = Arc::new(Shared {
+        terminated: AtomicBool::new(false),
+    });
+    let obj = ctx.new_object_with_opaque(Worker {
+        sender: sender.clone(),
+        shared: shared.clone(),
+    })?;
+    let thread_shared = shared.clone();
+    let (exited_sender, exited) = channel::<()>();
+    let terminate_timeout = options.terminate_timeout;
+    let thread = thread::Builder::new()
+        .name(format!("quijine-worker-{}", id))
+        .spawn(move || {
+            let _exited = exited_sender;
+            // removes the entry even if the thread panics
+            let exit = ExitGuard {
+                id,
+                sender: inbox_sender,
+            };
+            run_worker(&module_name, &options, sab, &receiver, &thread_shared, |event| {
+                let _ = exit.sender.send((id, event));
+            })
+        })
+        .map_err(|e| Error::with_external(ErrorKind::InternalError, e))?;
+    let value: Value<'q> = obj.clone().into();
+    let object = *value.as_raw();
+    forget(value);
+    let entry = unsafe {
+        Entry {
+            object: transmute::<qc::Value<'q>, qc::Value<'static>>(object),
+            context: ctx.id(),
+            runtime: transmute::<qc::Runtime<'q>, qc::Runtime<'static>>(rt.into()),
+            sender,
+            shared,
+            thread: Some(thread),
+            exited,
+            terminate_timeout,
+        }
+    };
+    let registry = rt.worker_registry_mut().ok_or_else(not_installed)?;
+    registry.entries.insert(id, entry);
+    Ok(obj.into())
+}
+
+fn run_worker<F: Fn(Event)>(
+    module_name: &str,
+    options: &WorkerOptions,
+    sab: bool,
+    receiver: &Receiver<ToWorker>,
+    shared: &Arc<Shared>,
+    send: F,
+) {
+    let report = |e: Error| {
+        // an error caused by the termination is not reported
+        if !shared.is_terminated() {
+            send(Event::Error(e.to_string()));
+        }
+    };
+    {
+        let rts = RuntimeScope::new();
+        let rt = rts.get();
+        if sab {
+            rt.enable_shared_array_buffer().expect("no context is created yet");
+        }
+        rt.set_can_block(true);
+        let interrupt_shared = shared.clone();
+        rt.set_interrupt_handler(move || interrupt_shared.is_terminated());
+        let ctxs = rts.new_context_scope();
+        let ctx = ctxs.get();
+        let closed = Rc::new(Cell::new(false));
+        let init = init_worker_context(ctx, options, sab, closed.clone(), &send).and_then(|_| {
+            let source = (options.loader)(module_name)?;
+            ctx.eval(&source, module_name, EvalFlags::TYPE_MODULE)?;
+            rt.run_pending_jobs()
+        });
+        match init {
+            Ok(()) => {
+                while !closed.get() && !shared.is_terminated() {
+                    match receiver.recv() {
+                        Ok(ToWorker::Message(message)) => {
+                            if let Err(e) = ctx
+                                .global_object()
+                                .and_then(|global| deliver(global.into(), &message))
+                                .and_then(|_| rt.run_pending_jobs())
+                            {
+                                report(e);
+                            }
+                        }
+                        Ok(ToWorker::Terminate) | Err(_) => break,
+                    }
+                }
+            }
+            Err(e) => report(e),
+        }
+        // workers spawned by this worker are terminated here
+    }
+}
+
+fn init_worker_context<'q, F: Fn(Event)>(
+    ctx: Context<'q>,
+    options: &WorkerOptions,
+    sab: bool,
+    closed: Rc<Cell<bool>>,
+    send: &'q F,
+) -> Result<()> {
+    let global = ctx.global_object()?;
+    global.set("self", global.clone())?;
+    // module code is strict, so `onmessage = ...` requires the property
+    global.set("onmessage", ctx.null())?;
+    let post_message = ctx.new_function_from(
+        move |_ctx, _this: Value, (message,): (Value,)| {
+            send(Event::Message(serialize(&message, sab)?));
+            Ok(())
+        },
+        "postMessage",
+        1,
+    )?;
+    global.set("postMessage", post_message)?;
+    let close = ctx.new_function_from(
+        move |_ctx, _this: Value, _args: ()| {
+            closed.set(true);
+            Ok(())
+        },
+        "close",
+        0,
+    )?;
+    global.set("close", close)?;
+    if let Some(init_context) = &options.init_context {
+        init_context(ctx)?;
+    }
+    Ok(())
+}
+
+/// Calls `target.onmessage({ data })`.
+fn deliver<'q>(target: Value<'q>, message: &Serialized) -> Result<()> {
+    let ctx = target.context();
+    let handler: Value = target.get("onmessage")?;
+    if !handler.is_function() {
+        return Ok(());
+    }
+    let event = ctx.new_object()?;
+    event.set("data", deserialize(ctx, message)?)?;
+    ctx.call(handler, target, &[event.into()])?;
+    Ok(())
+}
+
+fn dispatch_event<'q>(ctx: Context<'q>, target: Value<'q>, event: Event) -> Result<()> {
+    match event {
+        Event::Message(message) => deliver(target, &message),
+        Event::Error(message) => {
+            let handler: Value = target.get("onerror")?;
+            if !handler.is_function() {
+                log::warn!("uncaught error in a worker: {}", message);
+                return Ok(());
+            }
+            let event = ctx.new_object()?;
+            event.set("message", message)?;
+            ctx.call(handler, target, &[event.into()])?;
+            Ok(())
+        }
+        Event::Exit => Ok(()),
+    }
+}
+
+/// Dispatches the events of workers whose `Worker` objects belong to `ctx`.
+/// If `timeout` is given, it waits for an event while no event is queued.
+pub(crate) fn dispatch(ctx: Context, timeout: Option<Duration>) -> Result<usize> {
+    let mut rt = ctx.runtime();
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut count = 0;
+    loop {
+        let (id, event, object) = {
+            let registry = match rt.worker_registry_mut() {
+                Some(registry) => registry,
+                None => return Ok(count),
+            };
+            while let Ok(event) = registry.inbox.try_recv() {
+                registry.pending.push_back(event);
+            }
+            let index = registry
+                .pending
+                .iter()
+                .position(|(id, _)| registry.entries.get(id).map_or(true, |entry| entry.context == ctx.id()));
+            let (id, event) = match index {
+                Some(i) => registry.pending.remove(i).unwrap(),
+                None => {
+                    let waiting = registry.entries.values().any(|entry| entry.context == ctx.id());
+                    let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                    if count > 0 || !waiting || remaining.is_none() || remaining == Some(Duration::ZERO) {
+                        return Ok(count);
+                    }
+                    match registry.inbox.recv_timeout(remaining.unwrap()) {
+                        Ok(event) => registry.pending.push_back(event),
+                        Err(RecvTimeoutError::Timeout) => return Ok(count),
+                        Err(RecvTimeoutError::Disconnected) => unreachable!("the registry has a sender"),
+                    }
+                    continue;
+                }
+            };
+            let object = match registry.entries.get(&id) {
+                Some(entry) => {
+                    ctx.as_raw().dup_value(entry.object);
+                    Value::from_raw_parts(entry.object, ctx.as_raw())
+                }
+                // the worker has already exited
+                None => continue,
+            };
+            (id, event, object)
+        };
+        count += 1;
+        if let Event::Exit = event {
+            // the registry must not be borrowed while the entry joins the thread
+            let entry = rt
+                .worker_registry_mut()
+                .and_then(|registry| registry.entries.remove(&id));
+            drop(entry);
+        }
+        dispatch_event(ctx, object, event)?;
+    }
+}
+
+/// Returns the number of running workers whose `Worker` objects belong to `ctx`.
+pub(crate) fn count(ctx: Context) -> usize {
+    ctx.runtime().worker_registry().map_or(0, |registry| {
+        registry
+            .entries
+            .values()
+            .filter(|entry| entry.context == ctx.id())
+            .count()
+    })
+}
+
+#[inline]
+fn not_installed() -> Error {
+    Error::with_str(ErrorKind::InternalError, "Worker is not installed")
+}
+
+impl<'r> Runtime<'r> {
+    #[inline]
+    fn worker_registry(&self) -> Option<&WorkerRegistry> {
+        self.opaque().worker_registry.as_ref()
+    }
+
+    #[inline]
+    fn worker_registry_mut(&mut self) -> Option<&mut WorkerRegistry> {
+        self.opaque_mut().worker_registry.as_mut()
+    }
+
+    #[inline]
+    fn set_worker_registry(&mut self, registry: WorkerRegistry) {
+        self.opaque_mut().worker_registry = Some(registry);
+    }
+}
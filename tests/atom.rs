@@ -0,0 +1,23 @@
+use quijine::{EvalFlags, GpnFlags, Result, Value};
+
+#[test]
+fn property_enum_atom() -> Result<()> {
+    quijine::context(|ctx| {
+        let obj: Value = ctx.eval(
+            "({ uniqueKeyOfPropertyEnumAtom: 1 })",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        for _ in 0..3 {
+            let props = obj.own_property_names(GpnFlags::STRING_MASK)?;
+            assert_eq!(1, props.len());
+            // each atom is owned by its own handle
+            for _ in 0..3 {
+                let atom = props[0].atom();
+                assert_eq!("uniqueKeyOfPropertyEnumAtom", atom.to_value()?.to_string()?);
+            }
+        }
+        assert_eq!(1, obj.get::<_, i32>("uniqueKeyOfPropertyEnumAtom")?);
+        Ok(())
+    })
+}
@@ -1,6 +1,8 @@
+#![allow(clippy::arc_with_non_send_sync)]
+
 use std::{cell::RefCell, sync::Arc};
 
-use quijine::{Class, ClassProperties, Context, EvalFlags, Result, Value};
+use quijine::{Class, ClassObject, ClassProperties, Context, EvalFlags, Result, Value};
 
 #[derive(Clone, Debug, Default)]
 struct S1 {
@@ -190,3 +192,142 @@ fn new_object_class_arc() -> Result<()> {
     assert_eq!((1, -1), s2.pos);
     Ok(())
 }
+
+#[test]
+fn prototype_chain() -> Result<()> {
+    quijine::context(|ctx| {
+        let ctor: Value = ctx.new_global_constructor::<S1>()?.into();
+        let sub = ctx.eval(
+            r#"
+            class Sub extends S1 {
+                greet() { return "hello " + this.name; }
+            }
+            new Sub("foo")
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        // instances of subclasses hold the opaque with the prototype of the subclass
+        assert_eq!(
+            "hello foo",
+            ctx.eval_into::<String>("new Sub('foo').greet()", "<input>", EvalFlags::TYPE_GLOBAL)?
+        );
+        let sub_ctor: Value = ctx.eval("Sub", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(sub.instance_of(&sub_ctor)? && sub.instance_of(&ctor)?);
+        assert_eq!(
+            "foo",
+            ClassObject::<S1>::try_from_instance(sub.clone())?
+                .opaque::<S1>()
+                .unwrap()
+                .name
+        );
+        // an object inheriting from an instance
+        let proto: Value = ctx
+            .new_object_with_opaque(S1 {
+                name: "proto".to_owned(),
+                pos: (0, 0),
+            })?
+            .into();
+        let derived: Value = ctx.new_object_with_proto(&proto)?.into();
+        assert!(derived.instance_of(&ctor)?);
+        assert!(derived.opaque::<S1>().is_none());
+        assert_eq!(
+            "proto",
+            ClassObject::<S1>::try_from_instance(derived.clone())?
+                .opaque::<S1>()
+                .unwrap()
+                .name
+        );
+        // set_prototype
+        let plain: Value = ctx.new_object()?.into();
+        assert!(!plain.instance_of(&ctor)?);
+        assert!(ClassObject::<S1>::try_from_instance(plain.clone()).is_err());
+        plain.set_prototype(&derived)?;
+        assert!(plain.instance_of(&ctor)?);
+        let global = ctx.global_object()?;
+        global.set("plain", plain.clone())?;
+        global.set("derived", derived.clone())?;
+        assert!(ctx.eval_into::<bool>(
+            "Object.getPrototypeOf(plain) === derived",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL
+        )?);
+        plain.prevent_extensions()?;
+        let e = plain.set_prototype(&ctx.null().into()).unwrap_err();
+        assert!(e.to_string().contains("not extensible"), "{}", e);
+        // cycles are rejected
+        assert!(proto.set_prototype(&plain).is_err());
+        // `instanceof` with a non-callable throws
+        assert!(plain.instance_of(&proto).is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn constructor_requires_new() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.new_global_constructor::<S1>()?;
+        let e = ctx.eval("S1('foo')", "<input>", EvalFlags::TYPE_GLOBAL).unwrap_err();
+        assert!(e.to_string().contains("TypeError"), "{}", e);
+        assert!(e.to_string().contains("must be called with new"), "{}", e);
+        let ok: bool = ctx.eval_into("new S1('foo') instanceof S1", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(ok);
+        // missing arguments up to `constructor_length` are `undefined`
+        let name: String = ctx.eval_into("new S1().name", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!("undefined", name);
+        Ok(())
+    })
+}
+
+#[test]
+fn function_metadata() -> Result<()> {
+    quijine::context(|ctx| {
+        let global = ctx.global_object()?;
+        ctx.new_global_constructor::<S1>()?;
+        let plain = ctx.new_function(|ctx, _this, _args| Ok(ctx.undefined().into()), "plain", 3)?;
+        global.set("plain", plain)?;
+        let typed = ctx.new_function_auto(
+            |_ctx, _this: Value, (a, _b): (i32, Option<i32>)| -> Result<i32> {
+                if a < 0 {
+                    return Err(quijine::Error::with_str(quijine::ErrorKind::RangeError, "negative"));
+                }
+                Ok(a)
+            },
+            "typed",
+        )?;
+        global.set("typed", typed)?;
+        let describe: String = ctx.eval_into(
+            r#"
+            const d = (f) => `${f.name}/${f.length}`;
+            const name = Object.getOwnPropertyDescriptor(S1.prototype, "name");
+            const pos = Object.getOwnPropertyDescriptor(S1.prototype, "pos");
+            [d(plain), d(typed), d(S1), d(S1.prototype.move), d(name.get), d(name.set), d(pos.get)].join(" ")
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("plain/3 typed/1 S1/1 move/2 get name/0 set name/1 get pos/0", describe);
+        // the name is not writable but configurable like JS functions
+        let configurable: bool = ctx.eval_into(
+            r#"
+            const desc = Object.getOwnPropertyDescriptor(typed, "name");
+            !desc.writable && !desc.enumerable && desc.configurable
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(configurable);
+        let stack: String = ctx.eval_into(
+            "try { typed(-1) } catch (e) { e.stack }",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(stack.contains("typed"), "{}", stack);
+        let inspected = ctx
+            .global_object()?
+            .get::<_, Value>("typed")?
+            .inspect(&Default::default())?;
+        assert_eq!("[Function: typed]", inspected);
+        Ok(())
+    })
+}
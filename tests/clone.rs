@@ -0,0 +1,106 @@
+use quijine::{EvalFlags, Object, Result, Value};
+
+fn eval<'q>(ctx: quijine::Context<'q>, code: &str) -> Result<Value<'q>> {
+    ctx.eval(code, "<input>", EvalFlags::TYPE_GLOBAL)
+}
+
+#[test]
+fn same_runtime() -> Result<()> {
+    quijine::run(|rt| {
+        let ctxs1 = rt.new_context_scope();
+        let ctxs2 = rt.new_context_scope();
+        let (ctx1, ctx2) = (ctxs1.get(), ctxs2.get());
+        let src = eval(
+            ctx1,
+            "const shared = { n: 1 }; const o = { a: [1, 'two', shared, , ], b: shared, c: null }; o.self = o; o",
+        )?;
+        let dst = src.structured_clone_to(ctx2)?;
+        ctx2.global_object()?.set("o", dst)?;
+        let checks: Vec<bool> = ctx2.eval_into(
+            r#"[
+                o.self === o,
+                o.a[2] === o.b,
+                o.a.length === 4,
+                o.a[1] === 'two',
+                o instanceof Object,
+                o.a instanceof Array,
+            ]"#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(checks.iter().all(|b| *b), "{:?}", checks);
+        // the clone is independent of the original
+        eval(ctx2, "o.b.n = 2")?;
+        let n: i32 = eval(ctx1, "shared.n")?.try_into()?;
+        assert_eq!(1, n);
+        Ok(())
+    })
+}
+
+#[test]
+fn same_runtime_fallback() -> Result<()> {
+    quijine::run(|rt| {
+        let ctxs1 = rt.new_context_scope();
+        let ctxs2 = rt.new_context_scope();
+        let (ctx1, ctx2) = (ctxs1.get(), ctxs2.get());
+        let src = eval(ctx1, "({ d: new Date(0), u: new Uint8Array([1, 2, 3]) })")?;
+        let dst: Object = src.structured_clone_to(ctx2)?.try_into()?;
+        ctx2.global_object()?.set("o", dst)?;
+        let ok: bool = eval(ctx2, "o.d instanceof Date && o.d.getTime() === 0 && o.u[2] === 3")?.try_into()?;
+        assert!(ok);
+        Ok(())
+    })
+}
+
+#[test]
+fn other_runtime() -> Result<()> {
+    quijine::context(|ctx1| {
+        quijine::context(|ctx2| {
+            let src = eval(ctx1, "const o = { s: 'x', n: [1.5, 2n], d: new Date(0) }; o.o = o; o")?;
+            let dst = src.structured_clone_to(ctx2)?;
+            ctx2.global_object()?.set("o", dst)?;
+            let ok: bool = eval(
+                ctx2,
+                "o.o === o && o.n[0] === 1.5 && o.n[1] === 2n && o.d instanceof Date",
+            )?
+            .try_into()?;
+            assert!(ok);
+            Ok(())
+        })
+    })
+}
+
+#[test]
+fn not_cloneable() -> Result<()> {
+    quijine::run(|rt| {
+        let ctxs1 = rt.new_context_scope();
+        let ctxs2 = rt.new_context_scope();
+        let (ctx1, ctx2) = (ctxs1.get(), ctxs2.get());
+        let ctxs3 = quijine::RuntimeScope::new();
+        let ctxs3 = ctxs3.new_context_scope();
+        let ctx3 = ctxs3.get();
+        let cases = [
+            (
+                "({ a: { f: [0, () => 1] } })",
+                "function at value.a.f[1] could not be cloned",
+            ),
+            (
+                "({ 'a b': Symbol('s') })",
+                "symbol at value[\"a b\"] could not be cloned",
+            ),
+            (
+                "({ get x() { return 1; } })",
+                "accessor property at value.x could not be cloned",
+            ),
+            ("({ m: new Map() })", "Map at value.m could not be cloned"),
+        ];
+        for (code, message) in cases.iter() {
+            let v = eval(ctx1, code)?;
+            for ctx in [ctx2, ctx3] {
+                let e = v.structured_clone_to(ctx).unwrap_err();
+                assert!(e.to_string().contains(message), "{}: {}", code, e);
+            }
+        }
+        Ok(())
+    })
+}
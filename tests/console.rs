@@ -0,0 +1,223 @@
+#![cfg(feature = "console")]
+
+use quijine::{ConsoleLevel, ConsoleSink, Context, EvalFlags, LogSink, Result};
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Clone, Default)]
+struct Collector(Rc<RefCell<Vec<(ConsoleLevel, String)>>>);
+
+impl ConsoleSink for Collector {
+    fn write(&self, level: ConsoleLevel, message: &str) {
+        self.0.borrow_mut().push((level, message.to_owned()));
+    }
+}
+
+impl Collector {
+    fn take(&self) -> Vec<(ConsoleLevel, String)> {
+        self.0.borrow_mut().drain(..).collect()
+    }
+
+    fn messages(&self) -> Vec<String> {
+        self.take().into_iter().map(|(_, m)| m).collect()
+    }
+}
+
+fn run(ctx: Context, code: &str) -> Result<()> {
+    ctx.eval(code, "<input>", EvalFlags::TYPE_GLOBAL)?;
+    Ok(())
+}
+
+#[test]
+fn levels() -> Result<()> {
+    quijine::context(|ctx| {
+        let sink = Collector::default();
+        ctx.install_console(sink.clone())?;
+        run(
+            ctx,
+            "console.log('a'); console.info('b'); console.debug('c'); console.warn('d'); console.error('e');",
+        )?;
+        assert_eq!(
+            vec![
+                (ConsoleLevel::Info, "a".to_owned()),
+                (ConsoleLevel::Info, "b".to_owned()),
+                (ConsoleLevel::Debug, "c".to_owned()),
+                (ConsoleLevel::Warn, "d".to_owned()),
+                (ConsoleLevel::Error, "e".to_owned()),
+            ],
+            sink.take()
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn format() -> Result<()> {
+    quijine::context(|ctx| {
+        let sink = Collector::default();
+        ctx.install_console(sink.clone())?;
+        run(
+            ctx,
+            r#"
+            console.log("%s is %d years old", "Bob", 42.5, "extra");
+            console.log("%i %f %j %% %c|", "42.9px", "1.5e1", { a: [1] }, "color: red");
+            console.log("%o", "str");
+            console.log("no args %s");
+            console.log(1, "two", [3], { four: 4 }, null, undefined, true);
+            console.log(-0, 10n, Symbol("sym"), "it's");
+            console.log();
+            "#,
+        )?;
+        assert_eq!(
+            vec![
+                "Bob is 42.5 years old extra",
+                "42 15 {\"a\":[1]} % |",
+                "'str'",
+                "no args %s",
+                "1 two [ 3 ] { four: 4 } null undefined true",
+                "-0 10n Symbol(sym) it's",
+                "",
+            ],
+            sink.messages()
+        );
+        // replaced globals are not used
+        run(
+            ctx,
+            r#"
+            Number = parseInt = parseFloat = undefined;
+            console.log("%d %i %f", "42.5", "42.9px", "1.5e1");
+            "#,
+        )?;
+        assert_eq!(vec!["42.5 42 15"], sink.messages());
+        Ok(())
+    })
+}
+
+#[test]
+fn inspect() -> Result<()> {
+    quijine::context(|ctx| {
+        let sink = Collector::default();
+        ctx.install_console(sink.clone())?;
+        run(
+            ctx,
+            r#"
+            console.log({ a: { b: { c: { d: 1 } } }, "e-f": 'x' });
+            console.log([function foo() {}, () => {}, [], {}]);
+            class Point { constructor() { this.x = 1; } }
+            console.log(new Point(), Object.create(null));
+            const o = { name: "o" }; o.self = o;
+            console.log(o);
+            console.log({ get g() { return 1; }, set s(v) {} });
+            console.log(new Date(0), /re/g);
+            console.log({ long: "x".repeat(40), longer: "y".repeat(40) });
+            "#,
+        )?;
+        assert_eq!(
+            vec![
+                "{ a: { b: { c: [Object] } }, 'e-f': 'x' }",
+                "[ [Function: foo], [Function (anonymous)], [], {} ]",
+                "Point { x: 1 } [Object: null prototype] {}",
+                "<ref *1> { name: 'o', self: [Circular *1] }",
+                "{ g: [Getter], s: [Setter] }",
+                "1970-01-01T00:00:00.000Z /re/g",
+                &format!("{{\n  long: '{}',\n  longer: '{}'\n}}", "x".repeat(40), "y".repeat(40)),
+            ],
+            sink.messages()
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn errors() -> Result<()> {
+    quijine::context(|ctx| {
+        let sink = Collector::default();
+        ctx.install_console(sink.clone())?;
+        run(
+            ctx,
+            r#"
+            function thrower() { throw new TypeError("bad"); }
+            try { thrower(); } catch (e) { console.error(e); }
+            function tracer() { console.trace("here %d", 1); }
+            tracer();
+            "#,
+        )?;
+        let messages = sink.take();
+        assert_eq!(ConsoleLevel::Error, messages[0].0);
+        assert!(messages[0].1.starts_with("TypeError: bad\n"), "{}", messages[0].1);
+        assert!(messages[0].1.contains("at thrower"), "{}", messages[0].1);
+        assert_eq!(ConsoleLevel::Error, messages[1].0);
+        assert!(messages[1].1.starts_with("Trace: here 1\n"), "{}", messages[1].1);
+        assert!(messages[1].1.contains("\n    at tracer"), "{}", messages[1].1);
+        assert!(!messages[1].1.contains("(native)"), "{}", messages[1].1);
+        Ok(())
+    })
+}
+
+#[test]
+fn count_and_time() -> Result<()> {
+    quijine::context(|ctx| {
+        let sink = Collector::default();
+        ctx.install_console(sink.clone())?;
+        run(
+            ctx,
+            r#"
+            console.count(); console.count(); console.count("x");
+            console.countReset(); console.count(); console.countReset("y");
+            console.time("t"); console.timeLog("t", "step", 1); console.timeEnd("t"); console.timeEnd("t");
+            "#,
+        )?;
+        let messages = sink.take();
+        let texts: Vec<_> = messages.iter().map(|(_, m)| m.as_str()).collect();
+        assert_eq!(
+            vec![
+                "default: 1",
+                "default: 2",
+                "x: 1",
+                "default: 1",
+                "Count for 'y' does not exist"
+            ],
+            texts[..5]
+        );
+        assert!(
+            texts[5].starts_with("t: ") && texts[5].ends_with("ms step 1"),
+            "{}",
+            texts[5]
+        );
+        assert!(texts[6].starts_with("t: ") && texts[6].ends_with("ms"), "{}", texts[6]);
+        assert_eq!(
+            (ConsoleLevel::Warn, "No such label 't' for console.timeEnd()"),
+            (messages[7].0, texts[7])
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn table() -> Result<()> {
+    quijine::context(|ctx| {
+        let sink = Collector::default();
+        ctx.install_console(sink.clone())?;
+        run(ctx, "console.table([{ a: 1, b: 'x' }, { a: 2 }, 3]);")?;
+        let expected = [
+            "┌─────────┬───┬─────┬────────┐",
+            "│ (index) │ a │  b  │ Values │",
+            "├─────────┼───┼─────┼────────┤",
+            "│    0    │ 1 │ 'x' │        │",
+            "│    1    │ 2 │     │        │",
+            "│    2    │   │     │   3    │",
+            "└─────────┴───┴─────┴────────┘",
+        ]
+        .join("\n");
+        assert_eq!(vec![expected], sink.messages());
+        Ok(())
+    })
+}
+
+#[test]
+fn log_sink() -> Result<()> {
+    let _ = env_logger::builder().is_test(true).try_init();
+    quijine::context(|ctx| {
+        ctx.install_console(LogSink)?;
+        run(ctx, "console.log('to the log crate', { a: 1 });")
+    })
+}
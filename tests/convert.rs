@@ -1,5 +1,5 @@
 use maplit::{btreemap, hashmap};
-use quijine::{EvalFlags, FromQj, Result};
+use quijine::{EvalFlags, FromQj, Function, Rest, Result, Value};
 use std::collections::{BTreeMap, HashMap};
 
 #[test]
@@ -125,3 +125,134 @@ fn js_to_rust_proto() -> Result<()> {
     })?;
     Ok(())
 }
+
+#[test]
+fn function_wrapper() -> Result<()> {
+    quijine::context(|ctx| {
+        let add: Function = ctx.eval_into(
+            "(function (a, b) { 'use strict'; return (this === undefined ? 0 : this.base) + a + b; })",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!(5, add.call::<_, i32>((2, 3))?);
+        let this = ctx.parse_json(r#"{"base": 10}"#, "<input>")?;
+        assert_eq!(15, add.call_with_this::<_, _, i32>(this, (2, 3))?);
+        let add = add.into_rust_fn::<(i32, i32), i32>();
+        assert_eq!(7, add((3, 4))?);
+
+        let point: Function = ctx.eval_into(
+            "(class Point { constructor(x, y) { this.x = x; this.y = y; } norm() { return Math.hypot(this.x, this.y); } })",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let p = point.construct((3, 4))?;
+        assert!(p.instance_of(&point)?);
+        let norm: Function = p.get("norm")?;
+        assert_eq!(5.0, norm.call_with_this::<_, _, f64>(p.clone(), ())?);
+        // a class constructor can't be called without `new`
+        assert!(point.call::<_, Value>((1, 2)).is_err());
+
+        let arrow: Function = ctx.eval_into("() => 1", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(arrow.construct(()).is_err());
+        assert!(ctx
+            .eval_into::<Function>("42", "<input>", EvalFlags::TYPE_GLOBAL)
+            .is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn optional_and_rest_args() -> Result<()> {
+    quijine::context(|ctx| {
+        let global = ctx.global_object()?;
+        let f = ctx.new_function_auto(
+            |_ctx, _this: Value, (a, b, rest): (i32, Option<i32>, Rest<i32>)| {
+                Ok(format!("{} {:?} {:?}", a, b, rest.into_inner()))
+            },
+            "f",
+        )?;
+        global.set("f", f)?;
+        let eval = |code: &str| ctx.eval_into::<String>(code, "<input>", EvalFlags::TYPE_GLOBAL);
+        assert_eq!("1", eval("String(f.length)")?);
+        assert_eq!("1 None []", eval("f(1)")?);
+        assert_eq!("1 None []", eval("f(1, undefined)")?);
+        assert_eq!("1 Some(2) [3, 4]", eval("f(1, 2, 3, 4)")?);
+        let e = ctx.eval("f()", "<input>", EvalFlags::TYPE_GLOBAL).unwrap_err();
+        assert!(e.to_string().contains("RangeError"), "{}", e);
+        // an explicit `length` overrides `A::LENGTH`
+        let g = ctx.new_function_from(|_ctx, _this: Value, (a, _b): (i32, Option<i32>)| Ok(a), "g", 2)?;
+        assert_eq!(2, g.get::<_, i32>("length")?);
+
+        let sum = ctx.new_function_auto(
+            |_ctx, _this: Value, args: (i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32)| {
+                let (a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, a10, a11) = args;
+                Ok(a0 + a1 + a2 + a3 + a4 + a5 + a6 + a7 + a8 + a9 + a10 + a11)
+            },
+            "sum",
+        )?;
+        assert_eq!(12, sum.get::<_, i32>("length")?);
+        let total: i32 = ctx.call_into(sum, (), (1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12))?;
+        assert_eq!(78, total);
+
+        let join: Function = ctx.eval_into("(...xs) => xs.join(',')", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!("1,a,true,2.5", join.call::<_, String>((1, "a", true, 2.5))?);
+        Ok(())
+    })
+}
+
+#[test]
+fn call_info() -> Result<()> {
+    quijine::context(|ctx| {
+        let describe = ctx.new_function_with_info(
+            |ctx, info| {
+                let target = if info.is_construct_call() {
+                    info.new_target().get::<_, String>("name")?
+                } else {
+                    "-".to_owned()
+                };
+                let this = if info.this().is_undefined() {
+                    "undefined".to_owned()
+                } else {
+                    info.this().get::<_, String>("tag")?
+                };
+                let s = format!(
+                    "{} {} {} {}",
+                    info.args().len(),
+                    this,
+                    target,
+                    info.arg(5).is_undefined()
+                );
+                if info.is_construct_call() {
+                    let obj = ctx.new_object()?;
+                    obj.set("s", s)?;
+                    Ok(obj.into())
+                } else {
+                    ctx.new_string(&s).map(|v| v.into())
+                }
+            },
+            "describe",
+            2,
+        )?;
+        describe.set_constructor_bit(true)?;
+        describe.set_constructor(ctx.new_object()?.into())?;
+        ctx.global_object()?.set("describe", describe)?;
+        let eval = |code: &str| ctx.eval_into::<String>(code, "<input>", EvalFlags::TYPE_GLOBAL);
+        assert_eq!("1 undefined - true", eval("describe(1)")?);
+        assert_eq!("0 obj - true", eval("describe.call({tag: 'obj'})")?);
+        assert_eq!("3 undefined describe true", eval("new describe(1, 2, 3).s")?);
+        assert_eq!(
+            "1 undefined Sub true",
+            eval("class Sub extends describe { constructor() { super(1); } }; new Sub().s")?
+        );
+        assert_eq!(
+            "describe 2 function",
+            eval("`${describe.name} ${describe.length} ${typeof describe}`")?
+        );
+        // the prototype does not depend on replaceable globals
+        eval("globalThis.proto = Function.prototype; Function = Array = undefined; ''")?;
+        let f = ctx.new_function_with_info(|ctx, _info| Ok(ctx.undefined().into()), "f", 0)?;
+        ctx.global_object()?.set("f", f)?;
+        assert_eq!("true", eval("String(Object.getPrototypeOf(f) === proto)")?);
+        Ok(())
+    })
+}
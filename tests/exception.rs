@@ -1,4 +1,4 @@
-use quijine::{PropFlags, Result};
+use quijine::{Error, ErrorCode, ErrorKind, ErrorValue, EvalFlags, JsStackFrame, PropFlags, Result};
 
 #[test]
 fn exception() -> Result<()> {
@@ -32,3 +32,334 @@ fn exception() -> Result<()> {
         Ok(())
     })
 }
+
+fn frame(function: &str, file: Option<&str>, line: Option<u32>) -> JsStackFrame {
+    JsStackFrame {
+        function: Some(function.to_owned()),
+        file: file.map(|f| f.to_owned()),
+        line,
+        column: None,
+    }
+}
+
+#[test]
+fn parse_stack_frames() {
+    let f = JsStackFrame::parse("    at foo (a/b.js:3:7)").unwrap();
+    assert_eq!(Some("foo"), f.function.as_deref());
+    assert_eq!(Some("a/b.js"), f.file.as_deref());
+    assert_eq!((Some(3), Some(7)), (f.line, f.column));
+    assert_eq!("at foo (a/b.js:3:7)", f.to_string());
+    assert_eq!(
+        frame("bar", None, None),
+        JsStackFrame::parse("at bar (native)").unwrap()
+    );
+    assert_eq!(
+        frame("<eval>", Some("x.js"), None),
+        JsStackFrame::parse("at <eval> (x.js)").unwrap()
+    );
+    assert_eq!(None, JsStackFrame::parse("Error: not a frame"));
+    let frames = JsStackFrame::parse_stack("    at f (c:\\x.js:1)\n    at <anonymous> (native)\n");
+    assert_eq!(
+        vec![frame("f", Some("c:\\x.js"), Some(1)), frame("<anonymous>", None, None)],
+        frames
+    );
+}
+
+#[test]
+fn error_frames() -> Result<()> {
+    quijine::context(|ctx| {
+        let e = ctx
+            .eval("function c() {\n  null.x;\n}\nc();", "z.js", EvalFlags::TYPE_GLOBAL)
+            .unwrap_err();
+        let data = match &e.value {
+            ErrorValue::JsError(data) => data,
+            v => panic!("{:?}", v),
+        };
+        assert_eq!(
+            vec![
+                frame("c", Some("z.js"), Some(2)),
+                frame("<eval>", Some("z.js"), Some(4))
+            ],
+            data.frames()
+        );
+        assert_eq!(
+            "TypeError: cannot read property 'x' of null\n    at c (z.js:2)\n    at <eval> (z.js:4)",
+            data.to_string()
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn rust_frames_in_js_stack() -> Result<()> {
+    quijine::context(|ctx| {
+        let global = ctx.global_object()?;
+        let fail = ctx.new_function(
+            |_ctx, _this, _args| Err(Error::with_str(ErrorKind::TypeError, "bad")),
+            "fail",
+            0,
+        )?;
+        global.set("fail", fail)?;
+        let nested = ctx.new_function(
+            |ctx, _this, _args| {
+                ctx.eval(
+                    "function inner() {\n  throw new RangeError('deep');\n}\n\
+                     try { inner(); } catch (e) { globalThis.thrown = e; globalThis.original = e.stack; throw e; }",
+                    "inner.js",
+                    EvalFlags::TYPE_GLOBAL,
+                )
+            },
+            "nested",
+            0,
+        )?;
+        global.set("nested", nested)?;
+        let stack: String = ctx.eval_into(
+            "function a() { fail(); }\ntry { a(); } catch (e) { e.stack }",
+            "a.js",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!(
+            vec![
+                frame("fail", None, None),
+                frame("a", Some("a.js"), None),
+                frame("<eval>", Some("a.js"), Some(2))
+            ],
+            JsStackFrame::parse_stack(&stack)
+        );
+        // the original error comes back to JS unchanged
+        let error: String = ctx.eval_into(
+            "function b() { nested(); }\ntry { b(); } catch (e) { globalThis.caught = e; String(e) }",
+            "b.js",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("RangeError: deep", error);
+        let same: bool = ctx.eval_into(
+            "caught === thrown && caught.stack === original",
+            "b.js",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(same);
+        let stack: String = ctx.eval_into("caught.stack", "b.js", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!(
+            vec![
+                frame("inner", Some("inner.js"), Some(2)),
+                frame("<eval>", Some("inner.js"), Some(4)),
+                frame("b", Some("b.js"), None),
+                frame("<eval>", Some("b.js"), Some(2)),
+            ],
+            JsStackFrame::parse_stack(&stack)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn rethrow_thrown_values() -> Result<()> {
+    quijine::context(|ctx| {
+        let global = ctx.global_object()?;
+        let pass = ctx.new_function(
+            |ctx, _this, args| ctx.call(args[0].clone(), ctx.undefined().into(), &[]),
+            "pass",
+            1,
+        )?;
+        global.set("pass", pass)?;
+        let ok: bool = ctx.eval_into(
+            r#"
+            class MyError extends Error {
+                constructor() { super("mine"); this.code = "E1"; }
+            }
+            const mine = new MyError();
+            const results = [];
+            for (const value of [42, "str", null, { plain: true }, mine]) {
+                try { pass(() => { throw value; }); } catch (e) { results.push(e === value); }
+            }
+            results.every((r) => r) && mine.code === "E1" && mine instanceof MyError
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(ok);
+        Ok(())
+    })
+}
+
+#[test]
+fn thrown_value() -> Result<()> {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+    let e = quijine::context(|ctx| {
+        let e = ctx.eval("throw 42", "<input>", EvalFlags::TYPE_GLOBAL).unwrap_err();
+        assert_eq!(Some(42), e.thrown_value(ctx).map(|v| v.to_i32().unwrap()));
+        assert_eq!("InternalError: 42", e.to_string());
+        let e = ctx
+            .eval("throw { code: 'E2' }", "<input>", EvalFlags::TYPE_GLOBAL)
+            .unwrap_err();
+        let code: String = e.thrown_value(ctx).unwrap().get("code")?;
+        assert_eq!("E2", code);
+        // the handle can be dropped on another thread
+        assert_send_sync(&e);
+        std::thread::spawn(move || drop(e)).join().unwrap();
+        ctx.runtime().run_gc();
+        ctx.eval("throw new RangeError('escaped')", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        Ok(())
+    })
+    .unwrap_err();
+    // the runtime has been freed
+    assert!(e.to_string().starts_with("InternalError: RangeError: escaped"), "{}", e);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct NotFound(std::io::Error);
+
+impl std::fmt::Display for NotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("config not found")
+    }
+}
+
+impl std::error::Error for NotFound {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// `Wrapped` carries an error of quijine as its source.
+#[derive(Debug)]
+struct Wrapped(Error);
+
+impl std::fmt::Display for Wrapped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("script failed")
+    }
+}
+
+impl std::error::Error for Wrapped {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl ErrorCode for NotFound {
+    fn code(&self) -> Option<String> {
+        Some("ENOENT".to_owned())
+    }
+}
+
+#[test]
+fn error_kinds() -> Result<()> {
+    quijine::context(|ctx| {
+        let fail = ctx.new_function(
+            |_ctx, _this, args| {
+                let kind = match args[0].to_string()?.as_str() {
+                    "EvalError" => ErrorKind::EvalError,
+                    "RangeError" => ErrorKind::RangeError,
+                    "ReferenceError" => ErrorKind::ReferenceError,
+                    "SyntaxError" => ErrorKind::SyntaxError,
+                    "TypeError" => ErrorKind::TypeError,
+                    "URIError" => ErrorKind::URIError,
+                    "AggregateError" => ErrorKind::AggregateError,
+                    _ => ErrorKind::InternalError,
+                };
+                Err(Error::with_str(kind, "failed"))
+            },
+            "fail",
+            1,
+        )?;
+        ctx.global_object()?.set("fail", fail)?;
+        let ok: bool = ctx.eval_into(
+            r#"
+            const ctors = [EvalError, RangeError, ReferenceError, SyntaxError, TypeError, URIError, AggregateError, InternalError];
+            ctors.every((ctor) => {
+                try { fail(ctor.name); } catch (e) {
+                    return e instanceof ctor && e.name === ctor.name && e.message === "failed" &&
+                        (ctor !== AggregateError || Array.isArray(e.errors));
+                }
+            })
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(ok);
+        Ok(())
+    })
+}
+
+#[test]
+fn external_cause_and_code() -> Result<()> {
+    quijine::context(|ctx| {
+        let open = ctx.new_function(
+            |_ctx, _this, _args| {
+                let e = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+                Err(Error::with_code(ErrorKind::TypeError, NotFound(e)))
+            },
+            "open",
+            0,
+        )?;
+        ctx.global_object()?.set("open", open)?;
+        let result: String = ctx.eval_into(
+            r#"
+            try { open(); } catch (e) {
+                [e instanceof TypeError, e.message, e.code, e.cause instanceof Error, e.cause.message].join()
+            }
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("true,config not found,ENOENT,true,no such file", result);
+        let e = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        assert_eq!(
+            Some("ENOENT"),
+            Error::with_code(ErrorKind::TypeError, NotFound(e)).code()
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn thrown_value_as_cause() -> Result<()> {
+    quijine::context(|ctx| {
+        let run = ctx.new_function(
+            |ctx, _this, _args| {
+                let e = ctx.eval("null.x", "inner.js", EvalFlags::TYPE_GLOBAL).unwrap_err();
+                Err(Error::external(Wrapped(e)))
+            },
+            "run",
+            0,
+        )?;
+        ctx.global_object()?.set("run", run)?;
+        let result: String = ctx.eval_into(
+            r#"
+            try { run(); } catch (e) {
+                [e.message, e.cause instanceof TypeError, e.cause.message].join()
+            }
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("script failed,true,cannot read property 'x' of null", result);
+        Ok(())
+    })
+}
+
+#[test]
+fn error_kinds_ignore_replaced_globals() -> Result<()> {
+    quijine::context(|ctx| {
+        let fail = ctx.new_function(
+            |_ctx, _this, _args| Err(Error::with_str(ErrorKind::URIError, "failed")),
+            "fail",
+            0,
+        )?;
+        ctx.global_object()?.set("fail", fail)?;
+        let ok: bool = ctx.eval_into(
+            r#"
+            const ctor = URIError;
+            globalThis.URIError = function () {};
+            try { fail(); } catch (e) { e instanceof ctor && e.name === "URIError" }
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(ok);
+        Ok(())
+    })
+}
@@ -0,0 +1,100 @@
+use quijine::{EvalFlags, Result, RuntimeHandle};
+use std::{sync::mpsc::channel, thread, time::Duration};
+
+#[test]
+fn call() -> Result<()> {
+    let handle = RuntimeHandle::spawn()?;
+    handle.call(|ctx| {
+        ctx.eval("var counter = 0;", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        Ok(())
+    })?;
+    let v: i32 = handle.call(|ctx| ctx.eval_into("++counter", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!(1, v);
+    let v: i32 = handle.call(|ctx| ctx.eval_into("++counter", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!(2, v, "the context is kept between jobs");
+    Ok(())
+}
+
+#[test]
+fn call_from_threads() -> Result<()> {
+    let handle = RuntimeHandle::spawn_with(
+        |rt| {
+            rt.set_memory_limit(16 * 1024 * 1024);
+            Ok(())
+        },
+        |ctx| {
+            ctx.eval("function square(x) { return x * x; }", "<init>", EvalFlags::TYPE_GLOBAL)?;
+            Ok(())
+        },
+    )?;
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let handle = handle.clone();
+            thread::spawn(move || {
+                handle.call(move |ctx| {
+                    let square = ctx.global_object()?.get::<_, quijine::Value>("square")?;
+                    ctx.call_into::<_, _, _, i32>(square, (), (i,))
+                })
+            })
+        })
+        .collect();
+    let mut results = threads
+        .into_iter()
+        .map(|th| th.join().unwrap())
+        .collect::<Result<Vec<_>>>()?;
+    results.sort_unstable();
+    assert_eq!(vec![0, 1, 4, 9], results);
+    Ok(())
+}
+
+#[test]
+fn post_and_pending_jobs() -> Result<()> {
+    let handle = RuntimeHandle::spawn()?;
+    let (tx, rx) = channel::<String>();
+    handle.post(move |ctx| {
+        let send = ctx
+            .new_function_from(
+                move |_ctx, _this: quijine::Value, (s,): (String,)| {
+                    tx.send(s).unwrap();
+                    Ok(())
+                },
+                "send",
+                1,
+            )
+            .unwrap();
+        ctx.global_object().unwrap().set("send", send).unwrap();
+        ctx.eval(
+            "Promise.resolve('resolved').then(send)",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )
+        .unwrap();
+    })?;
+    assert_eq!("resolved", rx.recv_timeout(Duration::from_secs(5)).unwrap());
+    Ok(())
+}
+
+#[test]
+fn interrupt() -> Result<()> {
+    let handle = RuntimeHandle::spawn()?;
+    let job = handle.spawn_job(|ctx| ctx.eval("for (;;) {}", "<input>", EvalFlags::TYPE_GLOBAL).map(|_| ()))?;
+    assert!(job.join_timeout(Duration::from_millis(50)).is_none());
+    handle.interrupt();
+    let err = job.join().expect_err("interrupted");
+    assert!(err.to_string().contains("interrupted"), "{}", err);
+    let v: i32 = handle.call(|ctx| ctx.eval_into("1 + 1", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!(2, v, "the runtime is still available");
+    Ok(())
+}
+
+#[test]
+fn panicking_job() -> Result<()> {
+    let handle = RuntimeHandle::spawn()?;
+    let e = handle.call::<_, ()>(|_ctx| panic!("boom")).unwrap_err();
+    assert!(e.to_string().contains("panic in a job: boom"), "{}", e);
+    handle.post(|_ctx| panic!("posted"))?;
+    // the runtime thread survives for the following jobs
+    let v: i32 = handle.call(|ctx| ctx.eval_into("1 + 1", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!(2, v);
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use quijine::{Class, Context, EvalFlags, InspectOptions, Result};
+
+fn inspect(ctx: Context, code: &str, options: &InspectOptions) -> Result<String> {
+    ctx.eval(code, "<input>", EvalFlags::TYPE_GLOBAL)?.inspect(options)
+}
+
+#[derive(Default)]
+struct Counter;
+
+impl Class for Counter {
+    fn name() -> &'static str {
+        "Counter"
+    }
+}
+
+#[test]
+fn primitives() -> Result<()> {
+    quijine::context(|ctx| {
+        let opts = InspectOptions::default();
+        assert_eq!("'str'", inspect(ctx, "'str'", &opts)?);
+        assert_eq!("\"it's\"", inspect(ctx, "\"it's\"", &opts)?);
+        assert_eq!("1.5", inspect(ctx, "1.5", &opts)?);
+        assert_eq!("-0", inspect(ctx, "-0", &opts)?);
+        assert_eq!("10n", inspect(ctx, "10n", &opts)?);
+        assert_eq!("Symbol(s)", inspect(ctx, "Symbol('s')", &opts)?);
+        assert_eq!("undefined", inspect(ctx, "undefined", &opts)?);
+        assert_eq!("null", inspect(ctx, "null", &opts)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn collections() -> Result<()> {
+    quijine::context(|ctx| {
+        let opts = InspectOptions::default();
+        assert_eq!(
+            "Map(2) { 'a' => 1, { k: 1 } => [ 2 ] }",
+            inspect(ctx, "new Map([['a', 1], [{ k: 1 }, [2]]])", &opts)?
+        );
+        assert_eq!("Set(2) { 1, 'x' }", inspect(ctx, "new Set([1, 'x'])", &opts)?);
+        assert_eq!("Map(0) {}", inspect(ctx, "new Map()", &opts)?);
+        assert_eq!(
+            "Uint8Array(3) [ 1, 2, 3 ]",
+            inspect(ctx, "new Uint8Array([1, 2, 3])", &opts)?
+        );
+        assert_eq!(
+            "[ 1, <2 empty items>, 4, <1 empty item> ]",
+            inspect(ctx, "const a = [1, , , 4]; a.length = 5; a", &opts)?
+        );
+        assert_eq!(
+            "[ <4294967293 empty items>, 1, <1 empty item> ]",
+            inspect(
+                ctx,
+                "const c = []; c[2 ** 32 - 3] = 1; c.length = 2 ** 32 - 1; c",
+                &opts
+            )?
+        );
+        assert_eq!(
+            "[ 1, 2, extra: true ]",
+            inspect(ctx, "const b = [1, 2]; b.extra = true; b", &opts)?
+        );
+        assert_eq!("{ [Symbol(k)]: 1 }", inspect(ctx, "({ [Symbol('k')]: 1 })", &opts)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn dates_boxed_primitives_and_promises() -> Result<()> {
+    quijine::context(|ctx| {
+        let opts = InspectOptions::default();
+        assert_eq!(
+            "2000-01-01T00:00:00.000Z",
+            inspect(ctx, "new Date(946684800000)", &opts)?
+        );
+        assert_eq!("Invalid Date", inspect(ctx, "new Date(NaN)", &opts)?);
+        assert_eq!("[Number: 3]", inspect(ctx, "new Number(3)", &opts)?);
+        assert_eq!("[String: 'abc']", inspect(ctx, "new String('abc')", &opts)?);
+        assert_eq!("[Boolean: true]", inspect(ctx, "new Boolean(true)", &opts)?);
+        assert_eq!(
+            "[Number: -0] { x: 1 }",
+            inspect(ctx, "Object.assign(new Number(-0), { x: 1 })", &opts)?
+        );
+        assert_eq!(
+            "{ [Symbol(Symbol.toStringTag)]: 'Number' }",
+            inspect(ctx, "({ [Symbol.toStringTag]: 'Number' })", &opts)?
+        );
+        assert_eq!("Promise { 1 }", inspect(ctx, "Promise.resolve(1)", &opts)?);
+        assert_eq!("Promise { <pending> }", inspect(ctx, "new Promise(() => {})", &opts)?);
+        assert_eq!(
+            "Promise { <rejected> 'no' }",
+            inspect(ctx, "const p = Promise.reject('no'); p.catch(() => {}); p", &opts)?
+        );
+        assert_eq!(
+            "Promise { [ 1 ], x: 2 }",
+            inspect(ctx, "Object.assign(Promise.resolve([1]), { x: 2 })", &opts)?
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn functions_and_classes() -> Result<()> {
+    quijine::context(|ctx| {
+        let opts = InspectOptions::default();
+        assert_eq!("[Function: f]", inspect(ctx, "(function f() {})", &opts)?);
+        assert_eq!("[class A]", inspect(ctx, "(class A {})", &opts)?);
+        assert_eq!("[AsyncFunction: g]", inspect(ctx, "(async function g() {})", &opts)?);
+        assert_eq!(
+            "[GeneratorFunction (anonymous)]",
+            inspect(ctx, "(function* () {})", &opts)?
+        );
+        assert_eq!("A {}", inspect(ctx, "class A {}; new A()", &opts)?);
+        let counter = ctx.new_object_with_opaque(Counter)?;
+        assert_eq!("Counter {}", counter.inspect(&opts)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn cycles() -> Result<()> {
+    quijine::context(|ctx| {
+        let opts = InspectOptions::default();
+        assert_eq!(
+            "<ref *1> { a: [ [Circular *1] ], m: Map(1) { 'self' => [Circular *1] } }",
+            inspect(ctx, "const o = {}; o.a = [o]; o.m = new Map([['self', o]]); o", &opts)?
+        );
+        // shared references are not cycles
+        assert_eq!(
+            "{ x: { v: 1 }, y: { v: 1 } }",
+            inspect(ctx, "const s = { v: 1 }; ({ x: s, y: s })", &opts)?
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn options() -> Result<()> {
+    quijine::context(|ctx| {
+        let nested = "({ a: { b: { c: { d: {} } } } })";
+        assert_eq!(
+            "{ a: { b: { c: [Object] } } }",
+            inspect(ctx, nested, &InspectOptions::default())?
+        );
+        assert_eq!(
+            "{ a: [Object] }",
+            inspect(ctx, nested, &InspectOptions::new().depth(Some(0)))?
+        );
+        assert_eq!(
+            "{ a: { b: { c: { d: {} } } } }",
+            inspect(ctx, nested, &InspectOptions::new().depth(None))?
+        );
+        let opts = InspectOptions::new().max_array_length(Some(3));
+        assert_eq!(
+            "[ 0, 1, 2, ... 7 more items ]",
+            inspect(ctx, "[...Array(10).keys()]", &opts)?
+        );
+        assert_eq!(
+            "Set(4) { 0, 1, 2, ... 1 more item }",
+            inspect(ctx, "new Set([0, 1, 2, 3])", &opts)?
+        );
+        let opts = InspectOptions::new().break_length(10);
+        assert_eq!("{\n  a: 1,\n  b: 'x'\n}", inspect(ctx, "({ a: 1, b: 'x' })", &opts)?);
+        let opts = InspectOptions::new().colors(true);
+        assert_eq!(
+            "{ a: \x1b[33m1\x1b[39m, b: \x1b[32m'x'\x1b[39m, c: \x1b[1mnull\x1b[22m, f: \x1b[36m[Function: f]\x1b[39m }",
+            inspect(ctx, "({ a: 1, b: 'x', c: null, f: function f() {} })", &opts)?
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn replaced_builtins() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.eval(
+            "Object.prototype.toString = () => '[object Fake]'; Function.prototype.toString = () => 'class {}';",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!(
+            "[ Map(1) { 'a' => 1 }, [Function: f] ]",
+            inspect(
+                ctx,
+                "[new Map([['a', 1]]), function f() {}]",
+                &InspectOptions::default()
+            )?
+        );
+        Ok(())
+    })
+}
@@ -102,7 +102,7 @@ fn init_modules(ctx: Context<'_>, c_string_arena: &mut CStringArena) {
                 "Counter",
                 1,
                 js_c_function!(|ctx, _this, args| {
-                    let v = Counter(args.get(0).and_then(|v| v.to_i32().ok()).unwrap_or(0));
+                    let v = Counter(args.first().and_then(|v| v.to_i32().ok()).unwrap_or(0));
                     ctx.new_object_with_opaque(v).unwrap().into()
                 }),
             )
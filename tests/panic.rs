@@ -0,0 +1,120 @@
+use quijine::{Class, EvalFlags, PanicPolicy, Result};
+
+fn define_explode(ctx: quijine::Context) -> Result<()> {
+    let explode = ctx.new_function(
+        |_ctx, _this, args| {
+            let what: String = args[0].to_string()?;
+            panic!("{} exploded", what)
+        },
+        "explode",
+        1,
+    )?;
+    ctx.global_object()?.set("explode", explode)?;
+    Ok(())
+}
+
+#[test]
+fn throw_on_panic() -> Result<()> {
+    quijine::context(|ctx| {
+        define_explode(ctx)?;
+        let result: String = ctx.eval_into(
+            r#"
+            function f() { explode("bomb"); }
+            try { f(); } catch (e) {
+                [e instanceof InternalError, e.message, e.stack.includes("at explode (native)")].join()
+            }
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("true,bomb exploded,true", result);
+        assert!(!ctx.runtime().is_poisoned());
+        // the runtime is still usable
+        assert_eq!(2, ctx.eval_into::<i32>("1 + 1", "<input>", EvalFlags::TYPE_GLOBAL)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn poison_on_panic() -> Result<()> {
+    quijine::context(|ctx| {
+        let rt = ctx.runtime();
+        rt.set_panic_policy(PanicPolicy::Poison);
+        assert_eq!(PanicPolicy::Poison, rt.panic_policy());
+        define_explode(ctx)?;
+        ctx.eval("globalThis.caught = false;", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        let e = ctx
+            .eval(
+                "try { explode('bomb'); } catch (e) { caught = true; }",
+                "<input>",
+                EvalFlags::TYPE_GLOBAL,
+            )
+            .unwrap_err();
+        assert!(e.to_string().contains("bomb exploded"), "{}", e);
+        // the error cannot be caught
+        assert!(!ctx.global_object()?.get::<_, bool>("caught")?);
+        assert!(rt.is_poisoned());
+        let e = ctx.eval("1", "<input>", EvalFlags::TYPE_GLOBAL).unwrap_err();
+        assert!(e.to_string().contains("poisoned"), "{}", e);
+        let explode = ctx.global_object()?.get("explode")?;
+        assert!(ctx.call(explode, ctx.undefined().into(), &[]).is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn panic_in_interrupt_handler() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.runtime().set_interrupt_handler(|| panic!("handler"));
+        let e = ctx.eval("for (;;) {}", "<input>", EvalFlags::TYPE_GLOBAL).unwrap_err();
+        assert!(e.to_string().contains("interrupted"), "{}", e);
+        Ok(())
+    })
+}
+
+#[derive(Default)]
+struct Bomb;
+
+impl Class for Bomb {
+    fn name() -> &'static str {
+        "Bomb"
+    }
+}
+
+impl Drop for Bomb {
+    fn drop(&mut self) {
+        panic!("dropped");
+    }
+}
+
+#[test]
+fn panic_in_finalizer() -> Result<()> {
+    quijine::context(|ctx| {
+        let bomb = ctx.new_object_with_opaque(Bomb)?;
+        drop(bomb);
+        ctx.runtime().run_gc();
+        assert_eq!(2, ctx.eval_into::<i32>("1 + 1", "<input>", EvalFlags::TYPE_GLOBAL)?);
+        Ok(())
+    })
+}
+
+#[cfg(feature = "c_function_list")]
+#[test]
+fn panic_in_c_function() -> Result<()> {
+    use quijine::{js_c_function, CFunctionListBuilder, CStringArena, Value};
+    quijine::context(|ctx| {
+        let mut arena = CStringArena::new();
+        let list = CFunctionListBuilder::new(&mut arena)
+            .cfunc_def("explode", 0, js_c_function!(|_ctx, _this, _args| panic!("c function")))
+            .build();
+        let global: Value = ctx.global_object()?.into();
+        global.set_property_function_list(&list);
+        let message: String = ctx.eval_into(
+            "try { explode(); } catch (e) { e.message }",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("c function", message);
+        Ok(())
+    })
+}
@@ -0,0 +1,151 @@
+use quijine::{EvalFlags, JobLimits, ReadObjFlags, Result, RuntimePool, WriteObjFlags};
+use std::{sync::Arc, thread, time::Duration};
+
+#[test]
+fn preload() -> Result<()> {
+    let pool = RuntimePool::builder()
+        .size(2)
+        .preload("function add(a, b) { return a + b; }", "add.js", EvalFlags::TYPE_GLOBAL)
+        .build()?;
+    assert_eq!(2, pool.size());
+    let v: i32 = pool.call(|ctx| ctx.eval_into("add(1, 2)", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!(3, v);
+    Ok(())
+}
+
+#[test]
+fn preload_module() -> Result<()> {
+    let pool = RuntimePool::builder()
+        .preload(
+            "export const greet = (s) => `Hello, ${s}!`; globalThis.greet = greet;",
+            "greet.js",
+            EvalFlags::TYPE_MODULE,
+        )
+        .build()?;
+    let v: String = pool.call(|ctx| ctx.eval_into("greet('world')", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!("Hello, world!", v);
+    Ok(())
+}
+
+#[test]
+fn preload_bytecode() -> Result<()> {
+    let bytecode = quijine::context(|ctx| {
+        let func = ctx.eval(
+            "var answer = 42;",
+            "answer.js",
+            EvalFlags::TYPE_GLOBAL | EvalFlags::FLAG_COMPILE_ONLY,
+        )?;
+        ctx.write_object(func, WriteObjFlags::BYTECODE)
+    })?;
+    let pool = RuntimePool::builder()
+        .init_context(|ctx| {
+            ctx.global_object()?.set("base", 40)?;
+            Ok(())
+        })
+        .preload_bytecode(bytecode.clone())
+        .build()?;
+    let v: i32 = pool.call(|ctx| ctx.eval_into("answer - base", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!(2, v);
+    // the bytecode is still readable by hand
+    quijine::context(|ctx| {
+        let func = ctx.read_object(&bytecode, ReadObjFlags::BYTECODE)?;
+        ctx.eval_function(func)?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn fresh_context() -> Result<()> {
+    let pool = RuntimePool::builder().size(1).build()?;
+    pool.call(|ctx| {
+        ctx.eval("globalThis.leaked = 1;", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        Ok(())
+    })?;
+    let t: String = pool.call(|ctx| ctx.eval_into("typeof leaked", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!("undefined", t);
+    Ok(())
+}
+
+#[test]
+fn time_limit() -> Result<()> {
+    let pool = RuntimePool::builder().time_limit(Duration::from_millis(50)).build()?;
+    let res = pool.call(|ctx| {
+        ctx.eval("for (;;) {}", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        Ok(())
+    });
+    assert!(res.is_err());
+    // the limit is applied to each job
+    let v: i32 = pool.call(|ctx| ctx.eval_into("1 + 1", "<input>", EvalFlags::TYPE_GLOBAL))?;
+    assert_eq!(2, v);
+    let v: i32 = pool.call_with_limits(JobLimits::default(), |ctx| {
+        ctx.eval_into(
+            "const t = Date.now(); while (Date.now() - t < 100) {} 3",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )
+    })?;
+    assert_eq!(3, v);
+    Ok(())
+}
+
+#[test]
+fn memory_limit() -> Result<()> {
+    let pool = RuntimePool::builder().memory_limit(1024 * 1024).build()?;
+    let res = pool.call(|ctx| {
+        ctx.eval(
+            "const a = []; for (let i = 0; i < 1e7; i++) a.push({ i });",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        Ok(())
+    });
+    assert!(res.is_err());
+    let v: i32 = pool.call(|ctx| {
+        ctx.eval_into(
+            "const a = []; for (let i = 0; i < 1e3; i++) a.push({ i }); a.length",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )
+    })?;
+    assert_eq!(1000, v);
+    Ok(())
+}
+
+#[test]
+fn from_threads() -> Result<()> {
+    let pool = Arc::new(
+        RuntimePool::builder()
+            .size(3)
+            .preload("const square = (x) => x * x;", "square.js", EvalFlags::TYPE_GLOBAL)
+            .build()?,
+    );
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let pool = pool.clone();
+            thread::spawn(move || {
+                let v: i32 = pool
+                    .call(move |ctx| ctx.eval_into(&format!("square({})", i), "<input>", EvalFlags::TYPE_GLOBAL))
+                    .unwrap();
+                assert_eq!(i * i, v);
+            })
+        })
+        .collect();
+    for th in threads {
+        th.join().unwrap();
+    }
+    Ok(())
+}
+
+#[test]
+fn panicking_job() -> Result<()> {
+    let pool = RuntimePool::builder().size(1).build()?;
+    for i in 0..3 {
+        let e = pool.call::<_, ()>(|_ctx| panic!("boom")).unwrap_err();
+        assert!(e.to_string().contains("panic in a job: boom"), "{}", e);
+        let v: i32 = pool.call(move |ctx| ctx.eval_into(&format!("{} * 2", i), "<input>", EvalFlags::TYPE_GLOBAL))?;
+        assert_eq!(i * 2, v);
+    }
+    assert_eq!(1, pool.size());
+    Ok(())
+}
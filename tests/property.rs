@@ -1,4 +1,4 @@
-use quijine::{EvalFlags, IntoQjAtom, PropFlags, Result, Value};
+use quijine::{EvalFlags, IntoQjAtom, PropFlags, PropertyDescriptor, Result, Value};
 
 #[test]
 fn prevent_extensions() -> Result<()> {
@@ -141,3 +141,233 @@ fn define_property_get_set_raw() -> Result<()> {
         Ok(())
     })
 }
+
+#[test]
+fn seal_and_freeze() -> Result<()> {
+    quijine::context(|ctx| {
+        let eval = |code: &str| ctx.eval(code, "<input>", EvalFlags::TYPE_GLOBAL | EvalFlags::FLAG_STRICT);
+        let sealed = eval("({ x: 1 })")?;
+        assert!(!sealed.is_sealed()?);
+        assert!(sealed.seal()?);
+        assert!(sealed.is_sealed()? && !sealed.is_frozen()?);
+        let frozen = eval("({ x: 1, get y() { return 2; }, [Symbol.iterator]: 3 })")?;
+        assert!(frozen.freeze()?);
+        assert!(frozen.is_sealed()? && frozen.is_frozen()?);
+        let global = ctx.global_object()?;
+        global.set("sealed", sealed)?;
+        global.set("frozen", frozen)?;
+        assert_eq!(
+            "true,true,true,true",
+            eval(
+                "[Object.isSealed(sealed), !Object.isFrozen(sealed), Object.isFrozen(frozen), frozen.y === 2].join()"
+            )?
+            .to_string()?
+        );
+        eval("sealed.x = 2")?;
+        eval("delete sealed.x").expect_err("sealed");
+        eval("sealed.z = 1").expect_err("sealed");
+        eval("frozen.x = 2").expect_err("frozen");
+        // primitives are regarded as frozen
+        let n: Value = ctx.new_int32(1).into();
+        assert!(n.freeze()? && n.is_frozen()?);
+        Ok(())
+    })
+}
+
+#[test]
+fn deep_freeze() -> Result<()> {
+    quijine::context(|ctx| {
+        let config = ctx.eval(
+            r#"
+            const config = {
+                server: { ports: [80, 443] },
+                get accessor() { return 1; },
+            };
+            config.server.config = config;
+            Object.defineProperty(config, "hidden", { value: { x: 1 }, enumerable: false, configurable: true, writable: true });
+            config
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(config.deep_freeze()?);
+        ctx.global_object()?.set("config", config)?;
+        let result = ctx.eval(
+            r#"
+            [
+                config.server,
+                config.server.ports,
+                config.hidden,
+                Object.getOwnPropertyDescriptor(config, "accessor").get,
+            ].every(Object.isFrozen) && !Object.isFrozen(Object.prototype)
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(result.to_bool()?);
+        ctx.eval(
+            "config.server.ports.push(8080)",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL | EvalFlags::FLAG_STRICT,
+        )
+        .expect_err("frozen");
+        Ok(())
+    })
+}
+
+#[test]
+fn property_descriptor() -> Result<()> {
+    quijine::context(|ctx| {
+        let obj = ctx.new_object()?;
+        ctx.global_object()?.set("obj", obj.clone())?;
+        let desc = PropertyDescriptor::new(ctx)
+            .with_value(ctx.new_int32(1))
+            .with_enumerable(true);
+        assert_eq!(
+            PropFlags::HAS_VALUE | PropFlags::HAS_ENUMERABLE | PropFlags::ENUMERABLE,
+            desc.flags()
+        );
+        assert!(obj.define_property_from("x", &desc)?);
+        let getter = ctx.new_function_from(|_ctx, _this: Value, _args: ()| Ok(2), "y", 0)?;
+        assert!(obj.define_property_from("y", &PropertyDescriptor::new(ctx).with_getter(getter))?);
+        let x = obj.own_property("x".into_qj_atom(ctx)?)?.unwrap();
+        assert_eq!(
+            (Some(false), Some(true), Some(false)),
+            (x.writable(), x.enumerable(), x.configurable())
+        );
+        assert!(x.is_data() && !x.is_accessor());
+        let y = obj.own_property("y".into_qj_atom(ctx)?)?.unwrap();
+        assert!(y.is_accessor() && y.value_opt().is_none() && y.value().is_undefined() && y.setter().is_undefined());
+        assert_eq!(None, y.writable());
+        assert_eq!(
+            "1,2,false",
+            ctx.eval(
+                "[obj.x, obj.y, Object.keys(obj).includes('y')].join()",
+                "<input>",
+                EvalFlags::TYPE_GLOBAL
+            )?
+            .to_string()?
+        );
+        // a descriptor read back redefines the same property
+        let copy = ctx.new_object()?;
+        assert!(copy.define_property_from("x", &x)?);
+        assert_eq!(1, copy.get::<_, i32>("x")?);
+        // an existing property keeps the attributes absent in the descriptor
+        obj.define_property_from(
+            "z",
+            &PropertyDescriptor::new(ctx)
+                .with_value(ctx.new_int32(3))
+                .with_configurable(true),
+        )?;
+        obj.define_property_from("z", &PropertyDescriptor::new(ctx).with_enumerable(true))?;
+        let z = obj.own_property("z".into_qj_atom(ctx)?)?.unwrap();
+        assert_eq!((Some(true), Some(true)), (z.enumerable(), z.configurable()));
+        assert_eq!(3, z.value().to_i32()?);
+        // a non-configurable property cannot be redefined
+        assert!(!obj.define_property_from("x", &PropertyDescriptor::new(ctx).with_enumerable(false))?);
+        let invalid = PropertyDescriptor::new(ctx)
+            .with_value(ctx.new_int32(1))
+            .with_getter(ctx.undefined());
+        let e = obj.define_property_from("w", &invalid).unwrap_err();
+        assert!(e.to_string().contains("invalid property descriptor"), "{}", e);
+        Ok(())
+    })
+}
+
+#[test]
+fn define_properties() -> Result<()> {
+    quijine::context(|ctx| {
+        let obj = ctx.new_object()?;
+        let symbol = ctx.eval("Symbol('s')", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(obj.define_properties([
+            (
+                "a".into_qj_atom(ctx)?,
+                PropertyDescriptor::new(ctx)
+                    .with_value(ctx.new_int32(1))
+                    .with_writable(true)
+            ),
+            (
+                symbol.into_qj_atom(ctx)?,
+                PropertyDescriptor::new(ctx)
+                    .with_value(ctx.new_int32(2))
+                    .with_enumerable(true)
+            ),
+        ])?);
+        let descs = obj.own_property_descriptors()?;
+        assert_eq!(2, descs.len());
+        assert_eq!("a", descs[0].0.to_value()?.to_string()?);
+        assert_eq!(Some(true), descs[0].1.writable());
+        assert_eq!(Some(false), descs[0].1.enumerable());
+        assert!(quijine::Symbol::try_from(descs[1].0.to_value()?).is_ok());
+        assert_eq!(2, descs[1].1.value().to_i32()?);
+        // copies the object including non-enumerable and symbol properties
+        let copy = ctx.new_object()?;
+        assert!(copy.define_properties(descs)?);
+        assert_eq!(2, copy.own_property_descriptors()?.len());
+        // an invalid descriptor is rejected before any property is defined
+        let invalid = PropertyDescriptor::new(ctx)
+            .with_value(ctx.new_int32(1))
+            .with_getter(ctx.undefined());
+        let target = ctx.new_object()?;
+        target
+            .define_properties([
+                ("b", PropertyDescriptor::new(ctx).with_value(ctx.new_int32(1))),
+                ("c", invalid),
+            ])
+            .unwrap_err();
+        assert!(target.own_property_descriptors()?.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn delete_and_has_own() -> Result<()> {
+    quijine::context(|ctx| {
+        let obj = ctx.eval(
+            "const obj = Object.create({ inherited: 1 }, { fixed: { value: 2 } }); obj.own = 3; obj",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert!(obj.has_own("own")? && obj.has_own("fixed")?);
+        assert!(!obj.has_own("inherited")? && obj.has_key("inherited")?);
+        assert!(obj.delete("own")?);
+        assert!(!obj.has_own("own")?);
+        // deleting a missing property succeeds
+        assert!(obj.delete("missing")?);
+        assert!(!obj.delete("fixed")?);
+        let e = obj
+            .delete_property("fixed".into_qj_atom(ctx)?, PropFlags::THROW)
+            .unwrap_err();
+        assert!(e.to_string().contains("could not delete"), "{}", e);
+        assert!(obj.has_own("fixed")?);
+        Ok(())
+    })
+}
+
+#[test]
+fn get_or_default() -> Result<()> {
+    quijine::context(|ctx| {
+        let obj = ctx.eval("({ port: 8080, host: undefined })", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!(8080, obj.get_or_default::<_, i32>("port")?);
+        assert_eq!(0, obj.get_or_default::<_, i32>("timeout")?);
+        assert_eq!("", obj.get_or_default::<_, String>("host")?);
+        Ok(())
+    })
+}
+
+#[test]
+fn indexed_properties() -> Result<()> {
+    quijine::context(|ctx| {
+        let xs = ctx.new_array_from([2, 3, 5])?;
+        assert_eq!(5, xs.get_index::<i32>(2)?);
+        assert!(xs.get_index::<Value>(3)?.is_undefined());
+        assert!(xs.set_index(4, "x")?);
+        assert_eq!(5, xs.get::<_, i32>("length")?);
+        assert_eq!("x", xs.get_index::<String>(4)?);
+        // works on any object
+        let obj = ctx.new_object()?;
+        obj.set_index(0, 1.5)?;
+        assert_eq!(1.5, obj.get::<_, f64>("0")?);
+        Ok(())
+    })
+}
@@ -0,0 +1,231 @@
+#![cfg(feature = "sandbox")]
+
+use quijine::{Context, EvalFlags, Intrinsics, Result, RuntimeScope, Sandbox};
+
+fn sandboxed<F>(sandbox: &Sandbox, f: F) -> Result<()>
+where
+    F: FnOnce(Context) -> Result<()>,
+{
+    let rts = RuntimeScope::new();
+    let ctxs = sandbox.new_context_scope(rts.get())?;
+    ctxs.with(f)
+}
+
+fn eval_string(ctx: Context, code: &str) -> Result<String> {
+    ctx.eval_into(code, "<untrusted>", EvalFlags::TYPE_GLOBAL)
+}
+
+fn expose_read_file(sandbox: &Sandbox, ctx: Context) -> Result<bool> {
+    sandbox.expose(ctx, "fs.read", "readFile", 1, |ctx, _this, args| {
+        Ok(ctx.new_string(&format!("contents of {}", args[0].to_string()?))?.into())
+    })
+}
+
+#[test]
+fn eval_is_disabled() -> Result<()> {
+    sandboxed(&Sandbox::new(), |ctx| {
+        assert_eq!(
+            "undefined,undefined",
+            eval_string(ctx, "[typeof eval, typeof Function].join()")?
+        );
+        let escapes = [
+            "(function () {}).constructor('return this')()",
+            "(async function () {}).constructor('return this')",
+            "(function* () {}).constructor('yield this')",
+            "(async function* () {}).constructor('yield this')",
+            "Object.constructor('return this')()",
+            "(() => {}).constructor.call(null, 'return this')()",
+            "Reflect.construct(Object.getPrototypeOf(() => {}).constructor, ['return this'])",
+            "new (Object.getPrototypeOf(function* () {}).constructor)('yield 1')",
+        ];
+        for escape in escapes {
+            let e = ctx
+                .eval(escape, "<untrusted>", EvalFlags::TYPE_GLOBAL)
+                .unwrap_err()
+                .to_string();
+            assert!(
+                e.contains("disallowed") || e.contains("not a constructor"),
+                "{}: {}",
+                escape,
+                e
+            );
+        }
+        // the bindings cannot be restored
+        ctx.eval("eval = 1; Function = 1;", "<untrusted>", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!("undefined", eval_string(ctx, "typeof eval")?);
+        Ok(())
+    })
+}
+
+#[test]
+fn builtins_are_frozen() -> Result<()> {
+    sandboxed(&Sandbox::new(), |ctx| {
+        ctx.eval(
+            r#"
+            Object.prototype.polluted = 1;
+            Array.prototype.push = () => "evil";
+            ({}).__proto__.viaProto = 1;
+            JSON.parse = () => "evil";
+            Math = null;
+            try { Object.defineProperty(Object.prototype, "defined", { value: 1 }); } catch (e) {}
+            try { Object.setPrototypeOf(Array.prototype, null); } catch (e) {}
+            "#,
+            "<untrusted>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let checks = r#"
+            [
+                ({}).polluted === undefined,
+                ({}).viaProto === undefined,
+                ({}).defined === undefined,
+                [].push(1) === 1,
+                JSON.parse("1") === 1,
+                typeof Math === "object",
+                Object.getPrototypeOf(Array.prototype) === Object.prototype,
+            ].join()
+        "#;
+        assert_eq!("true,true,true,true,true,true,true", eval_string(ctx, checks)?);
+        // strict code gets errors
+        let e = ctx
+            .eval(
+                "'use strict'; String.prototype.trim = null;",
+                "<untrusted>",
+                EvalFlags::TYPE_GLOBAL,
+            )
+            .unwrap_err();
+        assert!(e.to_string().contains("read-only"), "{}", e);
+        // scripts still define their own globals
+        ctx.eval(
+            "var mine = 1; function f() { return mine; }",
+            "<untrusted>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!(1, ctx.eval_into::<i32>("f()", "<untrusted>", EvalFlags::TYPE_GLOBAL)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn capabilities() -> Result<()> {
+    let sandbox = Sandbox::new().grant("fs.read");
+    assert!(sandbox.is_granted("fs.read"));
+    assert!(sandbox.is_granted("fs.read.text"));
+    assert!(!sandbox.is_granted("fs"));
+    assert!(!sandbox.is_granted("fs.write"));
+    sandboxed(&sandbox, |ctx| {
+        assert!(expose_read_file(&sandbox, ctx)?);
+        assert!(
+            !sandbox.expose(ctx, "fs.write", "writeFile", 2, |ctx, _this, _args| Ok(ctx
+                .undefined()
+                .into()))?
+        );
+        assert_eq!("contents of a.txt", eval_string(ctx, "readFile('a.txt')")?);
+        assert_eq!("undefined", eval_string(ctx, "typeof writeFile")?);
+        ctx.eval(
+            "readFile = () => 'evil'; readFile.call = () => 'evil'; delete globalThis.readFile;",
+            "<untrusted>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("contents of b.txt", eval_string(ctx, "readFile.call(null, 'b.txt')")?);
+        let e = ctx
+            .eval(
+                "readFile.constructor('return this')()",
+                "<untrusted>",
+                EvalFlags::TYPE_GLOBAL,
+            )
+            .unwrap_err();
+        assert!(e.to_string().contains("disallowed"), "{}", e);
+        Ok(())
+    })?;
+    // a parent capability grants its children
+    let sandbox = Sandbox::new().grant("fs");
+    sandboxed(&sandbox, |ctx| {
+        assert!(expose_read_file(&sandbox, ctx)?);
+        Ok(())
+    })?;
+    // exposed functions are frozen even if scripts replaced `Object.freeze`
+    let sandbox = Sandbox::new().grant("fs.read").freeze_builtins(false);
+    sandboxed(&sandbox, |ctx| {
+        ctx.eval("Object.freeze = (o) => o;", "<untrusted>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(expose_read_file(&sandbox, ctx)?);
+        assert_eq!("true", eval_string(ctx, "String(Object.isFrozen(readFile))")?);
+        Ok(())
+    })
+}
+
+#[test]
+fn intrinsics() -> Result<()> {
+    let sandbox = Sandbox::new().intrinsics(Intrinsics::JSON);
+    sandboxed(&sandbox, |ctx| {
+        assert_eq!(
+            "undefined,undefined,undefined,undefined,object",
+            eval_string(
+                ctx,
+                "[typeof Date, typeof Promise, typeof Map, typeof Proxy, typeof JSON].join()"
+            )?
+        );
+        Ok(())
+    })?;
+    let sandbox = Sandbox::new().allow_eval(true).freeze_builtins(false);
+    sandboxed(&sandbox, |ctx| {
+        assert_eq!(
+            3,
+            ctx.eval_into::<i32>("eval('1 + 2')", "<untrusted>", EvalFlags::TYPE_GLOBAL)?
+        );
+        assert_eq!(
+            1,
+            ctx.eval_into::<i32>("Array.prototype.x = 1; [].x", "<untrusted>", EvalFlags::TYPE_GLOBAL)?
+        );
+        Ok(())
+    })
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn from_toml() -> Result<()> {
+    let sandbox = Sandbox::from_toml(
+        r#"
+        # profile for plugins
+        intrinsics = [
+            "json",
+            "map_set", # collections
+        ]
+        freeze_builtins = true
+        allow_eval = false
+        capabilities = ["fs.read", "net"]
+        memory_limit = 16_777_216
+        "#,
+    )?;
+    assert!(sandbox.is_granted("fs.read") && sandbox.is_granted("net.fetch"));
+    sandboxed(&sandbox, |ctx| {
+        assert_eq!(
+            "object,function,undefined,undefined",
+            eval_string(ctx, "[typeof JSON, typeof Map, typeof Promise, typeof eval].join()")?
+        );
+        assert!(expose_read_file(&sandbox, ctx)?);
+        Ok(())
+    })?;
+    let errors = [
+        ("[sandbox]", "unknown field `sandbox`"),
+        (
+            "intrinsics = [\"json\"",
+            "expected a right bracket, found eof at line 1",
+        ),
+        ("allow_eval = yes", "invalid TOML value"),
+        (
+            "\nallow_eval = \"false\"",
+            "expected a boolean for key `allow_eval` at line 2",
+        ),
+        ("intrinsics = [\"fs\"]", "unknown intrinsic `fs`"),
+        ("capabilities = [1]", "expected a string for key `capabilities`"),
+        ("shell = true", "unknown field `shell`"),
+        ("memory_limit = -1", "expected usize for key `memory_limit`"),
+        ("allow_eval = true\nallow_eval = false", "duplicate field `allow_eval`"),
+        ("name = \"unterminated", "unterminated string"),
+    ];
+    for (source, message) in errors {
+        let e = Sandbox::from_toml(source).unwrap_err();
+        assert!(e.to_string().contains(message), "{:?}: {}", source, e);
+    }
+    Ok(())
+}
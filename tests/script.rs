@@ -0,0 +1,71 @@
+use quijine::{BytecodeError, CompiledScript, ErrorKind, EvalFlags, Result, QUICKJS_VERSION};
+use std::error::Error as _;
+
+#[test]
+fn compile_and_run() -> Result<()> {
+    let bytes = quijine::context(|ctx| {
+        let script = ctx.compile("const x = 20; x + 22", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(!script.is_module());
+        assert_eq!(EvalFlags::TYPE_GLOBAL, script.eval_flags());
+        let v: i32 = script.run(ctx)?.try_into()?;
+        assert_eq!(42, v);
+        Ok(script.to_bytes())
+    })?;
+    // run in another runtime
+    quijine::context(|ctx| {
+        let script = CompiledScript::from_bytes(&bytes)?;
+        let v: i32 = script.run(ctx)?.try_into()?;
+        assert_eq!(42, v);
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn compile_module() -> Result<()> {
+    let bytes = quijine::context(|ctx| {
+        let script = ctx.compile(
+            "export const x = 1; globalThis.y = x + 1;",
+            "mod.js",
+            EvalFlags::TYPE_MODULE | EvalFlags::FLAG_COMPILE_ONLY,
+        )?;
+        assert!(script.is_module());
+        assert_eq!(EvalFlags::TYPE_MODULE, script.eval_flags());
+        Ok(script.to_bytes())
+    })?;
+    quijine::context(|ctx| {
+        CompiledScript::from_bytes(&bytes)?.run(ctx)?;
+        let y: i32 = ctx.global_object()?.get("y")?;
+        assert_eq!(2, y);
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn syntax_error() {
+    let err = quijine::context(|ctx| ctx.compile("1 +", "<input>", EvalFlags::TYPE_GLOBAL)).unwrap_err();
+    assert!(err.to_string().contains("SyntaxError"), "{}", err);
+}
+
+#[test]
+fn refuse_invalid_blobs() -> Result<()> {
+    let bytes = quijine::context(|ctx| Ok(ctx.compile("1", "<input>", EvalFlags::TYPE_GLOBAL)?.to_bytes()))?;
+    let version = QUICKJS_VERSION.as_bytes();
+    assert_eq!(version, &bytes[6..6 + version.len()]);
+
+    let mut stale = bytes.clone();
+    stale[6] = b'1';
+    let err = CompiledScript::from_bytes(&stale).unwrap_err();
+    assert!(err.to_string().contains("stale bytecode"), "{}", err);
+    assert_eq!(ErrorKind::ExternalError, err.kind);
+    let source = err.source().and_then(|e| e.downcast_ref::<BytecodeError>());
+    assert!(matches!(source, Some(BytecodeError::Stale(_))), "{:?}", source);
+
+    let err = CompiledScript::from_bytes(&bytes[1..]).unwrap_err();
+    assert!(err.to_string().contains("not a compiled script"), "{}", err);
+
+    let err = CompiledScript::from_bytes(&bytes[..8]).unwrap_err();
+    assert!(err.to_string().contains("truncated header"), "{}", err);
+    Ok(())
+}
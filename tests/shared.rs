@@ -0,0 +1,135 @@
+use quijine::{EvalFlags, Result, RuntimeHandle, RuntimePool, RuntimeScope, SharedBuffer};
+use std::sync::atomic::Ordering;
+
+#[test]
+fn share_between_runtimes() -> Result<()> {
+    let buf = SharedBuffer::new(16);
+    let handles = (0..2)
+        .map(|_| RuntimeHandle::spawn_with(|rt| rt.enable_shared_array_buffer(), |_ctx| Ok(())))
+        .collect::<Result<Vec<_>>>()?;
+    let b = buf.clone();
+    let waiter = handles[0].spawn_job(move |ctx| {
+        let sab = ctx.new_shared_array_buffer(&b)?;
+        ctx.global_object()?.set("sab", sab)?;
+        ctx.eval_into::<String>(
+            r#"
+            const a = new Int32Array(sab);
+            let r;
+            while ((r = Atomics.wait(a, 0, 0, 1000)) === "timed-out") {}
+            String(Atomics.load(a, 0))
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )
+    })?;
+    let b = buf.clone();
+    handles[1].call(move |ctx| {
+        let sab = ctx.new_shared_array_buffer(&b)?;
+        ctx.global_object()?.set("sab", sab)?;
+        ctx.eval(
+            "const a = new Int32Array(sab); Atomics.store(a, 0, 42); Atomics.notify(a, 0);",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        Ok(())
+    })?;
+    assert_eq!("42", waiter.join()?);
+    assert_eq!(42, buf.as_atomic_i32()[0].load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[test]
+fn parallel_pool() -> Result<()> {
+    const LEN: usize = 1 << 16;
+    const WORKERS: usize = 4;
+    let buf = SharedBuffer::new(LEN * 8);
+    let pool = RuntimePool::builder().size(WORKERS).shared_array_buffer(true).build()?;
+    let jobs = (0..WORKERS)
+        .map(|i| {
+            let b = buf.clone();
+            pool.spawn_job(move |ctx| {
+                let sab = ctx.new_shared_array_buffer(&b)?;
+                let global = ctx.global_object()?;
+                global.set("sab", sab)?;
+                global.set("start", (i * LEN / WORKERS) as i32)?;
+                global.set("end", ((i + 1) * LEN / WORKERS) as i32)?;
+                ctx.eval(
+                    "const f = new Float64Array(sab); for (let i = start; i < end; i++) f[i] = i * 0.5;",
+                    "<input>",
+                    EvalFlags::TYPE_GLOBAL,
+                )?;
+                Ok(())
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for job in jobs {
+        job.join()?;
+    }
+    let bytes = unsafe { buf.as_slice() };
+    for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+        assert_eq!(i as f64 * 0.5, f64::from_ne_bytes(chunk.try_into().unwrap()));
+    }
+    Ok(())
+}
+
+#[test]
+fn from_js() -> Result<()> {
+    let rts = RuntimeScope::new();
+    rts.get().enable_shared_array_buffer()?;
+    let buf = rts.run_with_context(|ctx| {
+        let sab = ctx.eval(
+            "const s = new SharedArrayBuffer(4); new Uint8Array(s)[3] = 7; s",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let buf = sab.shared_array_buffer().expect("SharedArrayBuffer");
+        assert_eq!(4, buf.len());
+        // an ArrayBuffer is not shared
+        let ab = ctx.eval("new ArrayBuffer(4)", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(ab.shared_array_buffer().is_none());
+        let obj = ctx.eval("({})", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(obj.shared_array_buffer().is_none());
+        Ok(buf)
+    })?;
+    drop(rts);
+    // the memory outlives the runtime
+    assert_eq!(7, buf.as_atomic_u8()[3].load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[test]
+fn structured_clone() -> Result<()> {
+    let rts1 = RuntimeScope::new();
+    let rts2 = RuntimeScope::new();
+    rts1.get().enable_shared_array_buffer()?;
+    rts2.get().enable_shared_array_buffer()?;
+    let ctxs1 = rts1.new_context_scope();
+    let ctxs2 = rts2.new_context_scope();
+    let (ctx1, ctx2) = (ctxs1.get(), ctxs2.get());
+    let v = ctx1.eval(
+        "globalThis.s = new SharedArrayBuffer(4); ({ s })",
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    let cloned = v.structured_clone_to(ctx2)?;
+    ctx2.global_object()?.set("o", cloned)?;
+    ctx2.eval("new Int32Array(o.s)[0] = 5", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    let n: i32 = ctx1.eval_into("new Int32Array(s)[0]", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    assert_eq!(5, n);
+    Ok(())
+}
+
+#[test]
+fn not_enabled() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    assert!(rts.get().enable_shared_array_buffer().is_err());
+    assert!(ctxs.get().new_shared_array_buffer(&SharedBuffer::new(4)).is_err());
+    // SharedArrayBuffers owned by the runtime can't be cloned
+    let v = ctxs
+        .get()
+        .eval("({ s: new SharedArrayBuffer(4) })", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    let e = quijine::context(|ctx| v.structured_clone_to(ctx).map(|_| ())).unwrap_err();
+    assert!(e.to_string().contains("SharedArrayBuffer at value.s"), "{}", e);
+    Ok(())
+}
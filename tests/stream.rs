@@ -0,0 +1,206 @@
+#![cfg(all(feature = "stream", feature = "timers"))]
+
+use quijine::{message_queue, Context, EvalFlags, Result, Stream, Value};
+use std::{
+    cell::Cell,
+    future,
+    pin::Pin,
+    rc::Rc,
+    task::{self, Poll},
+    thread,
+    time::Duration,
+};
+
+fn log(ctx: Context) -> Result<String> {
+    ctx.eval_into("log.join(',')", "<input>", EvalFlags::TYPE_GLOBAL)
+}
+
+#[test]
+fn for_await_message_queue() -> Result<()> {
+    quijine::context(|ctx| {
+        let (sender, queue) = message_queue();
+        let producer = thread::spawn(move || {
+            for i in 0..5 {
+                thread::sleep(Duration::from_millis(2));
+                sender.send(i).unwrap();
+            }
+        });
+        ctx.global_object()?.set("messages", ctx.new_async_iterator(queue)?)?;
+        ctx.eval(
+            r#"
+            globalThis.log = [];
+            (async () => {
+                for await (const m of messages) log.push(m);
+                log.push("end");
+            })();
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        ctx.run_until_idle()?;
+        while ctx.wait_for_streams()? {
+            ctx.run_until_idle()?;
+        }
+        producer.join().unwrap();
+        assert_eq!("0,1,2,3,4,end", log(ctx)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn run_until_idle_does_not_wait_for_streams() -> Result<()> {
+    quijine::context(|ctx| {
+        let (sender, queue) = message_queue();
+        ctx.global_object()?.set("messages", ctx.new_async_iterator(queue)?)?;
+        ctx.eval(
+            r#"
+            globalThis.log = [];
+            (async () => {
+                for await (const m of messages) log.push(m);
+                log.push("end");
+            })();
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        ctx.run_until_idle()?;
+        assert_eq!("", log(ctx)?);
+        // a future woken after it is ready does not leave a wakeup behind
+        let woken = future::poll_fn(|cx| {
+            cx.waker().wake_by_ref();
+            Poll::Ready(1)
+        });
+        assert_eq!(1, ctx.block_on(woken)?);
+        sender.send(1).unwrap();
+        assert!(ctx.wait_for_streams()?);
+        ctx.run_until_idle()?;
+        assert_eq!("1", log(ctx)?);
+        drop(sender);
+        assert!(ctx.wait_for_streams()?);
+        ctx.run_until_idle()?;
+        assert_eq!("1,end", log(ctx)?);
+        assert!(!ctx.wait_for_streams()?);
+        Ok(())
+    })
+}
+
+struct Counter {
+    n: i32,
+    dropped: Rc<Cell<bool>>,
+}
+
+impl Stream for Counter {
+    type Item = i32;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut task::Context<'_>) -> Poll<Option<i32>> {
+        self.n += 1;
+        Poll::Ready(Some(self.n))
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        self.dropped.set(true);
+    }
+}
+
+#[test]
+fn break_drops_stream() -> Result<()> {
+    quijine::context(|ctx| {
+        let dropped = Rc::new(Cell::new(false));
+        let counter = Counter {
+            n: 0,
+            dropped: dropped.clone(),
+        };
+        ctx.global_object()?.set("counter", ctx.new_async_iterator(counter)?)?;
+        ctx.eval(
+            r#"
+            globalThis.log = [];
+            (async () => {
+                for await (const n of counter) {
+                    log.push(n);
+                    if (n === 3) break;
+                }
+                log.push(JSON.stringify(await counter.next()));
+            })();
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        ctx.run_until_idle()?;
+        assert!(dropped.get());
+        assert_eq!(r#"1,2,3,{"done":true}"#, log(ctx)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn consume_async_generator() -> Result<()> {
+    quijine::context(|ctx| {
+        let gen: Value = ctx.eval(
+            r#"
+            (async function* () {
+                yield 1;
+                await null;
+                yield "two";
+                throw new RangeError("three");
+            })()
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let mut stream = gen.async_iter()?;
+        let first: i32 = ctx.block_on(stream.next())?.unwrap()?.to_i32()?;
+        assert_eq!(1, first);
+        let second = ctx.block_on(stream.next())?.unwrap()?;
+        assert_eq!("two", second.to_string()?);
+        let error = ctx.block_on(stream.next())?.unwrap().unwrap_err();
+        assert!(error.to_string().contains("three"), "{}", error);
+        assert!(ctx.block_on(stream.next())?.is_none());
+        // replaced globals are not used
+        let gen: Value = ctx.eval(
+            r#"
+            const gen = (async function* () { yield 1; })();
+            globalThis.Symbol = undefined;
+            globalThis.Promise = undefined;
+            gen
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let mut stream = gen.async_iter()?;
+        assert_eq!(1, ctx.block_on(stream.next())?.unwrap()?.to_i32()?);
+        assert!(ctx.block_on(stream.next())?.is_none());
+        Ok(())
+    })
+}
+
+#[test]
+fn round_trip() -> Result<()> {
+    quijine::context(|ctx| {
+        let (sender, queue) = message_queue();
+        let iterator = ctx.new_async_iterator(queue)?;
+        let doubled: Value = ctx.call(
+            ctx.eval(
+                "(async function* (it) { for await (const x of it) yield x * 2; })",
+                "<input>",
+                EvalFlags::TYPE_GLOBAL,
+            )?,
+            ctx.undefined().into(),
+            &[iterator.into()],
+        )?;
+        let producer = thread::spawn(move || {
+            for i in 1..=3 {
+                sender.send(i).unwrap();
+            }
+        });
+        let mut stream = doubled.async_iter()?;
+        let mut values = Vec::new();
+        while let Some(v) = ctx.block_on(stream.next())? {
+            values.push(v?.to_i32()?);
+        }
+        producer.join().unwrap();
+        assert_eq!(vec![2, 4, 6], values);
+        Ok(())
+    })
+}
@@ -0,0 +1,136 @@
+#![cfg(feature = "timers")]
+
+use quijine::{Clock, Context, EvalFlags, Result};
+use std::time::{Duration, Instant};
+
+fn log(ctx: Context) -> Result<String> {
+    ctx.eval_into("log.join(',')", "<input>", EvalFlags::TYPE_GLOBAL)
+}
+
+#[test]
+fn order() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.install_timers(Clock::Virtual)?;
+        ctx.eval(
+            r#"
+            globalThis.log = [];
+            setTimeout(() => log.push("t20"), 20);
+            setTimeout((a, b) => log.push(`t10 ${a} ${b}`), 10, "x", "y");
+            setTimeout(() => log.push("t0"));
+            queueMicrotask(() => log.push("micro"));
+            Promise.resolve().then(() => log.push("promise"));
+            log.push("sync");
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!(3, ctx.timer_count());
+        ctx.run_until_idle()?;
+        assert_eq!("sync,micro,promise,t0,t10 x y,t20", log(ctx)?);
+        assert_eq!(0, ctx.timer_count());
+        Ok(())
+    })
+}
+
+#[test]
+fn clear_and_interval() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.install_timers(Clock::Virtual)?;
+        ctx.eval(
+            r#"
+            globalThis.log = [];
+            const t = setTimeout(() => log.push("cleared"), 5);
+            clearTimeout(t);
+            let n = 0;
+            const i = setInterval(() => {
+                log.push(`i${++n}`);
+                if (n === 3) clearInterval(i);
+            }, 10);
+            setTimeout(() => log.push("t25"), 25);
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        ctx.run_until_idle()?;
+        assert_eq!("i1,i2,t25,i3", log(ctx)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn run_for() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.install_timers(Clock::Virtual)?;
+        ctx.eval(
+            r#"
+            globalThis.log = [];
+            setInterval(() => log.push("tick"), 100);
+            setTimeout(() => log.push("late"), 1000);
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let start = Instant::now();
+        ctx.run_for(Duration::from_millis(350))?;
+        assert_eq!("tick,tick,tick", log(ctx)?);
+        // the time advances by 350ms, so the next tick comes 50ms later
+        ctx.run_for(Duration::from_millis(50))?;
+        assert_eq!("tick,tick,tick,tick", log(ctx)?);
+        // a virtual clock never sleeps
+        assert!(start.elapsed() < Duration::from_millis(300));
+        assert_eq!(2, ctx.timer_count());
+        Ok(())
+    })
+}
+
+#[test]
+fn real_clock() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.install_timers(Clock::Real)?;
+        ctx.eval(
+            "globalThis.log = []; setTimeout(() => log.push('done'), 30);",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let start = Instant::now();
+        ctx.run_until_idle()?;
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!("done", log(ctx)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn errors() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.install_timers(Clock::Virtual)?;
+        let e = ctx
+            .eval("setTimeout(42)", "<input>", EvalFlags::TYPE_GLOBAL)
+            .unwrap_err();
+        assert!(e.to_string().contains("not a function"), "{}", e);
+        ctx.eval(
+            "globalThis.log = []; setTimeout(() => { throw new Error('boom'); }); setTimeout(() => log.push('next'), 1);",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let e = ctx.run_until_idle().unwrap_err();
+        assert!(e.to_string().contains("boom"), "{}", e);
+        // the remaining timers still run
+        ctx.run_until_idle()?;
+        assert_eq!("next", log(ctx)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn pending_timers_are_freed() -> Result<()> {
+    quijine::context(|ctx| {
+        ctx.install_timers(Clock::Virtual)?;
+        ctx.eval(
+            "const o = { big: new Array(1000).fill(1) }; setTimeout(() => o, 1000, o);",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        Ok(())
+    })
+}
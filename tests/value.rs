@@ -1,5 +1,5 @@
 use maplit::btreemap;
-use quijine::{EvalFlags, FromQj};
+use quijine::{ArrayRef, ErrorKind, EvalFlags, FromQj, Value};
 
 #[test]
 fn test_iterator_array() {
@@ -116,3 +116,146 @@ fn test_entries() {
     })
     .unwrap();
 }
+
+#[test]
+fn test_array_ref() {
+    quijine::context(|ctx| {
+        let xs: ArrayRef = ctx.new_array()?.try_into()?;
+        assert_eq!(0, xs.len()?);
+        assert!(xs.is_empty()?);
+        assert_eq!(1, xs.push(2)?);
+        assert_eq!(2, xs.push("three")?);
+        xs.extend([4.5, 6.0])?;
+        assert_eq!(4, xs.len()?);
+        assert_eq!(2, xs.get::<i32>(0)?);
+        assert_eq!("three", xs.get::<String>(1)?);
+        assert!(xs.get::<Value>(4)?.is_undefined());
+        let items = xs
+            .iter()?
+            .map(|v| v?.to_string())
+            .collect::<quijine::Result<Vec<_>>>()?;
+        assert_eq!(vec!["2", "three", "4.5", "6"], items);
+        // a plain object is not an array
+        let obj: Value = ctx.new_object()?.into();
+        assert!(ArrayRef::try_from(obj).is_err());
+        let arr: Value = ctx.eval("[1, 2]", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!(2, ArrayRef::try_from(arr)?.len()?);
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_bulk_arrays() {
+    quijine::context(|ctx| {
+        let n = 100_000;
+        let fs: Vec<f64> = (0..n).map(|i| i as f64 / 2.0).collect();
+        let xs = ctx.new_array_from_f64(&fs)?;
+        assert_eq!(n, xs.len()?);
+        assert_eq!(fs, xs.to_f64_vec()?);
+        let is: Vec<i32> = (0..n as i32).collect();
+        let ys = ctx.new_array_from_i32(&is)?;
+        assert_eq!(is, ys.to_i32_vec()?);
+        assert_eq!(is, Vec::<i32>::from_qj(ys.into())?);
+        ctx.global_object()?.set("xs", xs)?;
+        let sum: f64 = ctx.eval_into("xs.reduce((a, b) => a + b, 0)", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!(fs.iter().sum::<f64>(), sum);
+        // replacing `push` doesn't affect the conversions
+        ctx.eval(
+            "Array.prototype.push = () => { throw new Error('replaced'); }",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        let zs = ctx.new_array_from(vec![1, 2, 3])?;
+        assert_eq!(vec![1, 2, 3], Vec::<i32>::from_qj(zs.into())?);
+        let e = ctx.eval("[].push(1)", "<input>", EvalFlags::TYPE_GLOBAL).unwrap_err();
+        assert!(e.to_string().contains("replaced"), "{}", e);
+        // a failed conversion names the element
+        let arr: Value = ctx.eval("[1, Symbol()]", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        let e = ArrayRef::try_from(arr)?.to_f64_vec().unwrap_err();
+        assert_eq!(ErrorKind::TypeError, e.kind);
+        assert!(e.to_string().contains("index 1"), "{}", e);
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_new_iterator() {
+    use std::{cell::Cell, rc::Rc};
+
+    struct Guard(Rc<Cell<bool>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    quijine::context(|ctx| {
+        let global = ctx.global_object()?;
+        // lazy and infinite
+        let pulled = Rc::new(Cell::new(0));
+        let dropped = Rc::new(Cell::new(false));
+        let naturals = {
+            let pulled = pulled.clone();
+            let guard = Guard(dropped.clone());
+            (0..).inspect(move |_| {
+                let _ = &guard;
+                pulled.set(pulled.get() + 1);
+            })
+        };
+        global.set("naturals", ctx.new_iterator(naturals)?)?;
+        let sum: i32 = ctx.eval_into(
+            "let sum = 0; for (const i of naturals) { if (i >= 5) break; sum += i; } sum",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!(10, sum);
+        assert_eq!(6, pulled.get());
+        // `break` calls `return()`, which drops the Rust iterator
+        assert!(dropped.get());
+        let done: bool = ctx.eval_into("naturals.next().done", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(done);
+        // spread and destructuring
+        global.set("words", ctx.new_iterator(vec!["a", "b", "c"])?)?;
+        let joined: String = ctx.eval_into("[...words].join()", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert_eq!("a,b,c", joined);
+        let result: String = ctx.eval_into(
+            "const r = words.next(); [r.value, r.done, words[Symbol.iterator]() === words].join()",
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!(",true,true", result);
+        // re-entrant calls are rejected
+        let it = ctx.new_iterator((0..3).map(move |_| {
+            ctx.eval("reentrant.next()", "<input>", EvalFlags::TYPE_GLOBAL)
+                .map_or_else(|e| e.to_string(), |_| "no error".to_owned())
+        }))?;
+        global.set("reentrant", it)?;
+        let e: String = ctx.eval_into("reentrant.next().value", "<input>", EvalFlags::TYPE_GLOBAL)?;
+        assert!(e.contains("already running"), "{}", e);
+        // the prototype is %IteratorPrototype%, and replaced globals are not used
+        global.set("letters", ctx.new_iterator(vec!["x", "y"])?)?;
+        let result: String = ctx.eval_into(
+            r#"
+            const iteratorPrototype = Object.getPrototypeOf(Object.getPrototypeOf([][Symbol.iterator]()));
+            const inherited = Object.getPrototypeOf(letters) === iteratorPrototype;
+            globalThis.Symbol = undefined;
+            [inherited, ...letters].join()
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        assert_eq!("true,x,y", result);
+        global.set("numbers", ctx.new_array_from(vec![1, 2])?)?;
+        let numbers: Vec<i32> = global
+            .get::<_, Value>("numbers")?
+            .iterator()?
+            .map(|v| v?.to_i32())
+            .collect::<quijine::Result<_>>()?;
+        assert_eq!(vec![1, 2], numbers);
+        Ok(())
+    })
+    .unwrap();
+}
@@ -0,0 +1,267 @@
+#![cfg(feature = "worker")]
+
+use quijine::{Error, ErrorKind, EvalFlags, Result, RuntimeScope, WorkerOptions};
+use std::time::{Duration, Instant};
+
+fn loader(name: &str) -> Result<String> {
+    Ok(match name {
+        "echo.js" => "onmessage = (e) => postMessage({ echo: e.data });",
+        "square.js" => "onmessage = (e) => { postMessage(e.data * e.data); close(); };",
+        "hello.js" => "postMessage('hello');",
+        "idle.js" => "onmessage = () => {};",
+        "throw.js" => "throw new Error('oops');",
+        "busy.js" => "postMessage('started'); for (;;) {}",
+        "blocked.js" => "postMessage('started'); Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0);",
+        "shared.js" => "onmessage = (e) => { new Int32Array(e.data)[0] = 42; postMessage('done'); };",
+        "host.js" => "postMessage(hostName());",
+        _ => return Err(Error::with_str(ErrorKind::ReferenceError, "module not found")),
+    }
+    .to_owned())
+}
+
+/// Dispatches events until `done` becomes true.
+fn wait_until(ctx: quijine::Context, done: &str) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !ctx.eval_into::<bool>(done, "<input>", EvalFlags::TYPE_GLOBAL)? {
+        assert!(Instant::now() < deadline, "timed out: {}", done);
+        ctx.wait_worker_events(Duration::from_millis(100))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn echo() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader))?;
+    ctx.eval(
+        r#"
+        globalThis.received = [];
+        const w = new Worker("echo.js");
+        w.onmessage = (e) => received.push(e.data.echo);
+        w.postMessage({ a: [1, 2] });
+        w.postMessage("b");
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    wait_until(ctx, "received.length === 2")?;
+    let json: String = ctx.eval_into("JSON.stringify(received)", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    assert_eq!(r#"[{"a":[1,2]},"b"]"#, json);
+    assert!(ctx.eval_into::<bool>("w instanceof Worker", "<input>", EvalFlags::TYPE_GLOBAL)?);
+    Ok(())
+}
+
+#[test]
+fn close_and_exit() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader))?;
+    ctx.eval(
+        r#"
+        globalThis.result = null;
+        const w = new Worker("square.js");
+        w.onmessage = (e) => { result = e.data; };
+        w.postMessage(7);
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    assert_eq!(1, ctx.worker_count());
+    wait_until(ctx, "result !== null")?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while ctx.worker_count() > 0 {
+        assert!(Instant::now() < deadline);
+        ctx.wait_worker_events(Duration::from_millis(100))?;
+    }
+    assert_eq!(49, ctx.eval_into::<i32>("result", "<input>", EvalFlags::TYPE_GLOBAL)?);
+    Ok(())
+}
+
+#[test]
+fn max_workers() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader).max_workers(2))?;
+    let message: String = ctx.eval_into(
+        r#"
+        new Worker("idle.js");
+        new Worker("idle.js");
+        let message = "";
+        try { new Worker("idle.js"); } catch (e) { message = String(e); }
+        message
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    assert!(message.contains("too many workers"), "{}", message);
+    assert_eq!(2, ctx.worker_count());
+    Ok(())
+}
+
+#[test]
+fn errors() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader))?;
+    ctx.eval(
+        r#"
+        globalThis.errors = [];
+        new Worker("throw.js").onerror = (e) => errors.push(e.message);
+        new Worker("missing.js").onerror = (e) => errors.push(e.message);
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    wait_until(ctx, "errors.length === 2")?;
+    let errors: String = ctx.eval_into("errors.sort().join('\\n')", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    assert!(errors.contains("module not found"), "{}", errors);
+    assert!(errors.contains("oops"), "{}", errors);
+    Ok(())
+}
+
+#[test]
+fn terminate() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader))?;
+    ctx.eval(
+        r#"
+        globalThis.started = false;
+        globalThis.w = new Worker("busy.js");
+        w.onmessage = () => { started = true; };
+        w.onerror = (e) => { throw new Error("unexpected: " + e.message); };
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    wait_until(ctx, "started")?;
+    ctx.eval("w.terminate()", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while ctx.worker_count() > 0 {
+        assert!(Instant::now() < deadline);
+        ctx.wait_worker_events(Duration::from_millis(100))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn terminated_with_runtime() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader))?;
+    ctx.eval(
+        "new Worker('busy.js'); new Worker('idle.js');",
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    assert_eq!(2, ctx.worker_count());
+    // dropping the runtime interrupts and joins the workers
+    drop(ctxs);
+    drop(rts);
+    Ok(())
+}
+
+#[test]
+fn detach_blocked_worker() -> Result<()> {
+    let rts = RuntimeScope::new();
+    rts.get().enable_shared_array_buffer()?;
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader).terminate_timeout(Duration::from_millis(50)))?;
+    ctx.eval(
+        r#"
+        globalThis.started = false;
+        const w = new Worker("blocked.js");
+        w.onmessage = () => { started = true; };
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    wait_until(ctx, "started")?;
+    // the worker never reads the termination request while it is blocked in `Atomics.wait`
+    let start = Instant::now();
+    drop(ctxs);
+    drop(rts);
+    assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+    Ok(())
+}
+
+#[test]
+fn shared_array_buffer() -> Result<()> {
+    let rts = RuntimeScope::new();
+    rts.get().enable_shared_array_buffer()?;
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(loader))?;
+    ctx.eval(
+        r#"
+        globalThis.done = false;
+        globalThis.sab = new SharedArrayBuffer(4);
+        const w = new Worker("shared.js");
+        w.onmessage = () => { done = true; };
+        w.postMessage(sab);
+        "#,
+        "<input>",
+        EvalFlags::TYPE_GLOBAL,
+    )?;
+    wait_until(ctx, "done")?;
+    let n: i32 = ctx.eval_into("new Int32Array(sab)[0]", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    assert_eq!(42, n);
+    Ok(())
+}
+
+#[test]
+fn init_context() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    let options = WorkerOptions::new(loader).init_context(|ctx| {
+        let f = ctx.new_function_from(|_ctx, _this: quijine::Value, _args: ()| Ok("host"), "hostName", 0)?;
+        ctx.global_object()?.set("hostName", f)?;
+        Ok(())
+    });
+    ctx.install_worker(options)?;
+    let (hello, host): (String, String) = {
+        ctx.eval(
+            r#"
+            globalThis.messages = [];
+            new Worker("hello.js").onmessage = (e) => messages.push(e.data);
+            new Worker("host.js").onmessage = (e) => messages.push(e.data);
+            "#,
+            "<input>",
+            EvalFlags::TYPE_GLOBAL,
+        )?;
+        wait_until(ctx, "messages.length === 2")?;
+        (
+            ctx.eval_into("messages.sort()[0]", "<input>", EvalFlags::TYPE_GLOBAL)?,
+            ctx.eval_into("messages[1]", "<input>", EvalFlags::TYPE_GLOBAL)?,
+        )
+    };
+    assert_eq!("hello", hello);
+    assert_eq!("host", host);
+    Ok(())
+}
+
+#[test]
+fn panicking_loader() -> Result<()> {
+    let rts = RuntimeScope::new();
+    let ctxs = rts.new_context_scope();
+    let ctx = ctxs.get();
+    ctx.install_worker(WorkerOptions::new(|_name| panic!("loader")).max_workers(1))?;
+    ctx.eval("new Worker('a.js')", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while ctx.worker_count() > 0 {
+        assert!(Instant::now() < deadline, "the entry of the panicked worker remains");
+        ctx.wait_worker_events(Duration::from_millis(100))?;
+    }
+    // the panicked worker does not count against `max_workers`
+    ctx.eval("new Worker('b.js')", "<input>", EvalFlags::TYPE_GLOBAL)?;
+    Ok(())
+}